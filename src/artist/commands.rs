@@ -1,37 +1,66 @@
+use chrono::Utc;
+use clap::Subcommand;
 use colored::Colorize;
-use error_stack::ResultExt;
+use error_stack::{Report, ResultExt};
 use inflector::Inflector;
 use strum::IntoEnumIterator;
 
-use crate::artist::{ArtistCRUD, ArtistError, ArtistManager, ArtistResult};
+use crate::artist::{Artist, ArtistCRUD, ArtistError, ArtistManager, ArtistResult};
 use crate::dialoguer::Dialoguer;
-use crate::log::DjWizardLog;
-
-#[derive(Debug, Clone, strum_macros::Display, strum_macros::EnumIter)]
+use crate::log::{DjWizardLog, Priority};
+use crate::output::OutputFormat;
+use crate::queue::track_processor::TrackProcessor;
+use crate::soundeo::list_query::SoundeoListQuery;
+use crate::soundeo::search_bar::SoundeoSearchBar;
+use crate::soundeo::track::DownloadTarget;
+use crate::soundeo::track_list::SoundeoTracksList;
+use crate::user::SoundeoUser;
+
+#[derive(Debug, Clone, PartialEq, strum_macros::Display, strum_macros::EnumIter, Subcommand)]
 pub enum ArtistCommands {
+    /// Add one or more favorite artists.
     AddFavoriteArtist,
+    /// List all the favorite artists.
     ViewFavoriteArtists,
+    /// Remove a favorite artist or one of its genres.
     RemoveFavoriteArtist,
+    /// Search the favorite artists by name.
     SearchArtists,
+    /// List the favorite artists of a genre.
     ViewArtistsByGenre,
+    /// Find the favorite artists on Soundeo, choosing among the results when the name differs.
+    ResolveSoundeoArtists,
+    /// Queue the tracks released by the favorite artists since the last sync.
+    Sync,
+    /// Change the priority of the tracks queued by the artist sync.
+    SetSyncPriority,
 }
 
 impl ArtistCommands {
-    pub fn execute() -> ArtistResult<()> {
-        let options = Self::get_options();
-        let selection = Dialoguer::select(
-            "Artist Manager - What would you like to do?".to_string(),
-            options,
-            None,
-        )
-        .change_context(ArtistError)?;
+    pub async fn execute(command: Option<ArtistCommands>) -> ArtistResult<()> {
+        let command_to_run = match command {
+            Some(command) => command,
+            None => {
+                let options = Self::get_options();
+                let selection = Dialoguer::select(
+                    "Artist Manager - What would you like to do?".to_string(),
+                    options,
+                    None,
+                )
+                .change_context(ArtistError)?;
+                Self::get_selection(selection)
+            }
+        };
 
-        match Self::get_selection(selection) {
+        match command_to_run {
             ArtistCommands::AddFavoriteArtist => Self::add_favorite_artist(),
             ArtistCommands::ViewFavoriteArtists => Self::view_favorite_artists(),
             ArtistCommands::RemoveFavoriteArtist => Self::remove_favorite_artist(),
             ArtistCommands::SearchArtists => Self::search_artists(),
             ArtistCommands::ViewArtistsByGenre => Self::view_artists_by_genre(),
+            ArtistCommands::ResolveSoundeoArtists => Self::resolve_soundeo_artists().await,
+            ArtistCommands::Sync => Self::sync_favorite_artists().await,
+            ArtistCommands::SetSyncPriority => Self::set_sync_priority(),
         }
     }
 
@@ -162,6 +191,13 @@ impl ArtistCommands {
     fn view_favorite_artists() -> ArtistResult<()> {
         let manager = DjWizardLog::get_artist_manager().change_context(ArtistError)?;
 
+        let mut artists: Vec<_> = manager.get_all_artists();
+        artists.sort_by(|a, b| a.name.cmp(&b.name));
+
+        if OutputFormat::is_json() {
+            return Self::print_artists_json(None, &artists);
+        }
+
         if manager.favorite_artists.is_empty() {
             println!("{}", "No favorite artists found.".yellow());
             return Ok(());
@@ -170,9 +206,6 @@ impl ArtistCommands {
         println!("{}", "Favorite Artists".cyan().bold());
         println!("{}", "================".cyan());
 
-        let mut artists: Vec<_> = manager.get_all_artists();
-        artists.sort_by(|a, b| a.name.cmp(&b.name));
-
         for (i, artist) in artists.iter().enumerate() {
             println!("{}. {}", i + 1, artist.name.green());
         }
@@ -342,6 +375,10 @@ impl ArtistCommands {
 
         let results = manager.search_artists(&query);
 
+        if OutputFormat::is_json() {
+            return Self::print_artists_json(None, &results);
+        }
+
         if results.is_empty() {
             println!("No artists found matching '{}'", query.yellow());
             return Ok(());
@@ -409,6 +446,10 @@ impl ArtistCommands {
                 .filter(|artist| artist.genres.is_empty())
                 .collect();
 
+            if OutputFormat::is_json() {
+                return Self::print_artists_json(None, &artists_without_genre);
+            }
+
             if artists_without_genre.is_empty() {
                 println!("{}", "No artists without genre found.".yellow());
             } else {
@@ -424,6 +465,10 @@ impl ArtistCommands {
             let selected_genre = &genres[selection];
             let artists = manager.get_artists_by_genre(selected_genre);
 
+            if OutputFormat::is_json() {
+                return Self::print_artists_json(Some(selected_genre), &artists);
+            }
+
             println!("Artists in genre '{}':", selected_genre.cyan().bold());
             println!("{}", "=".repeat(selected_genre.len() + 18).cyan());
 
@@ -444,4 +489,228 @@ impl ArtistCommands {
 
         Ok(())
     }
+
+    async fn logged_in_user() -> ArtistResult<SoundeoUser> {
+        let mut soundeo_user = SoundeoUser::new().change_context(ArtistError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(ArtistError)?;
+        Ok(soundeo_user)
+    }
+
+    /// Searches the artist on Soundeo, taking the result with the same name or,
+    /// when `choose` is set, the one selected by the user
+    async fn resolve_artist(
+        artist: &Artist,
+        soundeo_user: &SoundeoUser,
+        choose: bool,
+    ) -> ArtistResult<Option<u32>> {
+        let results = SoundeoSearchBar::Artists
+            .search_term(artist.name.clone(), soundeo_user)
+            .await
+            .change_context(ArtistError)?;
+        if let Some(soundeo_id) = artist.find_soundeo_id(&results) {
+            return Ok(Some(soundeo_id));
+        }
+        if !choose || results.is_empty() {
+            return Ok(None);
+        }
+
+        let mut options: Vec<String> = results.iter().map(|result| result.label.clone()).collect();
+        options.push("Skip".to_string());
+        let selection = Dialoguer::select(
+            format!("Which Soundeo artist is '{}'?", artist.name.green()),
+            options,
+            None,
+        )
+        .change_context(ArtistError)?;
+        Ok(results
+            .get(selection)
+            .and_then(|result| result.value.parse::<u32>().ok()))
+    }
+
+    async fn resolve_soundeo_artists() -> ArtistResult<()> {
+        let mut manager = DjWizardLog::get_artist_manager().change_context(ArtistError)?;
+        let mut unresolved_artists: Vec<Artist> = manager
+            .get_all_artists()
+            .into_iter()
+            .filter(|artist| artist.soundeo_id.is_none())
+            .cloned()
+            .collect();
+        if unresolved_artists.is_empty() {
+            println!("{}", "Every favorite artist is already on Soundeo.".green());
+            return Ok(());
+        }
+        unresolved_artists.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let soundeo_user = Self::logged_in_user().await?;
+        for artist in unresolved_artists {
+            match Self::resolve_artist(&artist, &soundeo_user, true).await? {
+                Some(soundeo_id) => {
+                    manager.set_soundeo_id(&artist.name, soundeo_id)?;
+                    DjWizardLog::save_artist_manager(manager.clone())
+                        .change_context(ArtistError)?;
+                    println!(
+                        "{} found on Soundeo: {}",
+                        artist.name.green(),
+                        format!("ID {}", soundeo_id).yellow()
+                    );
+                }
+                None => println!("{} not found on Soundeo", artist.name.yellow()),
+            }
+        }
+        Ok(())
+    }
+
+    async fn sync_favorite_artists() -> ArtistResult<()> {
+        let manager = DjWizardLog::get_artist_manager().change_context(ArtistError)?;
+        if manager.favorite_artists.is_empty() {
+            println!("{}", "No favorite artists found.".yellow());
+            return Ok(());
+        }
+        let mut artists: Vec<Artist> = manager.get_all_artists().into_iter().cloned().collect();
+        artists.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let soundeo_user = Self::logged_in_user().await?;
+        let today = Utc::now().format("%Y-%m-%d").to_string();
+
+        let mut synced_artists = Vec::new();
+        let mut unresolved_artists = Vec::new();
+        let mut failed_artists = Vec::new();
+        for artist in artists {
+            println!("\nSyncing {}", artist.name.cyan());
+            match Self::sync_artist(&artist, &soundeo_user, manager.sync_priority, &today).await {
+                Ok(Some((added, skipped))) => synced_artists.push((artist.name, added, skipped)),
+                Ok(None) => {
+                    println!("{} not found on Soundeo", artist.name.yellow());
+                    unresolved_artists.push(artist.name);
+                }
+                Err(error) => {
+                    println!(
+                        "{}",
+                        format!("Could not sync {}: {:?}", artist.name, error).red()
+                    );
+                    failed_artists.push(artist.name);
+                }
+            }
+        }
+
+        println!("\n{}", "Artist sync summary".green());
+        for (name, added, skipped) in synced_artists {
+            println!(
+                "{}: {} queued, {} skipped",
+                name.cyan(),
+                added.to_string().green(),
+                skipped.to_string().yellow()
+            );
+        }
+        if !unresolved_artists.is_empty() {
+            println!(
+                "Not found on Soundeo, run resolve-soundeo-artists to choose them: {}",
+                unresolved_artists.join(", ").yellow()
+            );
+        }
+        for name in failed_artists.iter() {
+            println!("{}: {}", name.cyan(), "failed".red());
+        }
+
+        if !failed_artists.is_empty() {
+            return Err(Report::new(ArtistError)
+                .attach_printable(format!("Could not sync {}", failed_artists.join(", "))));
+        }
+        Ok(())
+    }
+
+    /// Queues the tracks of the artist released since its last sync, None if the artist
+    /// is not on Soundeo
+    async fn sync_artist(
+        artist: &Artist,
+        soundeo_user: &SoundeoUser,
+        priority: Priority,
+        today: &str,
+    ) -> ArtistResult<Option<(usize, usize)>> {
+        let soundeo_id = match artist.soundeo_id {
+            Some(soundeo_id) => soundeo_id,
+            None => {
+                let Some(soundeo_id) = Self::resolve_artist(artist, soundeo_user, false).await?
+                else {
+                    return Ok(None);
+                };
+                let mut manager = DjWizardLog::get_artist_manager().change_context(ArtistError)?;
+                manager.set_soundeo_id(&artist.name, soundeo_id)?;
+                DjWizardLog::save_artist_manager(manager).change_context(ArtistError)?;
+                soundeo_id
+            }
+        };
+
+        let url = SoundeoListQuery::new()
+            .artist(soundeo_id)
+            .date_range(&artist.sync_start_date(), today)
+            .build();
+        let mut track_list = SoundeoTracksList::new(url).change_context(ArtistError)?;
+        track_list
+            .get_tracks(soundeo_user)
+            .await
+            .change_context(ArtistError)?;
+        let track_ids: Vec<String> = track_list
+            .tracks
+            .into_iter()
+            .map(|listed_track| listed_track.id)
+            .collect();
+
+        let (added, skipped) = if track_ids.is_empty() {
+            (0, 0)
+        } else {
+            TrackProcessor::process_tracks_to_queue(
+                &track_ids,
+                soundeo_user,
+                priority,
+                &DownloadTarget::default(),
+                Some(format!("artist:{}", artist.name)),
+                false,
+                &format!("from {}", artist.name),
+            )
+            .await
+            .change_context(ArtistError)?
+        };
+
+        // Reload the artists, the sync could have taken a while
+        let mut manager = DjWizardLog::get_artist_manager().change_context(ArtistError)?;
+        manager.update_last_checked(&artist.name, today)?;
+        DjWizardLog::save_artist_manager(manager).change_context(ArtistError)?;
+        Ok(Some((added, skipped)))
+    }
+
+    fn set_sync_priority() -> ArtistResult<()> {
+        let mut manager = DjWizardLog::get_artist_manager().change_context(ArtistError)?;
+        let priorities = [Priority::High, Priority::Normal, Priority::Low];
+        let priority_options = vec!["High (download first)", "Normal", "Low (download last)"];
+        let selection = Dialoguer::select(
+            "Choose a priority for the tracks queued by the artist sync".to_string(),
+            priority_options,
+            priorities
+                .iter()
+                .position(|priority| *priority == manager.sync_priority),
+        )
+        .change_context(ArtistError)?;
+        manager.sync_priority = priorities[selection];
+        DjWizardLog::save_artist_manager(manager).change_context(ArtistError)?;
+        println!(
+            "The artist sync now queues the tracks with {} priority",
+            format!("{:?}", priorities[selection]).green()
+        );
+        Ok(())
+    }
+
+    fn print_artists_json(genre: Option<&String>, artists: &[&Artist]) -> ArtistResult<()> {
+        let mut artists = artists.to_vec();
+        artists.sort_by(|a, b| a.name.cmp(&b.name));
+        OutputFormat::print_json(&serde_json::json!({
+            "genre": genre,
+            "total": artists.len(),
+            "artists": artists,
+        }))
+        .change_context(ArtistError)
+    }
 }
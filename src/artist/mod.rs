@@ -1,13 +1,18 @@
 use std::{fmt, collections::HashMap};
 
-use chrono::Utc;
+use chrono::{Duration, Utc};
 use error_stack::{IntoReport, ResultExt};
 use serde::{Deserialize, Serialize};
 
-use crate::log::DjWizardLogResult;
+use crate::log::{DjWizardLogResult, Priority};
+use crate::soundeo::search_bar::SoundeoSearchBarResult;
 
 pub mod commands;
 
+/// Days of releases queued by the first sync of an artist, so following an artist doesn't
+/// queue their whole catalogue
+pub const FIRST_SYNC_DAYS: i64 = 30;
+
 #[derive(Debug)]
 pub struct ArtistError;
 
@@ -27,17 +32,48 @@ pub struct Artist {
     pub genres: Vec<String>, // Genres this artist is associated with
     pub created_at: String,
     pub last_updated: String,
+    /// Id of the artist on Soundeo, None until it is resolved
+    #[serde(default)]
+    pub soundeo_id: Option<u32>,
+    /// Day of the last sync, formatted as %Y-%m-%d, None until the first one
+    #[serde(default)]
+    pub last_checked_date: Option<String>,
+}
+
+impl Artist {
+    /// Id of the Soundeo search result named like the artist
+    pub fn find_soundeo_id(&self, results: &[SoundeoSearchBarResult]) -> Option<u32> {
+        let normalized_name = ArtistManager::normalize_name(&self.name);
+        results
+            .iter()
+            .find(|result| ArtistManager::normalize_name(&result.label) == normalized_name)
+            .and_then(|result| result.value.parse::<u32>().ok())
+    }
+
+    /// Tracks released since this day are queued by the next sync,
+    /// the last `FIRST_SYNC_DAYS` days for an artist never synced
+    pub fn sync_start_date(&self) -> String {
+        self.last_checked_date.clone().unwrap_or_else(|| {
+            (Utc::now() - Duration::days(FIRST_SYNC_DAYS))
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ArtistManager {
     pub favorite_artists: HashMap<String, Artist>, // Key: artist name (normalized)
+    /// Priority of the tracks queued by the artist sync
+    #[serde(default = "ArtistManager::default_sync_priority")]
+    pub sync_priority: Priority,
 }
 
 impl Default for ArtistManager {
     fn default() -> Self {
         Self {
             favorite_artists: HashMap::new(),
+            sync_priority: Self::default_sync_priority(),
         }
     }
 }
@@ -47,6 +83,10 @@ impl ArtistManager {
         Self::default()
     }
 
+    fn default_sync_priority() -> Priority {
+        Priority::Normal
+    }
+
     pub fn add_artist(&mut self, artist_name: &str, genre: Option<&str>) -> ArtistResult<bool> {
         let normalized_name = Self::normalize_name(artist_name);
         let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
@@ -79,6 +119,8 @@ impl ArtistManager {
                     genres,
                     created_at: now.clone(),
                     last_updated: now,
+                    soundeo_id: None,
+                    last_checked_date: None,
                 };
 
                 self.favorite_artists.insert(normalized_name, artist);
@@ -117,6 +159,31 @@ impl ArtistManager {
         }
     }
 
+    pub fn set_soundeo_id(&mut self, artist_name: &str, soundeo_id: u32) -> ArtistResult<()> {
+        let normalized_name = Self::normalize_name(artist_name);
+        let artist = self
+            .favorite_artists
+            .get_mut(&normalized_name)
+            .ok_or(ArtistError)
+            .into_report()
+            .attach_printable_lazy(|| format!("{} is not a favorite artist", artist_name))?;
+        artist.soundeo_id = Some(soundeo_id);
+        artist.last_updated = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        Ok(())
+    }
+
+    pub fn update_last_checked(&mut self, artist_name: &str, date: &str) -> ArtistResult<()> {
+        let normalized_name = Self::normalize_name(artist_name);
+        let artist = self
+            .favorite_artists
+            .get_mut(&normalized_name)
+            .ok_or(ArtistError)
+            .into_report()
+            .attach_printable_lazy(|| format!("{} is not a favorite artist", artist_name))?;
+        artist.last_checked_date = Some(date.to_string());
+        Ok(())
+    }
+
     pub fn get_artist(&self, artist_name: &str) -> Option<&Artist> {
         let normalized_name = Self::normalize_name(artist_name);
         self.favorite_artists.get(&normalized_name)
@@ -174,4 +241,54 @@ impl ArtistManager {
 pub trait ArtistCRUD {
     fn get_artist_manager() -> DjWizardLogResult<ArtistManager>;
     fn save_artist_manager(manager: ArtistManager) -> DjWizardLogResult<()>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_result(label: &str, value: &str) -> SoundeoSearchBarResult {
+        SoundeoSearchBarResult {
+            label: label.to_string(),
+            category: "Artists".to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_find_soundeo_id_by_name() {
+        let mut manager = ArtistManager::new();
+        manager.add_artist("Sub Focus", None).unwrap();
+        let artist = manager.get_artist("sub focus").unwrap();
+
+        let results = vec![
+            search_result("Sub Focus & Wilkinson", "321"),
+            search_result(" sub focus", "123"),
+        ];
+
+        assert_eq!(artist.find_soundeo_id(&results), Some(123));
+        assert_eq!(artist.find_soundeo_id(&results[..1]), None);
+    }
+
+    #[test]
+    fn test_sync_start_date() {
+        let mut manager = ArtistManager::new();
+        manager.add_artist("Sub Focus", None).unwrap();
+        let first_sync_day = (Utc::now() - Duration::days(FIRST_SYNC_DAYS))
+            .format("%Y-%m-%d")
+            .to_string();
+
+        assert_eq!(
+            manager.get_artist("Sub Focus").unwrap().sync_start_date(),
+            first_sync_day
+        );
+
+        manager
+            .update_last_checked("Sub Focus", "2025-09-18")
+            .unwrap();
+        assert_eq!(
+            manager.get_artist("Sub Focus").unwrap().sync_start_date(),
+            "2025-09-18"
+        );
+    }
+}
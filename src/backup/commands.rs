@@ -1,10 +1,11 @@
+use crate::oauth::{OAuthProvider, TokenManager};
 use crate::user::User;
 use colored::Colorize;
 use error_stack::{IntoReport, Report, ResultExt};
 use google_drive3::api::File;
 use google_drive3::hyper::client::HttpConnector;
 use google_drive3::hyper_rustls::HttpsConnector;
-use google_drive3::{hyper, hyper_rustls, oauth2, DriveHub};
+use google_drive3::{hyper, hyper_rustls, DriveHub};
 use std::default::Default;
 use std::fs;
 use std::io::Cursor;
@@ -20,7 +21,7 @@ impl BackupCommands {
 
         // Create the hub with proper authentication
         println!("{}", "Connecting to Google Drive...".cyan());
-        let hub = Self::create_authenticated_hub().await?;
+        let hub = Self::create_authenticated_hub(&mut user_config).await?;
         println!("{}", "Successfully connected to Google Drive.".green());
 
         match Self::upload_log_to_drive(&hub, &user_config)
@@ -52,49 +53,16 @@ impl BackupCommands {
         Ok(())
     }
 
-    async fn create_authenticated_hub() -> BackupResult<DriveHub<HttpsConnector<HttpConnector>>> {
-        let client_id = crate::config::AppConfig::GOOGLE_CLIENT_ID;
-        let client_secret =
-            crate::config::AppConfig::google_client_secret().unwrap_or_else(|| String::new()); // Empty for PKCE
-
-        let secret = oauth2::ApplicationSecret {
-            client_id: client_id.to_string(),
-            client_secret,
-            token_uri: "https://oauth2.googleapis.com/token".to_string(),
-            auth_uri: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
-            redirect_uris: vec!["http://localhost:8889/callback".to_string()],
-            project_id: None,
-            client_email: None,
-            client_x509_cert_url: None,
-            auth_provider_x509_cert_url: None,
-        };
-
-        // Define the path for the token cache file within the app's config directory.
-        let token_cache_path = User::get_config_file_path()
-            .change_context(BackupError)?
-            .replace("config.json", "google_token_cache.json");
-
-        let auth = oauth2::InstalledFlowAuthenticator::builder(
-            secret,
-            oauth2::InstalledFlowReturnMethod::HTTPRedirect,
-        )
-        .persist_tokens_to_disk(&token_cache_path)
-        .force_account_selection(true)
-        .build()
-        .await
-        .into_report()
-        .change_context(BackupError)?;
-
-        // The first time .token() is called, it will either use the cached token,
-        // refresh it, or start the full web-based authentication flow.
-        let scopes = &["https://www.googleapis.com/auth/drive.file"];
-        match auth.token(scopes).await {
-            Err(e) => {
-                return Err(Report::new(BackupError)
-                    .attach_printable(format!("Failed to get Google authentication token: {}", e)))
-            }
-            Ok(_) => println!("{}", "Google authentication successful.".green()),
+    async fn create_authenticated_hub(
+        user_config: &mut User,
+    ) -> BackupResult<DriveHub<HttpsConnector<HttpConnector>>> {
+        let mut tokens = TokenManager::new(OAuthProvider::GoogleDrive, user_config);
+        if !tokens.has_required_scopes() {
+            tokens.login().await.change_context(BackupError)?;
         }
+        // The backup takes far less than the token lifetime, a token refreshed now is enough
+        let access_token = tokens.access_token().await.change_context(BackupError)?;
+        println!("{}", "Google authentication successful.".green());
 
         let http_client = hyper::Client::builder().build(
             hyper_rustls::HttpsConnectorBuilder::new()
@@ -106,7 +74,7 @@ impl BackupCommands {
                 .build(),
         );
 
-        let hub = DriveHub::new(http_client, auth);
+        let hub = DriveHub::new(http_client, access_token);
 
         Ok(hub)
     }
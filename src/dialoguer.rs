@@ -135,6 +135,21 @@ impl Dialoguer {
         Ok(dialog)
     }
 
+    /// Input that can be left empty, prefilled with the current value
+    pub fn optional_input(prompt_text: String, initial_text: String) -> DialoguerResult<String> {
+        let colorful_theme = &ColorfulTheme::default();
+        let mut input = Input::with_theme(colorful_theme);
+        let dialog: String = input
+            .with_prompt(&prompt_text)
+            .with_initial_text(initial_text)
+            .allow_empty(true)
+            .interact_text()
+            .into_report()
+            .change_context(DialoguerError)?;
+
+        Ok(dialog.trim().to_string())
+    }
+
     pub fn password(prompt_text: String) -> Result<String, DialoguerError> {
         let colorful_theme = &ColorfulTheme::default();
         let mut input = Password::with_theme(colorful_theme);
@@ -0,0 +1,195 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Subcommand;
+use colored::Colorize;
+use error_stack::{IntoReport, ResultExt};
+use inflector::Inflector;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::artist::ArtistCRUD;
+use crate::dialoguer::Dialoguer;
+use crate::export::{
+    rekordbox, serato, traktor, ExportError, ExportLibrary, ExportResult, VolumePath,
+};
+use crate::log::DjWizardLog;
+use crate::soundeo::track::SoundeoTrack;
+use crate::user::User;
+
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+    Subcommand,
+    PartialEq,
+)]
+pub enum ExportCommands {
+    /// Write a rekordbox.xml collection with the downloaded tracks and the Spotify playlists.
+    Rekordbox {
+        /// Path of the xml file, defaults to rekordbox.xml in the download folder
+        #[clap(long)]
+        file: Option<String>,
+    },
+    /// Write a Traktor collection.nml with the downloaded tracks and the playlists.
+    Traktor {
+        /// Path of the nml file, defaults to collection.nml in the download folder
+        #[clap(long)]
+        file: Option<String>,
+    },
+    /// Write a Serato crate for each playlist.
+    Serato {
+        /// Path of the _Serato_ folder, defaults to the one of the drive holding the downloads
+        #[clap(long)]
+        folder: Option<String>,
+    },
+}
+
+impl ExportCommands {
+    pub fn execute(command: Option<ExportCommands>) -> ExportResult<()> {
+        let command = match command {
+            Some(command) => command,
+            None => {
+                let options = Self::get_options();
+                let selection =
+                    Dialoguer::select("What you want to do?".to_string(), options, None)
+                        .change_context(ExportError)?;
+                Self::get_selection(selection)
+            }
+        };
+        match command {
+            ExportCommands::Rekordbox { file } => Self::export_rekordbox(file),
+            ExportCommands::Traktor { file } => Self::export_traktor(file),
+            ExportCommands::Serato { folder } => Self::export_serato(folder),
+        }
+    }
+
+    fn get_options() -> Vec<String> {
+        Self::iter()
+            .map(|element| element.to_string().to_sentence_case())
+            .collect::<Vec<_>>()
+    }
+
+    fn get_selection(selection: usize) -> Self {
+        let options = Self::iter().collect::<Vec<_>>();
+        options[selection].clone()
+    }
+
+    fn export_rekordbox(file: Option<String>) -> ExportResult<()> {
+        let (library, download_dir) = Self::build_library()?;
+        let file_path = file
+            .map(PathBuf::from)
+            .unwrap_or_else(|| download_dir.join("rekordbox.xml"));
+        Self::write_export(&file_path, rekordbox::render(&library))?;
+        Self::print_summary(&library, &file_path);
+        println!(
+            "Import it in Rekordbox from {}",
+            "Preferences > Advanced > Database > rekordbox xml".cyan()
+        );
+        Ok(())
+    }
+
+    fn export_traktor(file: Option<String>) -> ExportResult<()> {
+        let (library, download_dir) = Self::build_library()?;
+        let file_path = file
+            .map(PathBuf::from)
+            .unwrap_or_else(|| download_dir.join("collection.nml"));
+        Self::write_export(&file_path, traktor::render(&library))?;
+        Self::print_summary(&library, &file_path);
+        println!(
+            "Import it in Traktor with {} on the collection, to keep your own collection.nml untouched",
+            "Import Another Collection".cyan()
+        );
+        Ok(())
+    }
+
+    fn export_serato(folder: Option<String>) -> ExportResult<()> {
+        let (library, download_dir) = Self::build_library()?;
+        let serato_folder = match folder {
+            Some(folder) => PathBuf::from(folder),
+            None => serato::default_serato_folder(&download_dir)
+                .ok_or(ExportError)
+                .into_report()
+                .attach_printable("Failed to find the _Serato_ folder, pass it with --folder")?,
+        };
+        let volume_root = VolumePath::new(&serato_folder).volume_root;
+        let mut skipped_tracks = 0;
+        let file_paths = serato::crate_file_paths(&serato_folder, &library.playlists);
+        for (playlist, file_path) in library.playlists.iter().zip(file_paths) {
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent)
+                    .into_report()
+                    .attach_printable(format!(
+                        "Failed to create the crates folder at {}",
+                        parent.display()
+                    ))
+                    .change_context(ExportError)?;
+            }
+            let (content, written) = serato::render_crate(&library, playlist, &volume_root);
+            skipped_tracks += playlist.track_positions().len() - written;
+            Self::write_export(&file_path, content)?;
+        }
+        Self::print_summary(&library, &serato_folder.join("Subcrates"));
+        if skipped_tracks > 0 {
+            println!(
+                "{}",
+                format!(
+                    "{} playlist entries were skipped because their file is on another drive than {}",
+                    skipped_tracks,
+                    serato_folder.display()
+                )
+                .yellow()
+            );
+        }
+        Ok(())
+    }
+
+    fn build_library() -> ExportResult<(ExportLibrary, PathBuf)> {
+        let mut user_config = User::new();
+        user_config.read_config_file().change_context(ExportError)?;
+        let download_dir = PathBuf::from(&user_config.download_path);
+        println!("Scanning the download folder...");
+        let mut local_files = SoundeoTrack::index_local_files(&download_dir);
+        let soundeo_log = DjWizardLog::get_soundeo().change_context(ExportError)?;
+        soundeo_log.add_owned_files(&mut local_files);
+        let spotify_log = DjWizardLog::get_spotify().change_context(ExportError)?;
+        let artist_manager = DjWizardLog::get_artist_manager().change_context(ExportError)?;
+        let mut library =
+            ExportLibrary::build(&soundeo_log.tracks_info, &spotify_log, &local_files);
+        library.add_genre_playlists();
+        library.add_artist_playlists(&artist_manager);
+        Ok((library, download_dir))
+    }
+
+    fn write_export(file_path: &Path, content: impl AsRef<[u8]>) -> ExportResult<()> {
+        fs::write(file_path, content)
+            .into_report()
+            .attach_printable(format!(
+                "Failed to write the export file at {}",
+                file_path.display()
+            ))
+            .change_context(ExportError)
+    }
+
+    fn print_summary(library: &ExportLibrary, file_path: &Path) {
+        println!(
+            "Exported {} tracks and {} playlists to {}",
+            library.tracks.len().to_string().green(),
+            library.playlists.len().to_string().green(),
+            file_path.display().to_string().cyan()
+        );
+        if library.missing_files > 0 {
+            println!(
+                "{}",
+                format!(
+                    "{} downloaded tracks were skipped because their file is not in the download folder",
+                    library.missing_files
+                )
+                .yellow()
+            );
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::export::{ExportLibrary, LogicalPlaylist, PlaylistEntry};
+use crate::spotify::organizer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum_macros::Display, strum_macros::EnumIter)]
+pub enum M3u8PathMode {
+    /// Paths relative to the folder of the .m3u8 file, portable with the whole folder
+    Relative,
+    Absolute,
+}
+
+/// Renders the extended M3U content, with the missing tracks as comments in their position
+pub fn render(
+    library: &ExportLibrary,
+    playlist: &LogicalPlaylist,
+    playlist_folder: &Path,
+    path_mode: M3u8PathMode,
+) -> String {
+    let mut lines = vec![
+        "#EXTM3U".to_string(),
+        format!("#PLAYLIST:{}", playlist.name),
+    ];
+    for entry in &playlist.entries {
+        match entry {
+            PlaylistEntry::Track(position) => {
+                let track = &library.tracks[*position];
+                let duration = track
+                    .duration_secs
+                    .map(|secs| secs.to_string())
+                    .unwrap_or_else(|| "-1".to_string());
+                lines.push(format!("#EXTINF:{},{}", duration, track.label()));
+                let path = match path_mode {
+                    M3u8PathMode::Relative => relative_path(playlist_folder, &track.path),
+                    M3u8PathMode::Absolute => track.path.clone(),
+                };
+                lines.push(path.display().to_string());
+            }
+            PlaylistEntry::Missing { label, reason } => {
+                lines.push(format!("# Missing ({}): {}", reason.description(), label));
+            }
+        }
+    }
+    lines.join("\n") + "\n"
+}
+
+/// Name of the .m3u8 file of every playlist by id, with the id appended to the names shared
+/// by several playlists so none overwrites another
+pub fn file_names<'a>(
+    playlists: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> HashMap<String, String> {
+    let sanitized: Vec<(&str, String)> = playlists
+        .into_iter()
+        .map(|(id, name)| (id, organizer::sanitize_name(name, id)))
+        .collect();
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for (_, name) in &sanitized {
+        *name_counts.entry(name.as_str()).or_default() += 1;
+    }
+    sanitized
+        .iter()
+        .map(|(id, name)| {
+            let file_name = if name_counts[name.as_str()] > 1 {
+                format!("{} ({}).m3u8", name, id)
+            } else {
+                format!("{}.m3u8", name)
+            };
+            (id.to_string(), file_name)
+        })
+        .collect()
+}
+
+/// Path to `target` from the `from_folder`, both expected to be absolute
+fn relative_path(from_folder: &Path, target: &Path) -> PathBuf {
+    let from: Vec<Component> = from_folder.components().collect();
+    let to: Vec<Component> = target.components().collect();
+    let common = from
+        .iter()
+        .zip(to.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..from.len() {
+        relative.push("..");
+    }
+    for component in &to[common..] {
+        relative.push(component);
+    }
+    relative
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{ExportTrack, MissingReason, PlaylistGroup};
+
+    #[test]
+    fn test_render_relative_and_absolute_paths() {
+        let library = ExportLibrary {
+            tracks: vec![ExportTrack {
+                soundeo_id: "1".to_string(),
+                artist: "Artist".to_string(),
+                name: "Track".to_string(),
+                album: String::new(),
+                genre: String::new(),
+                label: String::new(),
+                bpm: None,
+                key: None,
+                year: None,
+                duration_secs: Some(242),
+                size: None,
+                path: PathBuf::from("/music/Artist - Track.AIFF"),
+            }],
+            playlists: vec![],
+            missing_files: 0,
+        };
+        let playlist = LogicalPlaylist {
+            name: "Liquid".to_string(),
+            group: PlaylistGroup::Spotify,
+            spotify_playlist_id: None,
+            entries: vec![
+                PlaylistEntry::Track(0),
+                PlaylistEntry::Missing {
+                    label: "Artist - Other".to_string(),
+                    reason: MissingReason::NotPaired,
+                },
+            ],
+        };
+
+        let relative = render(
+            &library,
+            &playlist,
+            Path::new("/music/m3u8"),
+            M3u8PathMode::Relative,
+        );
+        assert_eq!(
+            relative,
+            "#EXTM3U\n#PLAYLIST:Liquid\n#EXTINF:242,Artist - Track\n../Artist - Track.AIFF\n# Missing (not paired): Artist - Other\n"
+        );
+        let absolute = render(
+            &library,
+            &playlist,
+            Path::new("/music/m3u8"),
+            M3u8PathMode::Absolute,
+        );
+        assert!(absolute.contains("\n/music/Artist - Track.AIFF\n"));
+    }
+
+    #[test]
+    fn test_file_names_of_playlists_with_the_same_name() {
+        let file_names = file_names(vec![
+            ("1", "Liquid"),
+            ("2", "Liquid"),
+            ("3", "Rollers"),
+            ("4", "???"),
+        ]);
+
+        assert_eq!(file_names["1"], "Liquid (1).m3u8");
+        assert_eq!(file_names["2"], "Liquid (2).m3u8");
+        assert_eq!(file_names["3"], "Rollers.m3u8");
+        assert_eq!(file_names["4"], "4.m3u8");
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+
+use crate::artist::ArtistManager;
+use crate::normalize::contains_words;
+use crate::soundeo::track::SoundeoTrack;
+use crate::spotify::Spotify;
+
+pub mod commands;
+pub mod m3u8;
+pub mod rekordbox;
+pub mod serato;
+pub mod traktor;
+
+#[derive(Debug)]
+pub struct ExportError;
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Export error")
+    }
+}
+
+impl std::error::Error for ExportError {}
+
+pub type ExportResult<T> = error_stack::Result<T, ExportError>;
+
+/// A downloaded track with the metadata DJ software reads from a collection file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportTrack {
+    pub soundeo_id: String,
+    pub artist: String,
+    pub name: String,
+    pub album: String,
+    pub genre: String,
+    pub label: String,
+    pub bpm: Option<f64>,
+    pub key: Option<String>,
+    pub year: Option<u32>,
+    pub duration_secs: Option<u64>,
+    pub size: Option<u64>,
+    pub path: PathBuf,
+}
+
+impl ExportTrack {
+    fn new(
+        soundeo_track: &SoundeoTrack,
+        path: PathBuf,
+        duration_secs: Option<u64>,
+        size: Option<u64>,
+    ) -> Self {
+        // Soundeo titles are "Artist - Track (Mix)"
+        let (artist, name) = match soundeo_track.title.split_once(" - ") {
+            Some((artist, name)) => (artist.trim().to_string(), name.trim().to_string()),
+            None => (String::new(), soundeo_track.title.clone()),
+        };
+        Self {
+            soundeo_id: soundeo_track.id.clone(),
+            artist,
+            name,
+            album: soundeo_track.release.clone(),
+            genre: soundeo_track.genre.clone(),
+            label: soundeo_track.label.clone(),
+            bpm: soundeo_track.bpm.trim().parse::<f64>().ok(),
+            key: soundeo_track
+                .key
+                .clone()
+                .filter(|key| !key.trim().is_empty()),
+            year: soundeo_track
+                .date
+                .get(..4)
+                .and_then(|year| year.parse::<u32>().ok()),
+            duration_secs,
+            size,
+            path,
+        }
+    }
+
+    /// "Artist - Track (Mix)", as shown by the players that only read a label
+    pub fn label(&self) -> String {
+        if self.artist.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} - {}", self.artist, self.name)
+        }
+    }
+}
+
+/// Where a logical playlist comes from, exported as a folder of playlists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PlaylistGroup {
+    Spotify,
+    Genre,
+    Artist,
+}
+
+impl PlaylistGroup {
+    pub fn folder_name(&self) -> &'static str {
+        match self {
+            PlaylistGroup::Spotify => "Spotify",
+            PlaylistGroup::Genre => "Genres",
+            PlaylistGroup::Artist => "Artists",
+        }
+    }
+}
+
+/// Why a track of a playlist has no file to point at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingReason {
+    NotPaired,
+    NoMatch,
+    NotDownloaded,
+    FileNotFound,
+}
+
+impl MissingReason {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MissingReason::NotPaired => "not paired",
+            MissingReason::NoMatch => "no match on Soundeo",
+            MissingReason::NotDownloaded => "not downloaded",
+            MissingReason::FileNotFound => "file not found",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaylistEntry {
+    /// Position of the track in `ExportLibrary::tracks`
+    Track(usize),
+    Missing {
+        label: String,
+        reason: MissingReason,
+    },
+}
+
+/// A playlist independent of the format it is exported to
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogicalPlaylist {
+    pub name: String,
+    pub group: PlaylistGroup,
+    /// Id of the Spotify playlist it comes from, names are not unique on Spotify
+    pub spotify_playlist_id: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+impl LogicalPlaylist {
+    /// Positions of the tracks with a file, the only entries collection formats can hold
+    pub fn track_positions(&self) -> Vec<usize> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match entry {
+                PlaylistEntry::Track(position) => Some(*position),
+                PlaylistEntry::Missing { .. } => None,
+            })
+            .collect()
+    }
+}
+
+/// Every downloaded track found on disk, and the playlists pointing at them
+#[derive(Debug, Clone, Default)]
+pub struct ExportLibrary {
+    pub tracks: Vec<ExportTrack>,
+    pub playlists: Vec<LogicalPlaylist>,
+    /// Tracks marked as downloaded whose file is not in the download folder
+    pub missing_files: usize,
+}
+
+impl ExportLibrary {
+    /// Builds the collection and one playlist per stored Spotify playlist.
+    /// `local_files` maps the file names found in the download folder to their path
+    pub fn build(
+        tracks_info: &HashMap<String, SoundeoTrack>,
+        spotify: &Spotify,
+        local_files: &HashMap<String, PathBuf>,
+    ) -> Self {
+        // Soundeo doesn't report durations, the paired Spotify track does
+        let mut durations: HashMap<&String, u64> = HashMap::new();
+        for spotify_track in spotify.playlists.values().flat_map(|p| p.tracks.values()) {
+            if let (Some(Some(soundeo_id)), Some(duration_ms)) = (
+                spotify
+                    .soundeo_track_ids
+                    .get(&spotify_track.spotify_track_id),
+                spotify_track.duration_ms,
+            ) {
+                durations.insert(soundeo_id, (duration_ms + 500) / 1000);
+            }
+        }
+
+        let mut soundeo_tracks: Vec<&SoundeoTrack> = tracks_info.values().collect();
+        soundeo_tracks.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.id.cmp(&b.id)));
+
+        let mut library = Self::default();
+        let mut positions: HashMap<&String, usize> = HashMap::new();
+        for soundeo_track in soundeo_tracks {
+            match soundeo_track.find_local_file(local_files) {
+                Some((_, path)) => {
+                    let size = path.metadata().ok().map(|metadata| metadata.len());
+                    let duration_secs = durations.get(&soundeo_track.id).copied();
+                    positions.insert(&soundeo_track.id, library.tracks.len());
+                    library.tracks.push(ExportTrack::new(
+                        soundeo_track,
+                        path.clone(),
+                        duration_secs,
+                        size,
+                    ));
+                }
+                None if soundeo_track.already_downloaded => library.missing_files += 1,
+                None => {}
+            }
+        }
+
+        let mut playlists: Vec<_> = spotify.playlists.values().collect();
+        playlists.sort_by_key(|playlist| playlist.name.to_lowercase());
+        library.playlists = playlists
+            .into_iter()
+            .map(|playlist| LogicalPlaylist {
+                name: playlist.name.clone(),
+                group: PlaylistGroup::Spotify,
+                spotify_playlist_id: Some(playlist.spotify_playlist_id.clone()),
+                entries: playlist
+                    .ordered_tracks()
+                    .into_iter()
+                    .map(|spotify_track| {
+                        let label = format!("{} - {}", spotify_track.artists, spotify_track.title);
+                        let reason = match spotify
+                            .soundeo_track_ids
+                            .get(&spotify_track.spotify_track_id)
+                        {
+                            None => MissingReason::NotPaired,
+                            Some(None) => MissingReason::NoMatch,
+                            Some(Some(soundeo_id)) => {
+                                if let Some(position) = positions.get(soundeo_id) {
+                                    return PlaylistEntry::Track(*position);
+                                }
+                                match tracks_info.get(soundeo_id) {
+                                    Some(track) if track.already_downloaded => {
+                                        MissingReason::FileNotFound
+                                    }
+                                    _ => MissingReason::NotDownloaded,
+                                }
+                            }
+                        };
+                        PlaylistEntry::Missing { label, reason }
+                    })
+                    .collect(),
+            })
+            .collect();
+        library
+    }
+
+    /// Adds one playlist per genre of the downloaded tracks
+    pub fn add_genre_playlists(&mut self) {
+        let mut by_genre: BTreeMap<&str, Vec<PlaylistEntry>> = BTreeMap::new();
+        for (position, track) in self.tracks.iter().enumerate() {
+            if !track.genre.is_empty() {
+                by_genre
+                    .entry(track.genre.as_str())
+                    .or_default()
+                    .push(PlaylistEntry::Track(position));
+            }
+        }
+        let playlists: Vec<LogicalPlaylist> = by_genre
+            .into_iter()
+            .map(|(genre, entries)| LogicalPlaylist {
+                name: genre.to_string(),
+                group: PlaylistGroup::Genre,
+                spotify_playlist_id: None,
+                entries,
+            })
+            .collect();
+        self.playlists.extend(playlists);
+    }
+
+    /// Adds one playlist per favorite artist with downloaded tracks
+    pub fn add_artist_playlists(&mut self, artist_manager: &ArtistManager) {
+        let mut artists = artist_manager.get_all_artists();
+        artists.sort_by_key(|artist| artist.name.to_lowercase());
+        for artist in artists {
+            let entries: Vec<PlaylistEntry> = self
+                .tracks
+                .iter()
+                .enumerate()
+                .filter(|(_, track)| contains_words(&track.artist, &artist.name))
+                .map(|(position, _)| PlaylistEntry::Track(position))
+                .collect();
+            if !entries.is_empty() {
+                self.playlists.push(LogicalPlaylist {
+                    name: artist.name.clone(),
+                    group: PlaylistGroup::Artist,
+                    spotify_playlist_id: None,
+                    entries,
+                });
+            }
+        }
+    }
+
+    /// The playlists grouped by their folder, in group order
+    pub fn playlists_by_group(&self) -> Vec<(PlaylistGroup, Vec<&LogicalPlaylist>)> {
+        let mut groups: BTreeMap<PlaylistGroup, Vec<&LogicalPlaylist>> = BTreeMap::new();
+        for playlist in &self.playlists {
+            groups.entry(playlist.group).or_default().push(playlist);
+        }
+        groups.into_iter().collect()
+    }
+
+    /// `file://localhost/` url of a track, the form Rekordbox expects
+    pub fn file_location(path: &Path) -> String {
+        match url::Url::from_file_path(path) {
+            Ok(url) => url.as_str().replacen("file:///", "file://localhost/", 1),
+            Err(_) => path.display().to_string(),
+        }
+    }
+}
+
+/// A path split into the volume it lives on and the folders below the volume root
+#[derive(Debug, Clone, PartialEq)]
+pub struct VolumePath {
+    /// "C:" on Windows, the disk name for `/Volumes/<disk>` on macOS, empty for the root volume
+    pub volume: String,
+    pub volume_root: PathBuf,
+    pub folders: Vec<String>,
+    pub file_name: String,
+}
+
+impl VolumePath {
+    pub fn new(path: &Path) -> Self {
+        let mut volume = String::new();
+        let mut volume_root = PathBuf::new();
+        let mut names: Vec<String> = vec![];
+        for component in path.components() {
+            match component {
+                Component::Prefix(prefix) => {
+                    volume = prefix.as_os_str().to_string_lossy().to_string();
+                    volume_root.push(component);
+                }
+                Component::RootDir => volume_root.push(component),
+                Component::Normal(name) => names.push(name.to_string_lossy().to_string()),
+                Component::CurDir | Component::ParentDir => {}
+            }
+        }
+        if volume.is_empty() && names.len() > 2 && names[0] == "Volumes" {
+            volume = names[1].clone();
+            volume_root.push(names.remove(0));
+            volume_root.push(names.remove(0));
+        }
+        let file_name = names.pop().unwrap_or_default();
+        Self {
+            volume,
+            volume_root,
+            folders: names,
+            file_name,
+        }
+    }
+
+    /// The path below the volume root, with `/` as separator
+    pub fn relative_to_volume(&self) -> String {
+        self.folders
+            .iter()
+            .chain(std::iter::once(&self.file_name))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Escapes the characters that can't appear inside an XML attribute value
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if (c as u32) < 0x20 && !matches!(c, '\t' | '\n' | '\r') => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spotify::playlist::SpotifyPlaylist;
+    use crate::spotify::track::SpotifyTrack;
+
+    fn soundeo_track(id: &str, title: &str, already_downloaded: bool) -> SoundeoTrack {
+        let mut track = SoundeoTrack::new(id.to_string());
+        track.title = title.to_string();
+        track.bpm = "174".to_string();
+        track.date = "2023-05-12".to_string();
+        track.genre = "Drum & Bass".to_string();
+        track.already_downloaded = already_downloaded;
+        track
+    }
+
+    fn library() -> ExportLibrary {
+        let tracks_info = HashMap::from([
+            (
+                "1".to_string(),
+                soundeo_track("1", "Calibre - Mr Majestic", true),
+            ),
+            (
+                "2".to_string(),
+                soundeo_track("2", "Alix Perez - Burden", true),
+            ),
+            ("3".to_string(), soundeo_track("3", "Not Downloaded", false)),
+        ]);
+        let local_files = HashMap::from([(
+            "Calibre - Mr Majestic.AIFF".to_string(),
+            PathBuf::from("/music/Calibre - Mr Majestic.AIFF"),
+        )]);
+        let mut playlist =
+            SpotifyPlaylist::new("https://open.spotify.com/playlist/liquid".to_string()).unwrap();
+        playlist.name = "Liquid".to_string();
+        for (id, duration_ms) in [
+            ("spotify_2", None),
+            ("spotify_1", Some(301_400)),
+            ("spotify_3", None),
+        ] {
+            playlist.tracks.insert(
+                id.to_string(),
+                SpotifyTrack::new(
+                    "Title".to_string(),
+                    "Artist".to_string(),
+                    id.to_string(),
+                    duration_ms,
+                ),
+            );
+            playlist.track_order.push(id.to_string());
+        }
+        let mut spotify = Spotify::new();
+        spotify
+            .playlists
+            .insert(playlist.spotify_playlist_id.clone(), playlist);
+        spotify
+            .soundeo_track_ids
+            .insert("spotify_1".to_string(), Some("1".to_string()));
+        spotify
+            .soundeo_track_ids
+            .insert("spotify_2".to_string(), Some("2".to_string()));
+
+        ExportLibrary::build(&tracks_info, &spotify, &local_files)
+    }
+
+    #[test]
+    fn test_build_collects_downloaded_tracks_and_playlists() {
+        let library = library();
+
+        assert_eq!(library.tracks.len(), 1);
+        assert_eq!(library.missing_files, 1);
+        let track = &library.tracks[0];
+        assert_eq!(track.label(), "Calibre - Mr Majestic");
+        assert_eq!(track.bpm, Some(174.0));
+        assert_eq!(track.year, Some(2023));
+        assert_eq!(track.duration_secs, Some(301));
+        assert_eq!(
+            library.playlists,
+            vec![LogicalPlaylist {
+                name: "Liquid".to_string(),
+                group: PlaylistGroup::Spotify,
+                spotify_playlist_id: Some("liquid".to_string()),
+                entries: vec![
+                    PlaylistEntry::Missing {
+                        label: "Artist - Title".to_string(),
+                        reason: MissingReason::FileNotFound,
+                    },
+                    PlaylistEntry::Track(0),
+                    PlaylistEntry::Missing {
+                        label: "Artist - Title".to_string(),
+                        reason: MissingReason::NotPaired,
+                    },
+                ],
+            }]
+        );
+        assert_eq!(library.playlists[0].track_positions(), vec![0]);
+    }
+
+    #[test]
+    fn test_genre_and_artist_groupings() {
+        let mut library = library();
+        let mut artist_manager = ArtistManager::new();
+        artist_manager.add_artist("Calibre", None).unwrap();
+        artist_manager.add_artist("Alix Perez", None).unwrap();
+
+        library.add_genre_playlists();
+        library.add_artist_playlists(&artist_manager);
+
+        let groups: Vec<(PlaylistGroup, Vec<&str>)> = library
+            .playlists_by_group()
+            .into_iter()
+            .map(|(group, playlists)| (group, playlists.iter().map(|p| p.name.as_str()).collect()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (PlaylistGroup::Spotify, vec!["Liquid"]),
+                (PlaylistGroup::Genre, vec!["Drum & Bass"]),
+                (PlaylistGroup::Artist, vec!["Calibre"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_paths_and_escaping() {
+        assert_eq!(
+            ExportLibrary::file_location(Path::new("/music/Drum & Bass/A B.AIFF")),
+            "file://localhost/music/Drum%20&%20Bass/A%20B.AIFF"
+        );
+        let volume_path = VolumePath::new(Path::new("/Volumes/USB/music/A B.AIFF"));
+        assert_eq!(volume_path.volume, "USB");
+        assert_eq!(volume_path.volume_root, PathBuf::from("/Volumes/USB"));
+        assert_eq!(volume_path.relative_to_volume(), "music/A B.AIFF");
+        assert_eq!(
+            escape_xml("Drum & \"Bass\" <3"),
+            "Drum &amp; &quot;Bass&quot; &lt;3"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::path::Path;
+
+use crate::export::{escape_xml, ExportLibrary, ExportTrack};
+
+/// Renders the library as a `rekordbox.xml` collection, importable from the Rekordbox preferences
+pub fn render(library: &ExportLibrary) -> String {
+    let mut xml = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        r#"<DJ_PLAYLISTS Version="1.0.0">"#.to_string(),
+        format!(
+            r#"  <PRODUCT Name="dj-wizard" Version="{}" Company=""/>"#,
+            env!("CARGO_PKG_VERSION")
+        ),
+        format!(r#"  <COLLECTION Entries="{}">"#, library.tracks.len()),
+    ];
+    for (position, track) in library.tracks.iter().enumerate() {
+        xml.push(format!("    {}", render_track(track_id(position), track)));
+    }
+    xml.push("  </COLLECTION>".to_string());
+
+    let groups = library.playlists_by_group();
+    xml.push("  <PLAYLISTS>".to_string());
+    xml.push(format!(
+        r#"    <NODE Type="0" Name="ROOT" Count="{}">"#,
+        groups.len()
+    ));
+    for (group, playlists) in groups {
+        xml.push(format!(
+            r#"      <NODE Type="0" Name="{}" Count="{}">"#,
+            group.folder_name(),
+            playlists.len()
+        ));
+        for playlist in playlists {
+            let positions = playlist.track_positions();
+            // KeyType 0 means the entries reference the TrackID of the collection
+            xml.push(format!(
+                r#"        <NODE Name="{}" Type="1" KeyType="0" Entries="{}">"#,
+                escape_xml(&playlist.name),
+                positions.len()
+            ));
+            for position in positions {
+                xml.push(format!(
+                    r#"          <TRACK Key="{}"/>"#,
+                    track_id(position)
+                ));
+            }
+            xml.push("        </NODE>".to_string());
+        }
+        xml.push("      </NODE>".to_string());
+    }
+    xml.push("    </NODE>".to_string());
+    xml.push("  </PLAYLISTS>".to_string());
+    xml.push("</DJ_PLAYLISTS>".to_string());
+    xml.join("\n") + "\n"
+}
+
+fn track_id(position: usize) -> usize {
+    position + 1
+}
+
+fn render_track(track_id: usize, track: &ExportTrack) -> String {
+    let mut attributes = vec![
+        ("TrackID", track_id.to_string()),
+        ("Name", track.name.clone()),
+        ("Artist", track.artist.clone()),
+        ("Album", track.album.clone()),
+        ("Genre", track.genre.clone()),
+        ("Label", track.label.clone()),
+    ];
+    if let Some(kind) = file_kind(&track.path) {
+        attributes.push(("Kind", kind));
+    }
+    attributes.push(("Comments", format!("Soundeo {}", track.soundeo_id)));
+    if let Some(size) = track.size {
+        attributes.push(("Size", size.to_string()));
+    }
+    if let Some(duration_secs) = track.duration_secs {
+        attributes.push(("TotalTime", duration_secs.to_string()));
+    }
+    if let Some(year) = track.year {
+        attributes.push(("Year", year.to_string()));
+    }
+    if let Some(bpm) = track.bpm {
+        attributes.push(("AverageBpm", format!("{:.2}", bpm)));
+    }
+    if let Some(key) = &track.key {
+        attributes.push(("Tonality", key.clone()));
+    }
+    attributes.push(("Location", ExportLibrary::file_location(&track.path)));
+
+    let attributes: Vec<String> = attributes
+        .into_iter()
+        .map(|(name, value)| format!(r#"{}="{}""#, name, escape_xml(&value)))
+        .collect();
+    format!("<TRACK {}/>", attributes.join(" "))
+}
+
+/// The file type as rekordbox names it, e.g. "WAV File"
+fn file_kind(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?;
+    Some(format!("{} File", extension.to_uppercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::export::{LogicalPlaylist, MissingReason, PlaylistEntry, PlaylistGroup};
+
+    #[test]
+    fn test_render_collection_and_playlist_nodes() {
+        let library = ExportLibrary {
+            tracks: vec![ExportTrack {
+                soundeo_id: "123".to_string(),
+                artist: "Calibre".to_string(),
+                name: "Mr Majestic".to_string(),
+                album: "Shelflife".to_string(),
+                genre: "Drum & Bass".to_string(),
+                label: "Signature".to_string(),
+                bpm: Some(174.0),
+                key: Some("Am".to_string()),
+                year: None,
+                duration_secs: Some(301),
+                size: None,
+                path: PathBuf::from("/music/Calibre - Mr Majestic.AIFF"),
+            }],
+            playlists: vec![LogicalPlaylist {
+                name: "Liquid <3".to_string(),
+                group: PlaylistGroup::Spotify,
+                spotify_playlist_id: None,
+                entries: vec![
+                    PlaylistEntry::Track(0),
+                    PlaylistEntry::Missing {
+                        label: "Artist - Other".to_string(),
+                        reason: MissingReason::NotPaired,
+                    },
+                ],
+            }],
+            missing_files: 0,
+        };
+
+        let xml = render(&library);
+
+        assert!(xml.contains(r#"<COLLECTION Entries="1">"#));
+        assert!(xml.contains(
+            r#"<TRACK TrackID="1" Name="Mr Majestic" Artist="Calibre" Album="Shelflife" Genre="Drum &amp; Bass" Label="Signature" Kind="AIFF File" Comments="Soundeo 123" TotalTime="301" AverageBpm="174.00" Tonality="Am" Location="file://localhost/music/Calibre%20-%20Mr%20Majestic.AIFF"/>"#
+        ));
+        assert!(xml.contains(r#"<NODE Type="0" Name="Spotify" Count="1">"#));
+        assert!(xml.contains(
+            r#"<NODE Name="Liquid &lt;3" Type="1" KeyType="0" Entries="1">
+          <TRACK Key="1"/>
+        </NODE>"#
+        ));
+    }
+
+    #[test]
+    fn test_file_kind_follows_the_extension() {
+        assert_eq!(
+            file_kind(Path::new("/music/A - B.AIFF")),
+            Some("AIFF File".to_string())
+        );
+        assert_eq!(
+            file_kind(Path::new("/music/A - B.wav")),
+            Some("WAV File".to_string())
+        );
+        assert_eq!(
+            file_kind(Path::new("/music/A - B.MP3")),
+            Some("MP3 File".to_string())
+        );
+        assert_eq!(file_kind(Path::new("/music/A - B")), None);
+    }
+}
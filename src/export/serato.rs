@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::export::{ExportLibrary, LogicalPlaylist, VolumePath};
+
+const CRATE_VERSION: &str = "1.0/Serato ScratchLive Crate";
+/// Serato nests crates by joining the parent and child names with `%%`
+const SUBCRATE_SEPARATOR: &str = "%%";
+const SERATO_FOLDER_NAME: &str = "_Serato_";
+/// Columns shown when the crate is opened, with their width
+const CRATE_COLUMNS: [(&str, &str); 4] =
+    [("song", "0"), ("artist", "0"), ("bpm", "0"), ("key", "0")];
+
+/// The `_Serato_` folder of the drive holding the downloads, crates only reference tracks of their drive
+pub fn default_serato_folder(download_dir: &Path) -> Option<PathBuf> {
+    let volume_path = VolumePath::new(download_dir);
+    if volume_path.volume.is_empty() {
+        std::env::var("HOME")
+            .ok()
+            .map(|home| Path::new(&home).join("Music").join(SERATO_FOLDER_NAME))
+    } else {
+        Some(volume_path.volume_root.join(SERATO_FOLDER_NAME))
+    }
+}
+
+/// `Subcrates/<Group>%%<Playlist>.crate` below the `_Serato_` folder for every playlist, in order.
+/// The Spotify playlist id is appended to the names shared by several playlists, so none
+/// overwrites another
+pub fn crate_file_paths(serato_folder: &Path, playlists: &[LogicalPlaylist]) -> Vec<PathBuf> {
+    let crate_names: Vec<String> = playlists
+        .iter()
+        .map(|playlist| {
+            [playlist.group.folder_name(), playlist.name.as_str()]
+                .map(sanitize_crate_name)
+                .join(SUBCRATE_SEPARATOR)
+        })
+        .collect();
+    let mut name_counts: HashMap<&str, usize> = HashMap::new();
+    for crate_name in &crate_names {
+        *name_counts.entry(crate_name.as_str()).or_default() += 1;
+    }
+    playlists
+        .iter()
+        .zip(&crate_names)
+        .map(|(playlist, crate_name)| {
+            let crate_name = match &playlist.spotify_playlist_id {
+                Some(playlist_id) if name_counts[crate_name.as_str()] > 1 => {
+                    format!("{} ({})", crate_name, sanitize_crate_name(playlist_id))
+                }
+                _ => crate_name.clone(),
+            };
+            serato_folder
+                .join("Subcrates")
+                .join(format!("{}.crate", crate_name))
+        })
+        .collect()
+}
+
+/// Renders the binary `.crate` content, skipping the tracks stored on another drive than `volume_root`.
+/// Returns the content and the count of tracks written
+pub fn render_crate(
+    library: &ExportLibrary,
+    playlist: &LogicalPlaylist,
+    volume_root: &Path,
+) -> (Vec<u8>, usize) {
+    let mut content = tag("vrsn", &utf16(CRATE_VERSION));
+    content.extend(tag(
+        "osrt",
+        &[tag("tvcn", &utf16("song")), tag("brev", &[0])].concat(),
+    ));
+    for (column, width) in CRATE_COLUMNS {
+        content.extend(tag(
+            "ovct",
+            &[tag("tvcn", &utf16(column)), tag("tvcw", &utf16(width))].concat(),
+        ));
+    }
+    let mut written = 0;
+    for position in playlist.track_positions() {
+        let volume_path = VolumePath::new(&library.tracks[position].path);
+        if volume_path.volume_root != volume_root {
+            continue;
+        }
+        let track_path = utf16(&volume_path.relative_to_volume());
+        content.extend(tag("otrk", &tag("ptrk", &track_path)));
+        written += 1;
+    }
+    (content, written)
+}
+
+/// A field is its 4 ascii bytes name, the payload length as big endian u32 and the payload
+fn tag(name: &str, payload: &[u8]) -> Vec<u8> {
+    let mut field = Vec::with_capacity(8 + payload.len());
+    field.extend_from_slice(name.as_bytes());
+    field.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    field.extend_from_slice(payload);
+    field
+}
+
+/// Serato strings are UTF-16 big endian, without terminator
+fn utf16(value: &str) -> Vec<u8> {
+    value.encode_utf16().flat_map(u16::to_be_bytes).collect()
+}
+
+fn sanitize_crate_name(name: &str) -> String {
+    name.replace(SUBCRATE_SEPARATOR, "%")
+        .chars()
+        .filter(|c| !matches!(*c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{ExportTrack, PlaylistEntry, PlaylistGroup};
+
+    fn export_track(path: &str) -> ExportTrack {
+        ExportTrack {
+            soundeo_id: "1".to_string(),
+            artist: "Calibre".to_string(),
+            name: "Mr Majestic".to_string(),
+            album: String::new(),
+            genre: String::new(),
+            label: String::new(),
+            bpm: None,
+            key: None,
+            year: None,
+            duration_secs: None,
+            size: None,
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn test_crate_path_and_track_fields() {
+        let library = ExportLibrary {
+            tracks: vec![
+                export_track("/Volumes/USB/music/A.AIFF"),
+                export_track("/Users/dj/Music/B.AIFF"),
+            ],
+            playlists: vec![],
+            missing_files: 0,
+        };
+        let playlist = LogicalPlaylist {
+            name: "Liquid/Rollers".to_string(),
+            group: PlaylistGroup::Spotify,
+            spotify_playlist_id: None,
+            entries: vec![PlaylistEntry::Track(0), PlaylistEntry::Track(1)],
+        };
+        let serato_folder = default_serato_folder(Path::new("/Volumes/USB/music")).unwrap();
+
+        assert_eq!(serato_folder, PathBuf::from("/Volumes/USB/_Serato_"));
+        assert_eq!(
+            crate_file_paths(&serato_folder, &[playlist.clone()]),
+            vec![PathBuf::from(
+                "/Volumes/USB/_Serato_/Subcrates/Spotify%%LiquidRollers.crate"
+            )]
+        );
+
+        let (content, written) = render_crate(&library, &playlist, Path::new("/Volumes/USB"));
+
+        assert_eq!(written, 1);
+        assert_eq!(&content[..8], b"vrsn\x00\x00\x00\x38");
+        let track_field = [
+            b"otrk\x00\x00\x00\x20ptrk\x00\x00\x00\x18".to_vec(),
+            utf16("music/A.AIFF"),
+        ]
+        .concat();
+        assert!(content.ends_with(&track_field));
+    }
+
+    #[test]
+    fn test_crate_paths_of_playlists_with_the_same_name() {
+        let playlist = |id: &str, name: &str| LogicalPlaylist {
+            name: name.to_string(),
+            group: PlaylistGroup::Spotify,
+            spotify_playlist_id: Some(id.to_string()),
+            entries: vec![],
+        };
+        let playlists = vec![
+            playlist("1", "Liquid"),
+            playlist("2", "Liquid"),
+            playlist("3", "Rollers"),
+        ];
+
+        let file_names: Vec<String> = crate_file_paths(Path::new("/_Serato_"), &playlists)
+            .iter()
+            .map(|path| path.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+
+        assert_eq!(
+            file_names,
+            vec![
+                "Spotify%%Liquid (1).crate",
+                "Spotify%%Liquid (2).crate",
+                "Spotify%%Rollers.crate"
+            ]
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use sha2::{Digest, Sha256};
+
+use crate::export::{escape_xml, ExportLibrary, ExportTrack, LogicalPlaylist, VolumePath};
+
+/// Traktor names the system disk of macOS after its volume label
+pub const DEFAULT_VOLUME: &str = "Macintosh HD";
+
+/// Renders the library as a Traktor `collection.nml`, importable from the Traktor browser
+pub fn render(library: &ExportLibrary) -> String {
+    let mut nml = vec![
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="no" ?>"#.to_string(),
+        r#"<NML VERSION="19">"#.to_string(),
+        format!(
+            r#"  <HEAD COMPANY="www.native-instruments.com" PROGRAM="dj-wizard {}"></HEAD>"#,
+            env!("CARGO_PKG_VERSION")
+        ),
+        format!(r#"  <COLLECTION ENTRIES="{}">"#, library.tracks.len()),
+    ];
+    for track in &library.tracks {
+        nml.extend(
+            render_track(track)
+                .into_iter()
+                .map(|line| format!("    {}", line)),
+        );
+    }
+    nml.push("  </COLLECTION>".to_string());
+
+    let groups = library.playlists_by_group();
+    nml.push("  <PLAYLISTS>".to_string());
+    nml.push(r#"    <NODE TYPE="FOLDER" NAME="$ROOT">"#.to_string());
+    nml.push(format!(r#"      <SUBNODES COUNT="{}">"#, groups.len()));
+    for (group, playlists) in groups {
+        nml.push(format!(
+            r#"        <NODE TYPE="FOLDER" NAME="{}">"#,
+            group.folder_name()
+        ));
+        nml.push(format!(
+            r#"          <SUBNODES COUNT="{}">"#,
+            playlists.len()
+        ));
+        for playlist in playlists {
+            let positions = playlist.track_positions();
+            nml.push(format!(
+                r#"            <NODE TYPE="PLAYLIST" NAME="{}">"#,
+                escape_xml(&playlist.name)
+            ));
+            nml.push(format!(
+                r#"              <PLAYLIST ENTRIES="{}" TYPE="LIST" UUID="{}">"#,
+                positions.len(),
+                playlist_uuid(playlist)
+            ));
+            for position in positions {
+                nml.push(format!(
+                    r#"                <ENTRY><PRIMARYKEY TYPE="TRACK" KEY="{}"></PRIMARYKEY></ENTRY>"#,
+                    escape_xml(&primary_key(&library.tracks[position]))
+                ));
+            }
+            nml.push("              </PLAYLIST>".to_string());
+            nml.push("            </NODE>".to_string());
+        }
+        nml.push("          </SUBNODES>".to_string());
+        nml.push("        </NODE>".to_string());
+    }
+    nml.push("      </SUBNODES>".to_string());
+    nml.push("    </NODE>".to_string());
+    nml.push("  </PLAYLISTS>".to_string());
+    nml.push("</NML>".to_string());
+    nml.join("\n") + "\n"
+}
+
+/// Traktor locations use `/:` as folder separator, e.g. `/:Users/:dj/:Music/:`
+struct TraktorLocation {
+    volume: String,
+    dir: String,
+    file: String,
+}
+
+impl TraktorLocation {
+    fn new(track: &ExportTrack) -> Self {
+        let volume_path = VolumePath::new(&track.path);
+        let volume = if volume_path.volume.is_empty() {
+            DEFAULT_VOLUME.to_string()
+        } else {
+            volume_path.volume
+        };
+        let dir = volume_path
+            .folders
+            .iter()
+            .fold("/:".to_string(), |dir, folder| {
+                format!("{}{}/:", dir, folder)
+            });
+        Self {
+            volume,
+            dir,
+            file: volume_path.file_name,
+        }
+    }
+}
+
+/// The key playlists use to reference a collection entry
+fn primary_key(track: &ExportTrack) -> String {
+    let location = TraktorLocation::new(track);
+    format!("{}{}{}", location.volume, location.dir, location.file)
+}
+
+/// Stable across exports, so Traktor updates the playlist instead of duplicating it.
+/// Spotify playlists also hash their id, as several can have the same name
+fn playlist_uuid(playlist: &LogicalPlaylist) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(playlist.group.folder_name().as_bytes());
+    hasher.update(b"/");
+    hasher.update(playlist.name.as_bytes());
+    if let Some(spotify_playlist_id) = &playlist.spotify_playlist_id {
+        hasher.update(b"/");
+        hasher.update(spotify_playlist_id.as_bytes());
+    }
+    hasher.finalize()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn render_track(track: &ExportTrack) -> Vec<String> {
+    let location = TraktorLocation::new(track);
+    let mut info = vec![
+        ("GENRE", track.genre.clone()),
+        ("LABEL", track.label.clone()),
+        ("COMMENT", format!("Soundeo {}", track.soundeo_id)),
+    ];
+    if let Some(key) = &track.key {
+        info.push(("KEY", key.clone()));
+    }
+    if let Some(duration_secs) = track.duration_secs {
+        info.push(("PLAYTIME", duration_secs.to_string()));
+    }
+    if let Some(size) = track.size {
+        // Traktor stores the file size in kilobytes
+        info.push(("FILESIZE", (size / 1024).to_string()));
+    }
+    if let Some(year) = track.year {
+        info.push(("RELEASE_DATE", format!("{}/1/1", year)));
+    }
+    let info: Vec<String> = info
+        .into_iter()
+        .map(|(name, value)| format!(r#"{}="{}""#, name, escape_xml(&value)))
+        .collect();
+
+    let mut lines = vec![
+        format!(
+            r#"<ENTRY TITLE="{}" ARTIST="{}">"#,
+            escape_xml(&track.name),
+            escape_xml(&track.artist)
+        ),
+        format!(
+            r#"  <LOCATION DIR="{}" FILE="{}" VOLUME="{}" VOLUMEID="{}"></LOCATION>"#,
+            escape_xml(&location.dir),
+            escape_xml(&location.file),
+            escape_xml(&location.volume),
+            escape_xml(&location.volume)
+        ),
+        format!(r#"  <ALBUM TITLE="{}"></ALBUM>"#, escape_xml(&track.album)),
+        format!("  <INFO {}></INFO>", info.join(" ")),
+    ];
+    if let Some(bpm) = track.bpm {
+        lines.push(format!(
+            r#"  <TEMPO BPM="{:.6}" BPM_QUALITY="100.000000"></TEMPO>"#,
+            bpm
+        ));
+    }
+    lines.push("</ENTRY>".to_string());
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::export::{PlaylistEntry, PlaylistGroup};
+
+    #[test]
+    fn test_render_collection_and_playlist_keys() {
+        let library = ExportLibrary {
+            tracks: vec![ExportTrack {
+                soundeo_id: "123".to_string(),
+                artist: "Calibre".to_string(),
+                name: "Mr Majestic".to_string(),
+                album: "Shelflife".to_string(),
+                genre: "Drum & Bass".to_string(),
+                label: "Signature".to_string(),
+                bpm: Some(174.0),
+                key: None,
+                year: Some(2023),
+                duration_secs: Some(301),
+                size: Some(52_428_800),
+                path: PathBuf::from("/Volumes/USB/music/Calibre - Mr Majestic.AIFF"),
+            }],
+            playlists: vec![LogicalPlaylist {
+                name: "Drum & Bass".to_string(),
+                group: PlaylistGroup::Genre,
+                spotify_playlist_id: None,
+                entries: vec![PlaylistEntry::Track(0)],
+            }],
+            missing_files: 0,
+        };
+
+        let nml = render(&library);
+
+        assert!(nml.contains(r#"<COLLECTION ENTRIES="1">"#));
+        assert!(nml.contains(
+            r#"<LOCATION DIR="/:music/:" FILE="Calibre - Mr Majestic.AIFF" VOLUME="USB" VOLUMEID="USB"></LOCATION>"#
+        ));
+        assert!(nml.contains(
+            r#"<INFO GENRE="Drum &amp; Bass" LABEL="Signature" COMMENT="Soundeo 123" PLAYTIME="301" FILESIZE="51200" RELEASE_DATE="2023/1/1"></INFO>"#
+        ));
+        assert!(nml.contains(r#"<TEMPO BPM="174.000000" BPM_QUALITY="100.000000"></TEMPO>"#));
+        assert!(nml.contains(r#"<NODE TYPE="FOLDER" NAME="Genres">"#));
+        assert!(nml.contains(
+            r#"<ENTRY><PRIMARYKEY TYPE="TRACK" KEY="USB/:music/:Calibre - Mr Majestic.AIFF"></PRIMARYKEY></ENTRY>"#
+        ));
+        assert_eq!(playlist_uuid(&library.playlists[0]).len(), 32);
+    }
+
+    #[test]
+    fn test_playlists_with_the_same_name_get_their_own_uuid() {
+        let playlist = |id: &str| LogicalPlaylist {
+            name: "Liquid".to_string(),
+            group: PlaylistGroup::Spotify,
+            spotify_playlist_id: Some(id.to_string()),
+            entries: vec![],
+        };
+
+        assert_ne!(playlist_uuid(&playlist("1")), playlist_uuid(&playlist("2")));
+        assert_eq!(playlist_uuid(&playlist("1")), playlist_uuid(&playlist("1")));
+    }
+}
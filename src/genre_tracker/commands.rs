@@ -1,29 +1,63 @@
-use std::collections::HashSet;
 use std::io::Write;
 
 use chrono::{Duration, Utc};
+use clap::Subcommand;
 use colored::Colorize;
-use error_stack::{IntoReport, ResultExt};
-use indicatif::{ProgressBar, ProgressStyle};
+use error_stack::{IntoReport, Report, ResultExt};
+use futures_util::{stream, StreamExt};
 use inflector::Inflector;
 use reqwest::Client;
 use strum::IntoEnumIterator;
 
 use crate::dialoguer::Dialoguer;
-use crate::genre_tracker::{GenreTrackerCRUD, GenreTrackerError, GenreTrackerResult};
+use crate::genre_tracker::{
+    find_last_page, CrawlCheckpoint, GenreTracker, GenreTrackerCRUD, GenreTrackerError,
+    GenreTrackerResult, TrackedGenre,
+};
 use crate::log::{DjWizardLog, Priority};
+use crate::output::OutputFormat;
+use crate::queue::commands::QueueCommands;
 use crate::queue::track_processor::TrackProcessor;
-use crate::soundeo::track::SoundeoTrack;
-use crate::soundeo::track_list::SoundeoTracksList;
+use crate::soundeo::track_list::{SoundeoListedTrack, SoundeoTracksList};
 use crate::user::SoundeoUser;
 
-#[derive(Debug, Clone, strum_macros::Display, strum_macros::EnumIter)]
+const SOUNDEO_TRACKS_URL: &str = "https://soundeo.com/list/tracks";
+
+/// Search pages scraped at the same time while the previous ones are queued
+const PAGES_SCRAPED_CONCURRENTLY: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, strum_macros::Display, strum_macros::EnumIter, Subcommand)]
 pub enum GenreTrackerCommands {
+    /// Queue the new tracks of a followed genre since its last check.
     QueueTracksFromFollowedGenre,
+    /// Change the date a followed genre was last checked.
     UpdateFollowedGenreDate,
+    /// Change the search filters of a followed genre.
+    EditFollowedGenreFilters,
+    /// Change the priority, format and folder of the tracks queued from a followed genre.
+    EditFollowedGenreDefaults,
+    /// Start following a genre.
     FollowNewGenre,
+    /// List the followed genres.
     ViewFollowedGenres,
+    /// Stop following a genre.
     RemoveFollowedGenre,
+    /// Update the list of genres available on Soundeo.
+    RefreshAvailableGenres,
+    /// Queue the new tracks of the followed genres up to today.
+    Sync {
+        /// Sync every followed genre without asking which ones
+        #[clap(long, action)]
+        all: bool,
+    },
+}
+
+/// Tracks handled by a crawl of a followed genre
+#[derive(Debug, Default)]
+struct CrawlSummary {
+    added: usize,
+    skipped: usize,
+    excluded: usize,
 }
 
 impl GenreTrackerCommands {
@@ -151,16 +185,44 @@ impl GenreTrackerCommands {
         Ok(start_date)
     }
 
-    pub async fn execute() -> GenreTrackerResult<()> {
-        let options = Self::get_options();
+    fn select_priority(genre_name: &str, current: Priority) -> GenreTrackerResult<Priority> {
+        let priority_options = vec!["High (download first)", "Normal", "Low (download last)"];
+        let default_selection = match current {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        };
         let selection = Dialoguer::select(
-            "Genre Tracker - What would you like to do?".to_string(),
-            options,
-            None,
+            format!("Choose a priority for the tracks of {}", genre_name),
+            priority_options,
+            Some(default_selection),
         )
         .change_context(GenreTrackerError)?;
 
-        match Self::get_selection(selection) {
+        let selected_priority = match selection {
+            0 => Priority::High,
+            1 => Priority::Normal,
+            _ => Priority::Low,
+        };
+        Ok(selected_priority)
+    }
+
+    pub async fn execute(command: Option<GenreTrackerCommands>) -> GenreTrackerResult<()> {
+        let command_to_run = match command {
+            Some(command) => command,
+            None => {
+                let options = Self::get_options();
+                let selection = Dialoguer::select(
+                    "Genre Tracker - What would you like to do?".to_string(),
+                    options,
+                    None,
+                )
+                .change_context(GenreTrackerError)?;
+                Self::get_selection(selection)
+            }
+        };
+
+        match command_to_run {
             GenreTrackerCommands::FollowNewGenre => Self::follow_new_genre().await,
             GenreTrackerCommands::QueueTracksFromFollowedGenre => {
                 Self::queue_tracks_from_followed_genre().await
@@ -168,8 +230,16 @@ impl GenreTrackerCommands {
             GenreTrackerCommands::UpdateFollowedGenreDate => {
                 Self::update_followed_genre_date().await
             }
+            GenreTrackerCommands::EditFollowedGenreFilters => Self::edit_followed_genre_filters(),
+            GenreTrackerCommands::EditFollowedGenreDefaults => Self::edit_followed_genre_defaults(),
             GenreTrackerCommands::ViewFollowedGenres => Self::view_followed_genres(),
             GenreTrackerCommands::RemoveFollowedGenre => Self::remove_followed_genre(),
+            GenreTrackerCommands::Sync { all } => Self::sync_followed_genres(all).await,
+            GenreTrackerCommands::RefreshAvailableGenres => {
+                let mut tracker =
+                    DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
+                Self::refresh_available_genres(&mut tracker).await
+            }
         }
     }
 
@@ -184,9 +254,44 @@ impl GenreTrackerCommands {
         options[selection].clone()
     }
 
+    async fn refresh_available_genres(tracker: &mut GenreTracker) -> GenreTrackerResult<()> {
+        let mut soundeo_user = SoundeoUser::new().change_context(GenreTrackerError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(GenreTrackerError)?;
+        let track_list = SoundeoTracksList::new(SOUNDEO_TRACKS_URL.to_string())
+            .change_context(GenreTrackerError)?;
+        let genres = track_list
+            .get_genres(&soundeo_user)
+            .await
+            .change_context(GenreTrackerError)?;
+        let genres_count = genres.len();
+        tracker.set_available_genres(genres, Utc::now().timestamp() as u64);
+        DjWizardLog::save_genre_tracker(tracker.clone()).change_context(GenreTrackerError)?;
+        println!(
+            "Genre list updated from Soundeo: {} genres available",
+            genres_count.to_string().green()
+        );
+        Ok(())
+    }
+
     async fn follow_new_genre() -> GenreTrackerResult<()> {
         let mut tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
 
+        if tracker.needs_genres_refresh(Utc::now().timestamp() as u64) {
+            if let Err(error) = Self::refresh_available_genres(&mut tracker).await {
+                println!(
+                    "{}",
+                    format!(
+                        "Could not update the genre list from Soundeo, using the stored one: {:?}",
+                        error
+                    )
+                    .yellow()
+                );
+            }
+        }
+
         // Filter out already tracked genres
         let available_genres: Vec<(u32, String)> = tracker
             .available_genres
@@ -241,9 +346,12 @@ impl GenreTrackerCommands {
             .add_tracked_genre(genre_id)
             .change_context(GenreTrackerError)?;
 
+        let priority = Self::select_priority(selected_genre_name, Priority::Normal)?;
+
         // Update the last_checked_date to the start_date so we begin from there
         if let Some(tracked_genre) = tracker.tracked_genres.get_mut(&genre_id) {
             tracked_genre.last_checked_date = start_date.clone();
+            tracked_genre.priority = priority;
         }
 
         DjWizardLog::save_genre_tracker(tracker).change_context(GenreTrackerError)?;
@@ -255,7 +363,7 @@ impl GenreTrackerCommands {
     }
 
     async fn queue_tracks_from_followed_genre() -> GenreTrackerResult<()> {
-        let mut tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
+        let tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
 
         if tracker.tracked_genres.is_empty() {
             println!("{}", "No genres are currently being tracked!".yellow());
@@ -303,9 +411,7 @@ impl GenreTrackerCommands {
         let genre_id = genre_options[selection].0;
         let tracked_genre = tracker.tracked_genres.get(&genre_id).unwrap();
 
-        // Calculate date range: from last_checked_date (inclusive) to today
-        let start_date = tracked_genre.last_checked_date.clone();
-        let end_date = Utc::now().format("%Y-%m-%d").to_string();
+        let (start_date, end_date) = Self::sync_date_range(tracked_genre);
 
         println!(
             "Updating {} from {} to {}",
@@ -315,8 +421,7 @@ impl GenreTrackerCommands {
         );
 
         // Show the Soundeo URL for user reference
-        let tracker_for_url = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
-        let sample_url = tracker_for_url.build_soundeo_url(genre_id, &start_date, &end_date, 1);
+        let sample_url = tracked_genre.search_url(&start_date, &end_date, 1);
         println!(
             "\n{}: {}",
             "Soundeo search URL".green(),
@@ -324,19 +429,42 @@ impl GenreTrackerCommands {
         );
         println!("{}", "You can visit this link to see what tracks will be processed.".dimmed());
 
+        Self::sync_genre(genre_id, &start_date, &end_date).await?;
+
+        Ok(())
+    }
+
+    /// From last_checked_date (inclusive) to today, unless an interrupted crawl
+    /// is resumed with its own range
+    fn sync_date_range(tracked_genre: &TrackedGenre) -> (String, String) {
+        match &tracked_genre.crawl_checkpoint {
+            Some(checkpoint) => (checkpoint.start_date.clone(), checkpoint.end_date.clone()),
+            None => (
+                tracked_genre.last_checked_date.clone(),
+                Utc::now().format("%Y-%m-%d").to_string(),
+            ),
+        }
+    }
+
+    async fn sync_genre(
+        genre_id: u32,
+        start_date: &str,
+        end_date: &str,
+    ) -> GenreTrackerResult<CrawlSummary> {
         // Fetch and queue tracks
-        Self::fetch_and_queue_tracks(genre_id, &start_date, &end_date).await?;
+        let summary = Self::fetch_and_queue_tracks(genre_id, start_date, end_date).await?;
 
-        // Update last checked date
+        // Update last checked date, reading the tracker saved by the crawl
+        let mut tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
         tracker
             .update_last_checked(genre_id)
             .change_context(GenreTrackerError)?;
         DjWizardLog::save_genre_tracker(tracker).change_context(GenreTrackerError)?;
 
-        Ok(())
+        Ok(summary)
     }
 
-    fn view_followed_genres() -> GenreTrackerResult<()> {
+    async fn sync_followed_genres(all: bool) -> GenreTrackerResult<()> {
         let tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
 
         if tracker.tracked_genres.is_empty() {
@@ -344,19 +472,108 @@ impl GenreTrackerCommands {
             return Ok(());
         }
 
-        println!("\n{}", "Currently tracked genres:".green());
-        println!("{}", "-".repeat(60));
+        let mut genres: Vec<TrackedGenre> = tracker.tracked_genres.into_values().collect();
+        genres.sort_by(|a, b| a.genre_name.cmp(&b.genre_name));
+
+        if !all {
+            let options: Vec<String> = genres
+                .iter()
+                .map(|genre| {
+                    format!(
+                        "{} (last checked: {})",
+                        genre.genre_name, genre.last_checked_date
+                    )
+                })
+                .collect();
+            let selections = Dialoguer::multiselect(
+                "Select the genres to sync".to_string(),
+                options,
+                None,
+                true,
+            )
+            .change_context(GenreTrackerError)?;
+            genres = selections
+                .into_iter()
+                .map(|selection| genres[selection].clone())
+                .collect();
+        }
+
+        let mut synced_genres = Vec::new();
+        let mut failed_genres = Vec::new();
+        for genre in genres {
+            let (start_date, end_date) = Self::sync_date_range(&genre);
+            println!(
+                "\nSyncing {} from {} to {}",
+                genre.genre_name.cyan(),
+                start_date.cyan(),
+                end_date.cyan()
+            );
+            match Self::sync_genre(genre.genre_id, &start_date, &end_date).await {
+                Ok(summary) => {
+                    synced_genres.push((genre.genre_name, start_date, end_date, summary))
+                }
+                Err(error) => {
+                    println!(
+                        "{}",
+                        format!("Could not sync {}: {:?}", genre.genre_name, error).red()
+                    );
+                    failed_genres.push(genre.genre_name);
+                }
+            }
+        }
+
+        println!("\n{}", "Genre sync summary".green());
+        for (genre_name, start_date, end_date, summary) in synced_genres {
+            println!(
+                "{}: {} queued, {} skipped, {} excluded ({} to {})",
+                genre_name.cyan(),
+                summary.added.to_string().green(),
+                summary.skipped.to_string().yellow(),
+                summary.excluded.to_string().yellow(),
+                start_date,
+                end_date
+            );
+        }
+        for genre_name in failed_genres.iter() {
+            println!("{}: {}", genre_name.cyan(), "failed".red());
+        }
+
+        if !failed_genres.is_empty() {
+            return Err(Report::new(GenreTrackerError)
+                .attach_printable(format!("Could not sync {}", failed_genres.join(", "))));
+        }
+        Ok(())
+    }
+
+    fn view_followed_genres() -> GenreTrackerResult<()> {
+        let tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
 
         let mut genres: Vec<_> = tracker.tracked_genres.values().collect();
         genres.sort_by(|a, b| a.genre_name.cmp(&b.genre_name));
 
+        if OutputFormat::is_json() {
+            return OutputFormat::print_json(&serde_json::json!({ "tracked_genres": genres }))
+                .change_context(GenreTrackerError);
+        }
+
+        if genres.is_empty() {
+            println!("{}", "No genres are currently being tracked!".yellow());
+            return Ok(());
+        }
+
+        println!("\n{}", "Currently tracked genres:".green());
+        println!("{}", "-".repeat(60));
+
         for genre in genres {
             println!(
-                "{}: {} | Created: {} | Last checked: {}",
+                "{}: {} | Created: {} | Last checked: {} | Filters: {} | Priority: {:?} | Download: {}",
                 genre.genre_name.cyan(),
                 format!("ID {}", genre.genre_id).yellow(),
                 genre.created_at,
-                genre.last_checked_date
+                genre.last_checked_date,
+                genre.filters.describe(),
+                genre.priority,
+                genre.download_target.describe()
             );
         }
 
@@ -470,12 +687,272 @@ impl GenreTrackerCommands {
         Ok(())
     }
 
+    fn edit_followed_genre_filters() -> GenreTrackerResult<()> {
+        let mut tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
+
+        if tracker.tracked_genres.is_empty() {
+            println!("{}", "No genres are currently being tracked!".yellow());
+            return Ok(());
+        }
+
+        let mut genre_options: Vec<(u32, String)> = tracker
+            .tracked_genres
+            .iter()
+            .map(|(id, info)| {
+                (
+                    *id,
+                    format!("{} ({})", info.genre_name, info.filters.describe()),
+                )
+            })
+            .collect();
+        genre_options.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let options: Vec<String> = genre_options.iter().map(|(_, name)| name.clone()).collect();
+
+        let selection = Dialoguer::select(
+            "Select a genre to edit its filters".to_string(),
+            options,
+            None,
+        )
+        .change_context(GenreTrackerError)?;
+
+        let genre_id = genre_options[selection].0;
+        let tracked_genre = tracker.tracked_genres.get_mut(&genre_id).unwrap();
+        let mut filters = tracked_genre.filters.clone();
+
+        filters.all_tracks = !Dialoguer::confirm(
+            "Only queue tracks available for download?".to_string(),
+            Some(!filters.all_tracks),
+        )
+        .change_context(GenreTrackerError)?;
+
+        filters.bpm_range = loop {
+            let current = filters
+                .bpm_range
+                .map(|(min_bpm, max_bpm)| format!("{}-{}", min_bpm, max_bpm))
+                .unwrap_or_default();
+            let bpm_input = Dialoguer::optional_input(
+                "BPM range, e.g. 170-176 (empty for any)".to_string(),
+                current,
+            )
+            .change_context(GenreTrackerError)?;
+            if bpm_input.is_empty() {
+                break None;
+            }
+            let bpm_range = bpm_input.split_once('-').and_then(|(min_bpm, max_bpm)| {
+                Some((min_bpm.trim().parse().ok()?, max_bpm.trim().parse().ok()?))
+            });
+            match bpm_range {
+                Some(bpm_range) => break Some(bpm_range),
+                None => println!("{}", "Invalid BPM range, use the format 170-176.".red()),
+            }
+        };
+
+        let key_input = Dialoguer::optional_input(
+            "Key as written on Soundeo, e.g. E♭min (empty for any)".to_string(),
+            filters.key.clone().unwrap_or_default(),
+        )
+        .change_context(GenreTrackerError)?;
+        filters.key = (!key_input.is_empty()).then_some(key_input);
+
+        filters.label_id = Self::input_soundeo_id(
+            "Soundeo label id, the labelFilter of a label url (empty for any)",
+            filters.label_id,
+        )?;
+        filters.artist_id = Self::input_soundeo_id(
+            "Soundeo artist id, the artistFilter of an artist url (empty for any)",
+            filters.artist_id,
+        )?;
+
+        let keywords_input = Dialoguer::optional_input(
+            "Skip titles containing, comma separated, e.g. Radio Edit, Acapella".to_string(),
+            filters.excluded_keywords.join(", "),
+        )
+        .change_context(GenreTrackerError)?;
+        filters.excluded_keywords = keywords_input
+            .split(',')
+            .map(|keyword| keyword.trim().to_string())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+
+        let genre_name = tracked_genre.genre_name.clone();
+        let description = filters.describe();
+        tracked_genre.filters = filters;
+        DjWizardLog::save_genre_tracker(tracker).change_context(GenreTrackerError)?;
+
+        println!(
+            "Filters of {} updated: {}",
+            genre_name.green(),
+            description.cyan()
+        );
+
+        Ok(())
+    }
+
+    fn edit_followed_genre_defaults() -> GenreTrackerResult<()> {
+        let mut tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
+
+        if tracker.tracked_genres.is_empty() {
+            println!("{}", "No genres are currently being tracked!".yellow());
+            return Ok(());
+        }
+
+        let mut genre_options: Vec<(u32, String)> = tracker
+            .tracked_genres
+            .iter()
+            .map(|(id, info)| {
+                (
+                    *id,
+                    format!(
+                        "{} ({:?}, {})",
+                        info.genre_name,
+                        info.priority,
+                        info.download_target.describe()
+                    ),
+                )
+            })
+            .collect();
+        genre_options.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let options: Vec<String> = genre_options.iter().map(|(_, name)| name.clone()).collect();
+
+        let selection = Dialoguer::select(
+            "Select a genre to edit its defaults".to_string(),
+            options,
+            None,
+        )
+        .change_context(GenreTrackerError)?;
+
+        let genre_id = genre_options[selection].0;
+        let tracked_genre = tracker.tracked_genres.get_mut(&genre_id).unwrap();
+        let genre_name = tracked_genre.genre_name.clone();
+
+        let priority = Self::select_priority(&genre_name, tracked_genre.priority)?;
+
+        let download_target =
+            QueueCommands::prompt_for_download_target(&tracked_genre.download_target)
+                .change_context(GenreTrackerError)?;
+
+        tracked_genre.priority = priority;
+        tracked_genre.download_target = download_target;
+        let description = format!(
+            "{:?} priority, {}",
+            tracked_genre.priority,
+            tracked_genre.download_target.describe()
+        );
+        DjWizardLog::save_genre_tracker(tracker).change_context(GenreTrackerError)?;
+
+        println!(
+            "Defaults of {} updated: {}",
+            genre_name.green(),
+            description.cyan()
+        );
+        println!(
+            "{}",
+            "The tracks already queued keep the defaults they were queued with.".dimmed()
+        );
+
+        Ok(())
+    }
+
+    fn input_soundeo_id(
+        prompt_text: &str,
+        current: Option<u32>,
+    ) -> GenreTrackerResult<Option<u32>> {
+        loop {
+            let id_input = Dialoguer::optional_input(
+                prompt_text.to_string(),
+                current.map(|id| id.to_string()).unwrap_or_default(),
+            )
+            .change_context(GenreTrackerError)?;
+            if id_input.is_empty() {
+                return Ok(None);
+            }
+            match id_input.parse() {
+                Ok(id) => return Ok(Some(id)),
+                Err(_) => println!("{}", "The id should be a number.".red()),
+            }
+        }
+    }
+
+    /// Requests a page of the search, None when it does not exist
+    async fn retrieve_search_page(
+        url: &str,
+        soundeo_user: &SoundeoUser,
+    ) -> GenreTrackerResult<Option<String>> {
+        let session_cookie = soundeo_user
+            .get_session_cookie()
+            .change_context(GenreTrackerError)?;
+        let response = Client::new()
+            .get(url)
+            .header("cookie", &session_cookie)
+            .send()
+            .await
+            .into_report()
+            .change_context(GenreTrackerError)?;
+        if response.status() == 404 {
+            return Ok(None);
+        }
+        let page = response
+            .text()
+            .await
+            .into_report()
+            .change_context(GenreTrackerError)?;
+        Ok(Some(page))
+    }
+
+    async fn find_search_last_page(
+        tracked_genre: &TrackedGenre,
+        start_date: &str,
+        end_date: &str,
+        soundeo_user: &SoundeoUser,
+    ) -> GenreTrackerResult<u32> {
+        let first_page_url = tracked_genre.search_url(start_date, end_date, 1);
+        let Some(first_page) = Self::retrieve_search_page(&first_page_url, soundeo_user).await?
+        else {
+            return Ok(0);
+        };
+        if let Some(last_page) = SoundeoTracksList::parse_last_page(&first_page) {
+            return Ok(last_page);
+        }
+
+        // Without a paginator, probe the pages in case its markup changed
+        find_last_page(|page| {
+            let url = tracked_genre.search_url(start_date, end_date, page);
+            async move {
+                print!("\rChecking page {}...", page.to_string().cyan());
+                std::io::stdout().flush().unwrap();
+                Ok(Self::retrieve_search_page(&url, soundeo_user)
+                    .await?
+                    .is_some())
+            }
+        })
+        .await
+    }
+
+    /// Scrapes the tracks of a page of the search, with the title and date shown on the list
+    async fn scrape_search_page(
+        url: String,
+        soundeo_user: &SoundeoUser,
+    ) -> GenreTrackerResult<Vec<SoundeoListedTrack>> {
+        let page = Self::retrieve_search_page(&url, soundeo_user).await?;
+        Ok(page
+            .map(|page| SoundeoTracksList::parse_tracks(&page))
+            .unwrap_or_default())
+    }
+
     async fn fetch_and_queue_tracks(
         genre_id: u32,
         start_date: &str,
         end_date: &str,
-    ) -> GenreTrackerResult<()> {
+    ) -> GenreTrackerResult<CrawlSummary> {
         let mut tracker = DjWizardLog::get_genre_tracker().change_context(GenreTrackerError)?;
+        let tracked_genre = tracker
+            .tracked_genres
+            .get(&genre_id)
+            .cloned()
+            .ok_or(GenreTrackerError)
+            .into_report()?;
 
         let mut soundeo_user = SoundeoUser::new().change_context(GenreTrackerError)?;
         soundeo_user
@@ -483,140 +960,109 @@ impl GenreTrackerCommands {
             .await
             .change_context(GenreTrackerError)?;
 
-        // Phase 1: Find the last page
-        println!("Finding the last page...");
-        let mut page = 1;
-        let mut last_page = None;
-
-        loop {
-            let url = tracker.build_soundeo_url(genre_id, start_date, end_date, page);
-            
-            // Show progress
-            print!("\rChecking page {}...", page.to_string().cyan());
-            std::io::stdout().flush().unwrap();
-
-            let client = Client::new();
-            let session_cookie = soundeo_user
-                .get_session_cookie()
-                .change_context(GenreTrackerError)?;
-
-            let response = client
-                .get(&url)
-                .header("cookie", &session_cookie)
-                .send()
-                .await
-                .into_report()
-                .change_context(GenreTrackerError)?;
-
-            if response.status() == 404 {
-                last_page = Some(page - 1);
-                println!("\rFound last page: {} (checked {} pages)", (page - 1).to_string().green(), page.to_string().cyan());
-                break;
+        let checkpoint = match tracked_genre.crawl_checkpoint.clone() {
+            Some(checkpoint)
+                if checkpoint.start_date == start_date && checkpoint.end_date == end_date =>
+            {
+                println!(
+                    "Resuming the interrupted crawl at page {} of {}",
+                    checkpoint.next_page.to_string().cyan(),
+                    checkpoint.last_page.to_string().cyan()
+                );
+                checkpoint
             }
-
-            page += 1;
-        }
-
-        let last_page = match last_page {
-            Some(p) if p > 0 => p,
             _ => {
-                println!("No pages found for this date range");
-                return Ok(());
+                // Phase 1: Find the last page
+                println!("Finding the last page...");
+                let last_page = Self::find_search_last_page(
+                    &tracked_genre,
+                    start_date,
+                    end_date,
+                    &soundeo_user,
+                )
+                .await?;
+                if last_page == 0 {
+                    println!("No pages found for this date range");
+                    return Ok(CrawlSummary::default());
+                }
+                println!("\rFound last page: {}", last_page.to_string().green());
+                CrawlCheckpoint {
+                    start_date: start_date.to_string(),
+                    end_date: end_date.to_string(),
+                    min_date: tracked_genre.last_checked_date.clone(),
+                    last_page,
+                    next_page: last_page,
+                }
             }
         };
+        let last_page = checkpoint.last_page;
+        let last_checked_date = checkpoint.min_date.clone();
+        if let Some(tracked_genre) = tracker.tracked_genres.get_mut(&genre_id) {
+            tracked_genre.crawl_checkpoint = Some(checkpoint.clone());
+        }
+        DjWizardLog::save_genre_tracker(tracker.clone()).change_context(GenreTrackerError)?;
 
-        // Phase 2: Process pages from last to first
-        println!("Processing {} pages from {} to 1...", last_page, last_page);
+        // Phase 2: Process pages from last to first, scraping the next ones meanwhile
+        println!(
+            "Processing {} pages from {} to 1...",
+            checkpoint.next_page, checkpoint.next_page
+        );
 
         let mut total_added = 0;
         let mut total_skipped = 0;
+        let mut total_excluded = 0;
         let mut empty_pages_count = 0;
-        let last_checked_date = tracker
-            .tracked_genres
-            .get(&genre_id)
-            .map(|g| g.last_checked_date.clone())
-            .unwrap_or_else(|| start_date.to_string());
 
-        // Process pages in reverse order (from last_page to 1)
-        for current_page in (1..=last_page).rev() {
-            println!("\nProcessing page {} of {}", current_page, last_page);
-
-            let url = tracker.build_soundeo_url(genre_id, start_date, end_date, current_page);
+        let soundeo_user = &soundeo_user;
+        let tracked_genre = &tracked_genre;
+        let mut scraped_pages = stream::iter((1..=checkpoint.next_page).rev())
+            .map(|page| async move {
+                let url = tracked_genre.search_url(start_date, end_date, page);
+                let tracks = Self::scrape_search_page(url, soundeo_user).await?;
+                GenreTrackerResult::Ok((page, tracks))
+            })
+            .buffered(PAGES_SCRAPED_CONCURRENTLY);
 
-            // Get tracks from this page
-            let mut track_list =
-                SoundeoTracksList::new(url.clone()).change_context(GenreTrackerError)?;
-            track_list
-                .get_tracks_id(&soundeo_user)
-                .await
-                .change_context(GenreTrackerError)?;
+        while let Some(scraped_page) = scraped_pages.next().await {
+            let (current_page, tracks) = scraped_page?;
+            println!("\nProcessing page {} of {}", current_page, last_page);
 
-            if track_list.track_ids.is_empty() {
+            if tracks.is_empty() {
                 println!("No tracks found on page {}", current_page);
+                Self::save_crawl_progress(&mut tracker, genre_id, current_page, None)?;
                 continue;
             }
 
-            println!(
-                "Found {} tracks on page {}",
-                track_list.track_ids.len(),
-                current_page
-            );
-
-            // Filter tracks by date and get track info
-            let mut tracks_to_process = HashSet::new();
-            let mut most_recent_date_in_page = None;
+            // Filter tracks by date
+            let mut tracks_to_process = Vec::new();
             let mut tracks_processed_count = 0;
-
-            // Create progress bar for getting track info
-            let total_tracks = track_list.track_ids.len();
-            let pb = ProgressBar::new(total_tracks as u64);
-            pb.set_style(ProgressStyle::default_bar()
-                .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {pos}/{len} ({eta})").unwrap()
-                .progress_chars("█  "));
-            pb.set_message(format!(
-                "Getting track info for page {} ({} tracks)",
-                current_page.to_string().cyan(),
-                total_tracks.to_string().cyan()
-            ));
-
-            for (index, track_id) in track_list.track_ids.iter().enumerate() {
-                let mut track_info = SoundeoTrack::new(track_id.clone());
-                track_info
-                    .get_info(&soundeo_user, false) // Don't print for each track
-                    .await
-                    .change_context(GenreTrackerError)?;
-
-                // Update progress bar with current track title
-                pb.set_message(format!(
-                    "Getting track info for page {} ({}/{}) - {}",
-                    current_page.to_string().cyan(),
-                    (index + 1).to_string().cyan(),
-                    total_tracks.to_string().cyan(),
-                    track_info.title.clone().green()
-                ));
-
-                // Always update most recent date - check ALL tracks in page, not just processed ones
-                if most_recent_date_in_page.is_none()
-                    || track_info.date > *most_recent_date_in_page.as_ref().unwrap()
-                {
-                    most_recent_date_in_page = Some(track_info.date.clone());
-                }
-
-                // Check if track date is >= last_checked_date (include same day)
-                if track_info.date >= last_checked_date {
-                    tracks_to_process.insert(track_id.clone());
+            // Check ALL tracks in page for the most recent date, not just processed ones
+            let most_recent_date_in_page =
+                tracks.iter().filter_map(|track| track.date.clone()).max();
+
+            for track_info in tracks.iter() {
+                // Check if track date is >= last_checked_date (include same day),
+                // a track without date is processed in case it is new
+                let is_new = track_info
+                    .date
+                    .as_ref()
+                    .map_or(true, |date| *date >= last_checked_date);
+                if is_new {
                     tracks_processed_count += 1;
+                    if tracked_genre.filters.excludes(&track_info.title) {
+                        total_excluded += 1;
+                    } else {
+                        tracks_to_process.push(track_info.id.clone());
+                    }
                 }
-                
-                pb.set_position((index + 1) as u64);
             }
-            
-            pb.finish_with_message(format!(
+
+            println!(
                 "Page {} processed: {} tracks found, {} tracks to queue",
                 current_page.to_string().green(),
-                total_tracks.to_string().cyan(),
+                tracks.len().to_string().cyan(),
                 tracks_to_process.len().to_string().green()
-            ));
+            );
 
             // Process tracks if there are any to process
             if !tracks_to_process.is_empty() {
@@ -633,8 +1079,10 @@ impl GenreTrackerCommands {
 
                 let (added, skipped) = TrackProcessor::process_tracks_to_queue(
                     &tracks_to_process,
-                    &soundeo_user,
-                    Priority::Normal,
+                    soundeo_user,
+                    tracked_genre.priority,
+                    &tracked_genre.download_target,
+                    Some(format!("genre:{}", genre_name)),
                     false, // repeat_download = false for genre tracking
                     &context_description,
                 )
@@ -643,6 +1091,12 @@ impl GenreTrackerCommands {
 
                 total_added += added;
                 total_skipped += skipped;
+            } else if tracks_processed_count > 0 {
+                empty_pages_count = 0;
+                println!(
+                    "All the new tracks on page {} are excluded by keyword",
+                    current_page
+                );
             } else {
                 println!(
                     "No new tracks to process on page {} (all tracks are older than {})",
@@ -660,29 +1114,56 @@ impl GenreTrackerCommands {
                 }
             }
 
-            // ALWAYS update progress with the most recent date found in this page
-            // This ensures we don't lose progress even if no tracks were processed
-            if let Some(most_recent_date) = most_recent_date_in_page {
-                if let Some(tracked_genre) = tracker.tracked_genres.get_mut(&genre_id) {
-                    tracked_genre.last_checked_date = most_recent_date.clone();
-                }
-                DjWizardLog::save_genre_tracker(tracker.clone())
-                    .change_context(GenreTrackerError)?;
-                println!(
-                    "Progress saved - last checked date updated to: {}",
-                    most_recent_date.cyan()
-                );
-            }
+            // ALWAYS save the progress, so an interrupted crawl resumes at the next page
+            Self::save_crawl_progress(
+                &mut tracker,
+                genre_id,
+                current_page,
+                most_recent_date_in_page,
+            )?;
         }
 
+        if let Some(tracked_genre) = tracker.tracked_genres.get_mut(&genre_id) {
+            tracked_genre.crawl_checkpoint = None;
+        }
+        DjWizardLog::save_genre_tracker(tracker).change_context(GenreTrackerError)?;
+
         println!(
-            "\n{}: Added {} tracks to queue, skipped {} tracks",
+            "\n{}: Added {} tracks to queue, skipped {} tracks, excluded {} tracks by keyword",
             "Summary".green(),
             total_added.to_string().cyan(),
-            total_skipped.to_string().yellow()
+            total_skipped.to_string().yellow(),
+            total_excluded.to_string().yellow()
         );
 
-        Ok(())
+        Ok(CrawlSummary {
+            added: total_added,
+            skipped: total_skipped,
+            excluded: total_excluded,
+        })
     }
 
+    fn save_crawl_progress(
+        tracker: &mut GenreTracker,
+        genre_id: u32,
+        processed_page: u32,
+        most_recent_date: Option<String>,
+    ) -> GenreTrackerResult<()> {
+        if let Some(tracked_genre) = tracker.tracked_genres.get_mut(&genre_id) {
+            if let Some(checkpoint) = tracked_genre.crawl_checkpoint.as_mut() {
+                checkpoint.next_page = processed_page - 1;
+            }
+            if let Some(most_recent_date) = &most_recent_date {
+                tracked_genre.last_checked_date = most_recent_date.clone();
+            }
+        }
+        DjWizardLog::save_genre_tracker(tracker.clone()).change_context(GenreTrackerError)?;
+        if let Some(most_recent_date) = most_recent_date {
+            println!(
+                "Progress saved - last checked date updated to: {}",
+                most_recent_date.cyan()
+            );
+        }
+        Ok(())
+    }
 }
@@ -1,11 +1,14 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::future::Future;
 
 use chrono::Utc;
 use error_stack::{IntoReport, ResultExt};
 use serde::{Deserialize, Serialize};
 
-use crate::log::DjWizardLogResult;
+use crate::log::{DjWizardLogResult, Priority};
+use crate::soundeo::list_query::SoundeoListQuery;
+use crate::soundeo::track::DownloadTarget;
 
 pub mod commands;
 
@@ -28,18 +31,130 @@ pub struct GenreInfo {
     pub name: String,
 }
 
+/// Extra filters of the Soundeo search of a tracked genre
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SearchFilters {
+    /// Include the tracks that are not available for download
+    #[serde(default)]
+    pub all_tracks: bool,
+    #[serde(default)]
+    pub bpm_range: Option<(u32, u32)>,
+    /// Key as Soundeo writes it, e.g. "E♭min"
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub label_id: Option<u32>,
+    #[serde(default)]
+    pub artist_id: Option<u32>,
+    /// Tracks whose title contains any of these words are not queued
+    #[serde(default)]
+    pub excluded_keywords: Vec<String>,
+}
+
+impl SearchFilters {
+    pub fn apply(&self, mut query: SoundeoListQuery) -> SoundeoListQuery {
+        query = query.available_only(!self.all_tracks);
+        if let Some((min_bpm, max_bpm)) = self.bpm_range {
+            query = query.bpm_range(min_bpm, max_bpm);
+        }
+        if let Some(key) = &self.key {
+            query = query.key(key);
+        }
+        if let Some(label_id) = self.label_id {
+            query = query.label(label_id);
+        }
+        if let Some(artist_id) = self.artist_id {
+            query = query.artist(artist_id);
+        }
+        query
+    }
+
+    pub fn excludes(&self, title: &str) -> bool {
+        let title = title.to_lowercase();
+        self.excluded_keywords
+            .iter()
+            .any(|keyword| title.contains(&keyword.to_lowercase()))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut description = vec![if self.all_tracks {
+            "all tracks".to_string()
+        } else {
+            "available tracks".to_string()
+        }];
+        if let Some((min_bpm, max_bpm)) = self.bpm_range {
+            description.push(format!("{}-{} BPM", min_bpm, max_bpm));
+        }
+        if let Some(key) = &self.key {
+            description.push(format!("key {}", key));
+        }
+        if let Some(label_id) = self.label_id {
+            description.push(format!("label {}", label_id));
+        }
+        if let Some(artist_id) = self.artist_id {
+            description.push(format!("artist {}", artist_id));
+        }
+        if !self.excluded_keywords.is_empty() {
+            description.push(format!("excluding {}", self.excluded_keywords.join(", ")));
+        }
+        description.join(", ")
+    }
+}
+
+/// Progress of a crawl, so an interrupted one resumes at the next page to process
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CrawlCheckpoint {
+    pub start_date: String,
+    pub end_date: String,
+    /// Tracks released before this date are not queued
+    pub min_date: String,
+    pub last_page: u32,
+    /// Pages are processed from the last one to the first one
+    pub next_page: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TrackedGenre {
     pub genre_id: u32,
     pub genre_name: String,
     pub last_checked_date: String,
     pub created_at: String,
+    #[serde(default)]
+    pub filters: SearchFilters,
+    /// Priority of the tracks queued from this genre
+    #[serde(default = "TrackedGenre::default_priority")]
+    pub priority: Priority,
+    /// Format and folder of the tracks queued from this genre
+    #[serde(default)]
+    pub download_target: DownloadTarget,
+    #[serde(default)]
+    pub crawl_checkpoint: Option<CrawlCheckpoint>,
+}
+
+impl TrackedGenre {
+    fn default_priority() -> Priority {
+        Priority::Normal
+    }
+
+    pub fn search_url(&self, start_date: &str, end_date: &str, page: u32) -> String {
+        self.filters
+            .apply(SoundeoListQuery::new().genre(self.genre_id))
+            .date_range(start_date, end_date)
+            .page(page)
+            .build()
+    }
 }
 
+/// Days after which the genre list is scraped again from Soundeo
+pub const GENRES_REFRESH_DAYS: u64 = 7;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct GenreTracker {
     pub tracked_genres: HashMap<u32, TrackedGenre>,
     pub available_genres: HashMap<u32, GenreInfo>,
+    /// Unix time of the last genre list scraped from Soundeo, 0 if never
+    #[serde(default)]
+    pub genres_updated_at: u64,
 }
 
 impl Default for GenreTracker {
@@ -51,21 +166,37 @@ impl Default for GenreTracker {
 impl GenreTracker {
     pub fn new() -> Self {
         let mut available_genres = HashMap::new();
-        
-        // Only Drum and Bass is enabled for now
-        // More genres will be added manually after confirming their IDs
-        available_genres.insert(1, GenreInfo { id: 1, name: "Drum and Bass".to_string() });
-        
-        // TODO: Add more genres after confirming their IDs on Soundeo
-        // available_genres.insert(2, GenreInfo { id: 2, name: "Dubstep".to_string() });
-        // available_genres.insert(3, GenreInfo { id: 3, name: "Techno".to_string() });
-        // available_genres.insert(4, GenreInfo { id: 4, name: "House".to_string() });
-        // etc...
+
+        // Drum and Bass is the only genre known until the list is scraped from Soundeo
+        available_genres.insert(1, GenreInfo { id: 1, name: "Drum & Bass".to_string() });
 
         Self {
             tracked_genres: HashMap::new(),
             available_genres,
+            genres_updated_at: 0,
+        }
+    }
+
+    pub fn needs_genres_refresh(&self, now: u64) -> bool {
+        now.saturating_sub(self.genres_updated_at) >= GENRES_REFRESH_DAYS * 24 * 60 * 60
+    }
+
+    /// Replaces the available genres with the ones scraped from Soundeo,
+    /// keeping the tracked genres selectable even if Soundeo dropped them
+    pub fn set_available_genres(&mut self, genres: Vec<(u32, String)>, now: u64) {
+        self.available_genres = genres
+            .into_iter()
+            .map(|(id, name)| (id, GenreInfo { id, name }))
+            .collect();
+        for tracked_genre in self.tracked_genres.values() {
+            self.available_genres
+                .entry(tracked_genre.genre_id)
+                .or_insert_with(|| GenreInfo {
+                    id: tracked_genre.genre_id,
+                    name: tracked_genre.genre_name.clone(),
+                });
         }
+        self.genres_updated_at = now;
     }
 
     pub fn add_tracked_genre(&mut self, genre_id: u32) -> GenreTrackerResult<()> {
@@ -76,6 +207,10 @@ impl GenreTracker {
                 genre_name: genre_info.name.clone(),
                 last_checked_date: now.clone(),
                 created_at: now,
+                filters: SearchFilters::default(),
+                priority: TrackedGenre::default_priority(),
+                download_target: DownloadTarget::default(),
+                crawl_checkpoint: None,
             };
             self.tracked_genres.insert(genre_id, tracked_genre);
             Ok(())
@@ -92,16 +227,94 @@ impl GenreTracker {
             Err(GenreTrackerError).into_report()
         }
     }
+}
 
-    pub fn build_soundeo_url(&self, genre_id: u32, start_date: &str, end_date: &str, page: u32) -> String {
-        format!(
-            "https://soundeo.com/list/tracks?availableFilter=1&genreFilter={}&timeFilter=r_{}_{}&page={}",
-            genre_id, start_date, end_date, page
-        )
+/// Finds the last page of a list with exponential probing followed by a binary search,
+/// relying on every page up to the last one existing
+pub async fn find_last_page<F, Fut>(mut page_exists: F) -> GenreTrackerResult<u32>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = GenreTrackerResult<bool>>,
+{
+    if !page_exists(1).await? {
+        return Ok(0);
+    }
+    let mut existing_page = 1;
+    let mut missing_page = 2;
+    while page_exists(missing_page).await? {
+        existing_page = missing_page;
+        missing_page *= 2;
+    }
+    while missing_page - existing_page > 1 {
+        let middle_page = existing_page + (missing_page - existing_page) / 2;
+        if page_exists(middle_page).await? {
+            existing_page = middle_page;
+        } else {
+            missing_page = middle_page;
+        }
     }
+    Ok(existing_page)
 }
 
 pub trait GenreTrackerCRUD {
     fn get_genre_tracker() -> DjWizardLogResult<GenreTracker>;
     fn save_genre_tracker(tracker: GenreTracker) -> DjWizardLogResult<()>;
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_url_with_filters() {
+        let mut tracker = GenreTracker::new();
+        tracker.add_tracked_genre(1).unwrap();
+        let tracked_genre = tracker.tracked_genres.get_mut(&1).unwrap();
+        tracked_genre.filters = SearchFilters {
+            all_tracks: true,
+            bpm_range: Some((172, 176)),
+            label_id: Some(2398),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            tracked_genre.search_url("2024-01-01", "2024-02-01", 2),
+            "https://soundeo.com/list/tracks?availableFilter=0&labelFilter=2398&genreFilter=1&timeFilter=r_2024-01-01_2024-02-01&bpmFilter=172%2C176&page=2"
+        );
+    }
+
+    async fn probe_last_page(last_page: u32) -> (u32, u32) {
+        let mut requests = 0;
+        let found_page = find_last_page(|page| {
+            requests += 1;
+            async move { Ok(page <= last_page) }
+        })
+        .await
+        .unwrap();
+        (found_page, requests)
+    }
+
+    #[tokio::test]
+    async fn test_find_last_page() {
+        assert_eq!(probe_last_page(0).await, (0, 1));
+        assert_eq!(probe_last_page(1).await, (1, 2));
+        assert_eq!(probe_last_page(2).await.0, 2);
+        assert_eq!(probe_last_page(64).await.0, 64);
+
+        let (found_page, requests) = probe_last_page(241).await;
+        assert_eq!(found_page, 241);
+        assert!(requests <= 16);
+    }
+
+    #[test]
+    fn test_excluded_keywords_ignore_case() {
+        let filters = SearchFilters {
+            excluded_keywords: vec!["radio edit".to_string(), "Acapella".to_string()],
+            ..Default::default()
+        };
+
+        assert!(filters.excludes("Come Together (Radio Edit)"));
+        assert!(filters.excludes("Original Don (ACAPELLA)"));
+        assert!(!filters.excludes("Original Don (Extended Mix)"));
+    }
+}
@@ -0,0 +1,88 @@
+use chrono::{Local, TimeZone};
+use colored::Colorize;
+use comfy_table::{Cell, Color, Table};
+use error_stack::ResultExt;
+
+use crate::log::DjWizardLog;
+use crate::output::OutputFormat;
+
+use super::{HistoryCRUD, HistoryError, HistoryResult, RunRecord, RunStatus};
+
+pub struct HistoryCommands;
+
+impl HistoryCommands {
+    /// Prints the most recent runs, newest first
+    pub fn execute(limit: usize) -> HistoryResult<()> {
+        let mut run_history = DjWizardLog::get_run_history().change_context(HistoryError)?;
+        run_history.reverse();
+        let total = run_history.len();
+        run_history.truncate(limit);
+
+        if OutputFormat::is_json() {
+            return OutputFormat::print_json(&serde_json::json!({
+                "runs": run_history,
+                "total": total,
+            }))
+            .change_context(HistoryError);
+        }
+
+        if run_history.is_empty() {
+            println!("{}", "No runs recorded yet.".yellow());
+            return Ok(());
+        }
+        Self::print_history_table(&run_history);
+        println!(
+            "Showing {} of {} recorded runs",
+            run_history.len().to_string().cyan(),
+            total.to_string().cyan()
+        );
+        Ok(())
+    }
+
+    fn print_history_table(run_history: &[RunRecord]) {
+        let mut table = Table::new();
+        table.set_header(vec![
+            "Started",
+            "Command",
+            "Duration",
+            "Status",
+            "Queued",
+            "Downloaded",
+            "Failed",
+            "Credits used",
+        ]);
+        for run in run_history {
+            let status = match run.status {
+                RunStatus::Succeeded => Cell::new(run.status).fg(Color::Green),
+                RunStatus::Failed => Cell::new(run.status).fg(Color::Red),
+            };
+            table.add_row(vec![
+                Cell::new(Self::format_timestamp(run.started_at)),
+                Cell::new(&run.command).fg(Color::Blue),
+                Cell::new(Self::format_duration(run.duration_secs())),
+                status,
+                Cell::new(run.tracks_queued).fg(Color::Cyan),
+                Cell::new(run.tracks_downloaded).fg(Color::Green),
+                Cell::new(run.tracks_failed).fg(Color::Red),
+                Cell::new(run.credits_used).fg(Color::Yellow),
+            ]);
+        }
+        println!("{table}");
+    }
+
+    fn format_timestamp(timestamp: u64) -> String {
+        Local
+            .timestamp_opt(timestamp as i64, 0)
+            .single()
+            .map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_else(|| timestamp.to_string())
+    }
+
+    fn format_duration(duration_secs: u64) -> String {
+        match duration_secs {
+            0..=59 => format!("{}s", duration_secs),
+            60..=3599 => format!("{}m {}s", duration_secs / 60, duration_secs % 60),
+            _ => format!("{}h {}m", duration_secs / 3600, (duration_secs % 3600) / 60),
+        }
+    }
+}
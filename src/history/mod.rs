@@ -0,0 +1,188 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::log::{DjWizardLog, DjWizardLogResult};
+
+pub mod commands;
+
+#[derive(Debug)]
+pub struct HistoryError;
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("History error")
+    }
+}
+
+impl std::error::Error for HistoryError {}
+
+pub type HistoryResult<T> = error_stack::Result<T, HistoryError>;
+
+/// Older records are dropped once the history reaches this size
+pub const MAX_RUN_RECORDS: usize = 500;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, strum_macros::Display)]
+pub enum RunStatus {
+    Succeeded,
+    Failed,
+}
+
+/// One invocation of the CLI, stored in the log once the command ends
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RunRecord {
+    pub command: String,
+    pub started_at: u64,
+    pub finished_at: u64,
+    pub status: RunStatus,
+    pub tracks_queued: u32,
+    pub tracks_downloaded: u32,
+    pub tracks_failed: u32,
+    pub credits_used: u32,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl RunRecord {
+    fn new(command: String, started_at: u64) -> Self {
+        Self {
+            command,
+            started_at,
+            finished_at: started_at,
+            status: RunStatus::Succeeded,
+            tracks_queued: 0,
+            tracks_downloaded: 0,
+            tracks_failed: 0,
+            credits_used: 0,
+            error: None,
+        }
+    }
+
+    pub fn duration_secs(&self) -> u64 {
+        self.finished_at.saturating_sub(self.started_at)
+    }
+}
+
+pub trait HistoryCRUD {
+    fn get_run_history() -> DjWizardLogResult<Vec<RunRecord>>;
+    fn add_run_record(run_record: RunRecord) -> DjWizardLogResult<()>;
+}
+
+struct ActiveRun {
+    record: RunRecord,
+    /// Last remaining credits reported by Soundeo, to add up what the run spent
+    last_remaining_credits: Option<u32>,
+}
+
+impl ActiveRun {
+    fn new(command: String, started_at: u64) -> Self {
+        Self {
+            record: RunRecord::new(command, started_at),
+            last_remaining_credits: None,
+        }
+    }
+
+    /// Credits only go down while the run spends them, an increase means the quota was reset
+    fn observe_remaining_credits(&mut self, remaining_credits: u32) {
+        if let Some(last) = self.last_remaining_credits {
+            self.record.credits_used += last.saturating_sub(remaining_credits);
+        }
+        self.last_remaining_credits = Some(remaining_credits);
+    }
+}
+
+static ACTIVE_RUN: Mutex<Option<ActiveRun>> = Mutex::new(None);
+
+/// Collects the counters of the current invocation from anywhere in the code
+pub struct RunHistory;
+
+impl RunHistory {
+    pub fn start(command: String) {
+        log::info!("Started `{}`", command);
+        Self::set_active_run(Some(ActiveRun::new(command, Self::now())));
+    }
+
+    pub fn track_queued() {
+        Self::update(|run| run.record.tracks_queued += 1);
+    }
+
+    pub fn track_downloaded() {
+        Self::update(|run| run.record.tracks_downloaded += 1);
+    }
+
+    pub fn track_failed() {
+        Self::update(|run| run.record.tracks_failed += 1);
+    }
+
+    pub fn observe_remaining_credits(remaining_credits: u32) {
+        Self::update(|run| run.observe_remaining_credits(remaining_credits));
+    }
+
+    /// Stores the record of the current run, failing to save it never fails the command
+    pub fn finish(error: Option<String>) {
+        let Some(ActiveRun { mut record, .. }) = Self::take_active_run() else {
+            return;
+        };
+        record.finished_at = Self::now();
+        record.status = match error {
+            Some(_) => RunStatus::Failed,
+            None => RunStatus::Succeeded,
+        };
+        record.error = error;
+        log::info!(
+            "Finished `{}` ({}): {} queued, {} downloaded, {} failed, {} credits used",
+            record.command,
+            record.status,
+            record.tracks_queued,
+            record.tracks_downloaded,
+            record.tracks_failed,
+            record.credits_used
+        );
+        if let Err(error) = DjWizardLog::add_run_record(record) {
+            log::warn!("Failed to save the run history: {:?}", error);
+        }
+    }
+
+    fn update(update: impl FnOnce(&mut ActiveRun)) {
+        if let Ok(mut active_run) = ACTIVE_RUN.lock() {
+            if let Some(run) = active_run.as_mut() {
+                update(run);
+            }
+        }
+    }
+
+    fn set_active_run(run: Option<ActiveRun>) {
+        if let Ok(mut active_run) = ACTIVE_RUN.lock() {
+            *active_run = run;
+        }
+    }
+
+    fn take_active_run() -> Option<ActiveRun> {
+        ACTIVE_RUN.lock().ok().and_then(|mut run| run.take())
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credits_used_add_up_across_a_quota_reset() {
+        let mut run = ActiveRun::new("dj-wizard queue -r".to_string(), 0);
+        for remaining_credits in [10, 9, 7, 150, 148] {
+            run.observe_remaining_credits(remaining_credits);
+        }
+
+        assert_eq!(run.record.credits_used, 5);
+        assert_eq!(run.record.command, "dj-wizard queue -r");
+    }
+}
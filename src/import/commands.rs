@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use colored::Colorize;
+use error_stack::{IntoReport, ResultExt};
+use inflector::Inflector;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::dialoguer::Dialoguer;
+use crate::import::{
+    match_tracks_info, rekordbox, soundeo_match_key, spotify_pairings, traktor, ImportError,
+    ImportResult, LibraryTrack,
+};
+use crate::log::DjWizardLog;
+use crate::soundeo::search_bar::SoundeoSearchBar;
+use crate::soundeo::track::SoundeoTrack;
+use crate::soundeo::SoundeoCRUD;
+use crate::spotify::SpotifyCRUD;
+use crate::user::SoundeoUser;
+
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+    Subcommand,
+    PartialEq,
+)]
+pub enum ImportCommands {
+    /// Mark the tracks of a rekordbox.xml collection as owned.
+    Rekordbox {
+        /// Path of the xml exported from Rekordbox, prompted if not provided
+        #[clap(long)]
+        file: Option<String>,
+        /// Only match the tracks already stored, without searching Soundeo
+        #[clap(long, action)]
+        no_search: bool,
+    },
+    /// Mark the tracks of a Traktor collection.nml as owned.
+    Traktor {
+        /// Path of the nml collection, prompted if not provided
+        #[clap(long)]
+        file: Option<String>,
+        /// Only match the tracks already stored, without searching Soundeo
+        #[clap(long, action)]
+        no_search: bool,
+    },
+}
+
+impl ImportCommands {
+    pub async fn execute(command: Option<ImportCommands>) -> ImportResult<()> {
+        let command = match command {
+            Some(command) => command,
+            None => {
+                let options = Self::get_options();
+                let selection =
+                    Dialoguer::select("What you want to do?".to_string(), options, None)
+                        .change_context(ImportError)?;
+                Self::get_selection(selection)
+            }
+        };
+        let (library_tracks, no_search) = match command {
+            ImportCommands::Rekordbox { file, no_search } => {
+                let content = Self::read_collection(file, "rekordbox.xml")?;
+                (rekordbox::parse(&content), no_search)
+            }
+            ImportCommands::Traktor { file, no_search } => {
+                let content = Self::read_collection(file, "collection.nml")?;
+                (traktor::parse(&content), no_search)
+            }
+        };
+        Self::import_library(library_tracks, no_search).await
+    }
+
+    fn get_options() -> Vec<String> {
+        Self::iter()
+            .map(|element| element.to_string().to_sentence_case())
+            .collect::<Vec<_>>()
+    }
+
+    fn get_selection(selection: usize) -> Self {
+        let options = Self::iter().collect::<Vec<_>>();
+        options[selection].clone()
+    }
+
+    fn read_collection(file: Option<String>, file_name: &str) -> ImportResult<String> {
+        let file_path = match file {
+            Some(file) => PathBuf::from(file),
+            None => {
+                let prompt_text = format!("Path of the {} file: ", file_name);
+                PathBuf::from(Dialoguer::input(prompt_text).change_context(ImportError)?)
+            }
+        };
+        fs::read_to_string(&file_path)
+            .into_report()
+            .attach_printable(format!(
+                "Failed to read the collection at {}",
+                file_path.display()
+            ))
+            .change_context(ImportError)
+    }
+
+    async fn import_library(
+        library_tracks: Vec<LibraryTrack>,
+        no_search: bool,
+    ) -> ImportResult<()> {
+        println!(
+            "Found {} tracks in the collection",
+            library_tracks.len().to_string().cyan()
+        );
+        let soundeo_log = DjWizardLog::get_soundeo().change_context(ImportError)?;
+        let mut matches = match_tracks_info(&library_tracks, &soundeo_log.tracks_info);
+        let matched_locally = matches.len();
+        println!(
+            "{} tracks matched the stored Soundeo tracks",
+            matched_locally.to_string().green()
+        );
+
+        let unmatched: Vec<usize> = (0..library_tracks.len())
+            .filter(|position| !matches.contains_key(position))
+            .collect();
+        let mut failed_searches = 0;
+        if !no_search && !unmatched.is_empty() {
+            let mut soundeo_user = SoundeoUser::new().change_context(ImportError)?;
+            soundeo_user
+                .login_and_update_user_info()
+                .await
+                .change_context(ImportError)?;
+            for (index, position) in unmatched.iter().enumerate() {
+                let library_track = &library_tracks[*position];
+                println!(
+                    "Searching {} of {}: {}",
+                    (index + 1).to_string().cyan(),
+                    unmatched.len(),
+                    library_track.get_search_term()
+                );
+                // A failed search leaves the track unmatched, the other matches are still stored
+                match Self::search_soundeo_track(library_track, &soundeo_user).await {
+                    Ok(Some(soundeo_id)) => {
+                        matches.insert(*position, soundeo_id);
+                    }
+                    Ok(None) => {}
+                    Err(error) => {
+                        log::warn!(
+                            "Search of {} failed: {:?}",
+                            library_track.get_search_term(),
+                            error
+                        );
+                        println!(
+                            "{}",
+                            format!(
+                                "Could not search {}: {:?}",
+                                library_track.get_search_term(),
+                                error
+                            )
+                            .red()
+                        );
+                        failed_searches += 1;
+                    }
+                }
+            }
+        }
+
+        let owned_tracks: HashMap<String, PathBuf> = matches
+            .iter()
+            .map(|(position, soundeo_id)| {
+                (soundeo_id.clone(), library_tracks[*position].path.clone())
+            })
+            .collect();
+        let removed_from_queue =
+            DjWizardLog::mark_tracks_as_owned(owned_tracks).change_context(ImportError)?;
+
+        let owned_ids: HashMap<String, String> = matches
+            .iter()
+            .map(|(position, soundeo_id)| {
+                (library_tracks[*position].match_key(), soundeo_id.clone())
+            })
+            .collect();
+        let spotify = DjWizardLog::get_spotify().change_context(ImportError)?;
+        let pairings = spotify_pairings(&spotify, &owned_ids);
+        for (spotify_track_id, soundeo_track_id) in &pairings {
+            DjWizardLog::update_spotify_to_soundeo_track(
+                spotify_track_id.clone(),
+                Some(soundeo_track_id.clone()),
+            )
+            .change_context(ImportError)?;
+        }
+
+        println!(
+            "{}: {} tracks marked as owned ({} stored, {} found on Soundeo), {} not found ({} failed searches), {} Spotify tracks paired, {} removed from the queue",
+            "Summary".green(),
+            matches.len().to_string().green(),
+            matched_locally,
+            matches.len() - matched_locally,
+            (library_tracks.len() - matches.len()).to_string().yellow(),
+            failed_searches.to_string().red(),
+            pairings.len().to_string().cyan(),
+            removed_from_queue.to_string().cyan()
+        );
+        Ok(())
+    }
+
+    /// Only accepts a search result with the same artists, title and version as the library
+    /// track, trying the fallback search terms until one has it
+    async fn search_soundeo_track(
+        library_track: &LibraryTrack,
+        soundeo_user: &SoundeoUser,
+    ) -> ImportResult<Option<String>> {
+        let match_key = library_track.match_key();
+        let mut found = None;
+        for term in library_track.get_search_terms() {
+            let search_results = SoundeoSearchBar::Tracks
+                .search_term(term, soundeo_user)
+                .await
+                .change_context(ImportError)?;
+            found = search_results
+                .into_iter()
+                .find(|result| soundeo_match_key(&result.label) == match_key);
+            if found.is_some() {
+                break;
+            }
+        }
+        let Some(search_result) = found else {
+            return Ok(None);
+        };
+        // Stores the track info, marking it as owned requires it
+        let mut soundeo_track = SoundeoTrack::new(search_result.value.clone());
+        soundeo_track
+            .get_info(soundeo_user, false)
+            .await
+            .change_context(ImportError)?;
+        Ok(Some(search_result.value))
+    }
+}
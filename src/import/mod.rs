@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::normalize::{search_terms, TrackName};
+use crate::soundeo::track::SoundeoTrack;
+use crate::spotify::Spotify;
+
+pub mod commands;
+pub mod rekordbox;
+pub mod traktor;
+mod xml;
+
+#[derive(Debug)]
+pub struct ImportError;
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Import error")
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+pub type ImportResult<T> = error_stack::Result<T, ImportError>;
+
+/// A track of the collection of a DJ software
+#[derive(Debug, Clone, PartialEq)]
+pub struct LibraryTrack {
+    pub artist: String,
+    pub title: String,
+    pub path: PathBuf,
+}
+
+impl LibraryTrack {
+    pub fn match_key(&self) -> String {
+        match_key(&self.artist, &self.title)
+    }
+
+    pub fn get_search_term(&self) -> String {
+        format!("{} - {}", self.artist, self.title)
+    }
+
+    pub fn get_search_terms(&self) -> Vec<String> {
+        search_terms(&self.artist, &self.title)
+    }
+}
+
+/// Key used to compare tracks across Soundeo, Spotify and the DJ software,
+/// see [`TrackName::key`]
+pub fn match_key(artist: &str, title: &str) -> String {
+    TrackName::parse(artist, title).key()
+}
+
+/// Soundeo titles already are "Artist - Title (Mix)"
+pub fn soundeo_match_key(title: &str) -> String {
+    TrackName::from_soundeo_title(title).key()
+}
+
+/// Position of the library tracks matching a stored Soundeo track, and the id of that track
+pub fn match_tracks_info(
+    library_tracks: &[LibraryTrack],
+    tracks_info: &HashMap<String, SoundeoTrack>,
+) -> HashMap<usize, String> {
+    let soundeo_ids: HashMap<String, &String> = tracks_info
+        .values()
+        .map(|track| (soundeo_match_key(&track.title), &track.id))
+        .collect();
+    library_tracks
+        .iter()
+        .enumerate()
+        .filter_map(|(position, library_track)| {
+            soundeo_ids
+                .get(&library_track.match_key())
+                .map(|soundeo_id| (position, soundeo_id.to_string()))
+        })
+        .collect()
+}
+
+/// Spotify tracks without a Soundeo track whose file is owned, paired to the owned Soundeo track.
+/// `owned_ids` maps the match key of the owned tracks to their Soundeo id
+pub fn spotify_pairings(
+    spotify: &Spotify,
+    owned_ids: &HashMap<String, String>,
+) -> HashMap<String, String> {
+    spotify
+        .playlists
+        .values()
+        .flat_map(|playlist| playlist.tracks.values())
+        .filter(|spotify_track| {
+            !matches!(
+                spotify
+                    .soundeo_track_ids
+                    .get(&spotify_track.spotify_track_id),
+                Some(Some(_))
+            )
+        })
+        .filter_map(|spotify_track| {
+            owned_ids
+                .get(&match_key(&spotify_track.artists, &spotify_track.title))
+                .map(|soundeo_id| (spotify_track.spotify_track_id.clone(), soundeo_id.clone()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spotify::playlist::SpotifyPlaylist;
+    use crate::spotify::track::SpotifyTrack;
+
+    fn library_track(artist: &str, title: &str) -> LibraryTrack {
+        LibraryTrack {
+            artist: artist.to_string(),
+            title: title.to_string(),
+            path: PathBuf::from(format!("/music/{} - {}.aiff", artist, title)),
+        }
+    }
+
+    #[test]
+    fn test_match_stored_tracks_and_pair_spotify_tracks() {
+        let mut stored_track = SoundeoTrack::new("1".to_string());
+        stored_track.title = "Calibre - Mr  Majestic (Original Mix)".to_string();
+        let tracks_info = HashMap::from([("1".to_string(), stored_track)]);
+        let library_tracks = vec![
+            library_track("Alix Perez", "Burden"),
+            library_track("CALIBRE", "Mr Majestic (Original Mix)"),
+        ];
+
+        let matches = match_tracks_info(&library_tracks, &tracks_info);
+
+        assert_eq!(matches, HashMap::from([(1, "1".to_string())]));
+
+        let mut playlist =
+            SpotifyPlaylist::new("https://open.spotify.com/playlist/liquid".to_string()).unwrap();
+        for (id, artists, title) in [
+            ("unpaired", "Calibre", "Mr Majestic (Original Mix)"),
+            ("paired", "Calibre", "Mr Majestic (Original Mix)"),
+            ("no_match", "Calibre", "Mr Majestic (Original Mix)"),
+            ("not_owned", "Alix Perez", "Burden"),
+        ] {
+            playlist.tracks.insert(
+                id.to_string(),
+                SpotifyTrack::new(title.to_string(), artists.to_string(), id.to_string(), None),
+            );
+        }
+        let mut spotify = Spotify::new();
+        spotify
+            .playlists
+            .insert(playlist.spotify_playlist_id.clone(), playlist);
+        spotify
+            .soundeo_track_ids
+            .insert("paired".to_string(), Some("2".to_string()));
+        spotify
+            .soundeo_track_ids
+            .insert("no_match".to_string(), None);
+        let owned_ids = HashMap::from([(library_tracks[1].match_key(), "1".to_string())]);
+
+        assert_eq!(
+            spotify_pairings(&spotify, &owned_ids),
+            HashMap::from([
+                ("unpaired".to_string(), "1".to_string()),
+                ("no_match".to_string(), "1".to_string()),
+            ])
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use std::path::PathBuf;
+
+use crate::import::xml;
+use crate::import::LibraryTrack;
+
+/// Reads the tracks of the COLLECTION of a `rekordbox.xml`, the playlist entries only hold a key
+pub fn parse(content: &str) -> Vec<LibraryTrack> {
+    let Some(collection) = xml::elements(content, "COLLECTION").into_iter().next() else {
+        return vec![];
+    };
+    xml::elements(collection.inner, "TRACK")
+        .into_iter()
+        .filter_map(|track| {
+            let path = location_to_path(track.attribute("Location")?)?;
+            Some(LibraryTrack {
+                artist: track.attribute("Artist").unwrap_or_default().to_string(),
+                title: track.attribute("Name").unwrap_or_default().to_string(),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Rekordbox locations are `file://localhost/` urls
+fn location_to_path(location: &str) -> Option<PathBuf> {
+    let location = location.replacen("file://localhost/", "file:///", 1);
+    url::Url::parse(&location).ok()?.to_file_path().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_collection_tracks() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8"?>
+<DJ_PLAYLISTS Version="1.0.0">
+  <COLLECTION Entries="2">
+    <TRACK TrackID="1" Name="Mr Majestic" Artist="Calibre" Location="file://localhost/music/Drum%20&amp;%20Bass/Calibre%20-%20Mr%20Majestic.aiff">
+      <TEMPO Inizio="0.025" Bpm="174.00"/>
+    </TRACK>
+    <TRACK TrackID="2" Name="No Location" Artist="Unknown"/>
+  </COLLECTION>
+  <PLAYLISTS>
+    <NODE Type="0" Name="ROOT" Count="1">
+      <NODE Name="Liquid" Type="1" KeyType="0" Entries="1">
+        <TRACK Key="1"/>
+      </NODE>
+    </NODE>
+  </PLAYLISTS>
+</DJ_PLAYLISTS>"#;
+
+        let tracks = parse(content);
+
+        assert_eq!(
+            tracks,
+            vec![LibraryTrack {
+                artist: "Calibre".to_string(),
+                title: "Mr Majestic".to_string(),
+                path: PathBuf::from("/music/Drum & Bass/Calibre - Mr Majestic.aiff"),
+            }]
+        );
+    }
+}
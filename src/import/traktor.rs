@@ -0,0 +1,93 @@
+use std::path::{Path, PathBuf};
+
+use crate::export::traktor::DEFAULT_VOLUME;
+use crate::import::xml;
+use crate::import::LibraryTrack;
+
+/// Reads the tracks of the COLLECTION of a Traktor `collection.nml`
+pub fn parse(content: &str) -> Vec<LibraryTrack> {
+    let Some(collection) = xml::elements(content, "COLLECTION").into_iter().next() else {
+        return vec![];
+    };
+    xml::elements(collection.inner, "ENTRY")
+        .into_iter()
+        .filter_map(|entry| {
+            let location = xml::elements(entry.inner, "LOCATION").into_iter().next()?;
+            let path = location_to_path(
+                location.attribute("VOLUME").unwrap_or_default(),
+                location.attribute("DIR").unwrap_or_default(),
+                location.attribute("FILE")?,
+            );
+            Some(LibraryTrack {
+                artist: entry.attribute("ARTIST").unwrap_or_default().to_string(),
+                title: entry.attribute("TITLE").unwrap_or_default().to_string(),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Traktor splits paths in the volume, the folders joined by `/:` and the file name.
+/// macOS volumes other than the system disk are mounted at `/Volumes/<volume>`
+fn location_to_path(volume: &str, dir: &str, file: &str) -> PathBuf {
+    let relative: PathBuf = dir
+        .split("/:")
+        .filter(|folder| !folder.is_empty())
+        .chain(std::iter::once(file))
+        .collect();
+    if volume.ends_with(':') {
+        return Path::new(&format!("{}\\", volume)).join(relative);
+    }
+    let on_root = Path::new("/").join(&relative);
+    if volume.is_empty() || volume == DEFAULT_VOLUME || on_root.exists() {
+        on_root
+    } else {
+        Path::new("/Volumes").join(volume).join(relative)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_collection_entries() {
+        let content = r#"<?xml version="1.0" encoding="UTF-8" standalone="no" ?>
+<NML VERSION="19">
+  <COLLECTION ENTRIES="2">
+    <ENTRY TITLE="Mr Majestic" ARTIST="Calibre">
+      <LOCATION DIR="/:Users/:dj/:Music/:" FILE="Calibre - Mr Majestic.aiff" VOLUME="Macintosh HD" VOLUMEID="1"></LOCATION>
+      <INFO GENRE="Drum &amp; Bass"></INFO>
+    </ENTRY>
+    <ENTRY TITLE="Burden" ARTIST="Alix Perez">
+      <LOCATION DIR="/:dj-wizard-missing-folder/:" FILE="Alix Perez - Burden.aiff" VOLUME="USB" VOLUMEID="2"></LOCATION>
+    </ENTRY>
+  </COLLECTION>
+  <PLAYLISTS>
+    <NODE TYPE="FOLDER" NAME="$ROOT">
+      <ENTRY><PRIMARYKEY TYPE="TRACK" KEY="Macintosh HD/:Users/:dj/:Music/:Calibre - Mr Majestic.aiff"></PRIMARYKEY></ENTRY>
+    </NODE>
+  </PLAYLISTS>
+</NML>"#;
+
+        let tracks = parse(content);
+
+        assert_eq!(
+            tracks,
+            vec![
+                LibraryTrack {
+                    artist: "Calibre".to_string(),
+                    title: "Mr Majestic".to_string(),
+                    path: PathBuf::from("/Users/dj/Music/Calibre - Mr Majestic.aiff"),
+                },
+                LibraryTrack {
+                    artist: "Alix Perez".to_string(),
+                    title: "Burden".to_string(),
+                    path: PathBuf::from(
+                        "/Volumes/USB/dj-wizard-missing-folder/Alix Perez - Burden.aiff"
+                    ),
+                },
+            ]
+        );
+    }
+}
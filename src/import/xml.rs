@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+/// An element of a DJ software collection file, with its attributes already unescaped
+#[derive(Debug, Clone, PartialEq)]
+pub struct XmlElement<'a> {
+    pub attributes: HashMap<String, String>,
+    /// Content between the opening and closing tags, empty for self-closing elements
+    pub inner: &'a str,
+}
+
+impl<'a> XmlElement<'a> {
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name).map(String::as_str)
+    }
+}
+
+/// Every `name` element of `content`, in document order.
+/// Only meant for the flat collection files of DJ software, elements of the same name can't nest
+pub fn elements<'a>(content: &'a str, name: &str) -> Vec<XmlElement<'a>> {
+    let opening = format!("<{}", name);
+    let closing = format!("</{}>", name);
+    let mut elements = vec![];
+    let mut rest = content;
+    while let Some(start) = rest.find(&opening) {
+        let after_name = &rest[start + opening.len()..];
+        // Skip longer names sharing the prefix, e.g. TRACKS when looking for TRACK
+        if !after_name.starts_with(|c: char| c.is_whitespace() || c == '/' || c == '>') {
+            rest = after_name;
+            continue;
+        }
+        let Some(tag_end) = find_tag_end(after_name) else {
+            break;
+        };
+        let self_closing = after_name[..tag_end].ends_with('/');
+        let attributes = parse_attributes(after_name[..tag_end].trim_end_matches('/'));
+        let body = &after_name[tag_end + 1..];
+        if self_closing {
+            elements.push(XmlElement {
+                attributes,
+                inner: "",
+            });
+            rest = body;
+            continue;
+        }
+        match body.find(&closing) {
+            Some(inner_end) => {
+                elements.push(XmlElement {
+                    attributes,
+                    inner: &body[..inner_end],
+                });
+                rest = &body[inner_end + closing.len()..];
+            }
+            None => {
+                elements.push(XmlElement {
+                    attributes,
+                    inner: body,
+                });
+                break;
+            }
+        }
+    }
+    elements
+}
+
+/// Position of the `>` closing the tag, ignoring the ones inside quoted values
+fn find_tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (position, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, '>') => return Some(position),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = tag.trim_start();
+    while let Some(equals) = rest.find('=') {
+        let name = rest[..equals].trim().to_string();
+        let value_start = rest[equals + 1..].trim_start();
+        let Some(quote) = value_start
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+        else {
+            break;
+        };
+        let Some(value_end) = value_start[1..].find(quote) else {
+            break;
+        };
+        attributes.insert(name, unescape(&value_start[1..value_end + 1]));
+        rest = value_start[value_end + 2..].trim_start();
+    }
+    attributes
+}
+
+/// Reverts the predefined entities and the numeric character references
+pub fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let entity = &rest[start..];
+        let Some(end) = entity.find(';') else {
+            unescaped.push_str(entity);
+            return unescaped;
+        };
+        let name = &entity[1..end];
+        let character = match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => name
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| name.strip_prefix('#').map(|decimal| decimal.parse::<u32>()))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => unescaped.push(character),
+            None => unescaped.push_str(&entity[..=end]),
+        }
+        rest = &entity[end + 1..];
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elements_attributes_and_inner_content() {
+        let content = r#"<TRACKS><TRACK Name="A &amp; B" Artist='C &#39;D&#x27;'>
+            <TEMPO Bpm="174"/>
+        </TRACK><TRACK Key="1"/></TRACKS>"#;
+
+        let tracks = elements(content, "TRACK");
+
+        assert_eq!(tracks.len(), 2);
+        assert_eq!(tracks[0].attribute("Name"), Some("A & B"));
+        assert_eq!(tracks[0].attribute("Artist"), Some("C 'D'"));
+        assert_eq!(
+            elements(tracks[0].inner, "TEMPO")[0].attribute("Bpm"),
+            Some("174")
+        );
+        assert_eq!(tracks[1].attribute("Key"), Some("1"));
+        assert_eq!(tracks[1].inner, "");
+    }
+}
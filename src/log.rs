@@ -1,11 +1,11 @@
 use std::collections::{HashMap, HashSet};
 use std::fs::read_to_string;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::{fmt, fs};
 
 use crate::artist::{ArtistCRUD, ArtistManager};
-use crate::url_list::UrlListCRUD;
+use crate::url_list::{TrackedSource, UrlListCRUD};
 use colored::Colorize;
 use error_stack::{IntoReport, Report, ResultExt};
 use reqwest::blocking::multipart::Form;
@@ -13,13 +13,15 @@ use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-use crate::soundeo::search_bar::SoundeoSearchBarResult;
-use crate::soundeo::track::SoundeoTrack;
+use crate::soundeo::track::{DownloadTarget, SoundeoTrack};
 use crate::soundeo::{Soundeo, SoundeoCRUD};
+use crate::spotify::managed::ManagedPlaylist;
 use crate::spotify::playlist::SpotifyPlaylist;
+use crate::spotify::review::{self, PairingReview, ReviewDecision};
 use crate::spotify::{Spotify, SpotifyCRUD};
 use crate::user::{IPFSConfig, SoundeoUser, User};
 use crate::genre_tracker::{GenreTracker, GenreTrackerCRUD};
+use crate::history::{HistoryCRUD, RunHistory, RunRecord, MAX_RUN_RECORDS};
 
 #[derive(Debug)]
 pub struct DjWizardLogError;
@@ -45,6 +47,9 @@ pub struct QueuedTrack {
     pub priority: Priority,
     pub order_key: f64,
     pub added_at: u64,
+    /// Where the track was queued from, e.g. "spotify" or "genre:Drum and Bass"
+    #[serde(default)]
+    pub source: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,14 +58,22 @@ pub struct DjWizardLog {
     pub queued_tracks: Vec<QueuedTrack>,
     #[serde(default)]
     pub available_tracks: HashSet<String>,
-    #[serde(default)]
+    /// Urls saved before they were tracked sources, moved to `tracked_sources` when read
+    #[serde(default, skip_serializing)]
     pub url_list: HashSet<String>,
+    #[serde(default)]
+    pub tracked_sources: HashMap<String, TrackedSource>,
     pub spotify: Spotify,
     pub soundeo: Soundeo,
     #[serde(default)]
     pub genre_tracker: GenreTracker,
     #[serde(default)]
     pub artist_manager: ArtistManager,
+    #[serde(default)]
+    pub run_history: Vec<RunRecord>,
+    /// Format and folder of the queued and available tracks that don't use the defaults
+    #[serde(default)]
+    pub download_targets: HashMap<String, DownloadTarget>,
 }
 
 impl DjWizardLog {
@@ -84,11 +97,6 @@ impl DjWizardLog {
         Ok(log.soundeo)
     }
 
-    pub fn get_url_list() -> DjWizardLogResult<HashSet<String>> {
-        let log = Self::read_log()?;
-        Ok(log.url_list)
-    }
-
     pub fn get_genre_tracker() -> DjWizardLogResult<GenreTracker> {
         let log = Self::read_log()?;
         Ok(log.genre_tracker)
@@ -104,11 +112,14 @@ impl DjWizardLog {
                 .change_context(DjWizardLogError)?;
 
             // Attempt to deserialize into the new format first. If it works, we're done.
-            if let Ok(log) = serde_json::from_str::<Self>(&log_content) {
+            if let Ok(mut log) = serde_json::from_str::<Self>(&log_content) {
+                log.migrate_url_list();
+                log.migrate_multiple_matches_cache();
                 return Ok(log);
             }
 
             // If that failed, it's likely the old format. Let's try to migrate.
+            log::warn!("Old log format detected at {}, migrating", soundeo_log_path);
             println!(
                 "{}",
                 "Old log format detected. Attempting to migrate queue automatically...".yellow()
@@ -147,6 +158,7 @@ impl DjWizardLog {
                             priority: Priority::Normal, // Default priority
                             order_key: i as f64,        // Preserve some order
                             added_at: now,
+                            source: None,
                         })
                         .collect();
 
@@ -157,10 +169,13 @@ impl DjWizardLog {
                 }
             }
 
-            let log: Self = serde_json::from_value(json_value).into_report().attach_printable("Failed to deserialize log file after attempting migration. The log file might be corrupted.").change_context(DjWizardLogError)?;
+            let mut log: Self = serde_json::from_value(json_value).into_report().attach_printable("Failed to deserialize log file after attempting migration. The log file might be corrupted.").change_context(DjWizardLogError)?;
 
+            log.migrate_url_list();
+            log.migrate_multiple_matches_cache();
             if migration_performed {
                 log.save_log()?;
+                log::info!("Migrated the queue of {} to the prioritized format", soundeo_log_path);
                 println!(
                     "{}",
                     "Migration successful! Your queue has been updated to the new prioritized format."
@@ -176,13 +191,36 @@ impl DjWizardLog {
                 spotify: Spotify::new(),
                 available_tracks: HashSet::new(),
                 url_list: HashSet::new(),
+                tracked_sources: HashMap::new(),
                 genre_tracker: GenreTracker::new(),
                 artist_manager: ArtistManager::new(),
+                run_history: Vec::new(),
+                download_targets: HashMap::new(),
             }
         };
         Ok(soundeo_log)
     }
 
+    /// Moves the old multiple matches cache to the review queue, it is dropped on the next save
+    fn migrate_multiple_matches_cache(&mut self) {
+        review::migrate_multiple_matches_cache(&mut self.spotify, &self.soundeo.tracks_info);
+    }
+
+    /// Turns the urls of the old url list into tracked sources, they are dropped on the next save
+    fn migrate_url_list(&mut self) {
+        for url in self.url_list.drain() {
+            if self.tracked_sources.contains_key(&url) {
+                continue;
+            }
+            match TrackedSource::from_saved_url(&url) {
+                Some(tracked_source) => {
+                    self.tracked_sources.insert(url, tracked_source);
+                }
+                None => log::warn!("Dropping the invalid url {} of the url list", url),
+            }
+        }
+    }
+
     fn save_log(&self) -> DjWizardLogResult<()> {
         let soundeo_user = SoundeoUser::new().change_context(DjWizardLogError)?;
         let save_log_string = serde_json::to_string_pretty(self)
@@ -207,7 +245,11 @@ impl DjWizardLog {
         Ok(format!("{}/soundeo_log.json", user.download_path))
     }
 
-    pub fn add_queued_track(track_id: String, priority: Priority) -> DjWizardLogResult<bool> {
+    pub fn add_queued_track(
+        track_id: String,
+        priority: Priority,
+        source: Option<String>,
+    ) -> DjWizardLogResult<bool> {
         let mut log = Self::read_log()?;
         log.last_update = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -235,9 +277,11 @@ impl DjWizardLog {
                 1.0
             },
             added_at: log.last_update,
+            source,
         };
         log.queued_tracks.push(new_track);
         log.save_log()?;
+        RunHistory::track_queued();
         Ok(true)
     }
 
@@ -309,6 +353,25 @@ impl DjWizardLog {
         Ok(result)
     }
 
+    pub fn get_download_target(track_id: &str) -> DjWizardLogResult<DownloadTarget> {
+        let log = Self::read_log()?;
+        Ok(log.download_targets.get(track_id).cloned().unwrap_or_default())
+    }
+
+    /// Stores the format and folder of a track, forgetting it when it is the default one
+    pub fn set_download_target(track_id: String, target: DownloadTarget) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        let previous_target = if target == DownloadTarget::default() {
+            log.download_targets.remove(&track_id)
+        } else {
+            log.download_targets.insert(track_id, target.clone())
+        };
+        if previous_target.unwrap_or_default() != target {
+            log.save_log()?;
+        }
+        Ok(())
+    }
+
     pub fn remove_available_track(track_id: String) -> DjWizardLogResult<bool> {
         let mut log = Self::read_log()?;
         log.last_update = SystemTime::now()
@@ -447,6 +510,32 @@ impl SoundeoCRUD for DjWizardLog {
         log.save_log()?;
         Ok(())
     }
+
+    fn mark_tracks_as_owned(owned_tracks: HashMap<String, PathBuf>) -> DjWizardLogResult<usize> {
+        let mut log = Self::read_log()?;
+        log.last_update = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .into_report()
+            .change_context(DjWizardLogError)?
+            .as_secs();
+        for (soundeo_track_id, path) in owned_tracks {
+            log.soundeo
+                .tracks_info
+                .get_mut(&soundeo_track_id)
+                .ok_or(DjWizardLogError)
+                .into_report()
+                .attach_printable(format!("Track {} is not stored", soundeo_track_id))?
+                .already_downloaded = true;
+            log.soundeo.owned_tracks.insert(soundeo_track_id, path);
+        }
+        let initial_len = log.queued_tracks.len();
+        let owned_tracks = &log.soundeo.owned_tracks;
+        log.queued_tracks
+            .retain(|queued_track| !owned_tracks.contains_key(&queued_track.track_id));
+        let removed_from_queue = initial_len - log.queued_tracks.len();
+        log.save_log()?;
+        Ok(removed_from_queue)
+    }
 }
 
 impl SpotifyCRUD for DjWizardLog {
@@ -489,42 +578,159 @@ impl SpotifyCRUD for DjWizardLog {
         Ok(())
     }
 
-    fn add_to_multiple_matches_cache(
-        spotify_id: String,
-        results: Vec<SoundeoSearchBarResult>,
+    fn add_pairing_review(review: PairingReview) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        if log
+            .spotify
+            .review_decisions
+            .contains_key(&review.spotify_track_id)
+        {
+            return Ok(());
+        }
+        match log.spotify.pairing_reviews.get_mut(&review.spotify_track_id) {
+            Some(current) => {
+                current.title = review.title;
+                current.artists = review.artists;
+                current.rejected.extend(review.rejected);
+                current.merge_candidates(review.candidates);
+            }
+            None => {
+                log.spotify
+                    .pairing_reviews
+                    .insert(review.spotify_track_id.clone(), review);
+            }
+        }
+        log.save_log()
+    }
+
+    fn reject_review_candidates(
+        spotify_track_id: String,
+        soundeo_track_ids: Vec<String>,
     ) -> DjWizardLogResult<()> {
         let mut log = Self::read_log()?;
         log.spotify
-            .multiple_matches_cache
-            .insert(spotify_id, results);
+            .pairing_reviews
+            .get_mut(&spotify_track_id)
+            .ok_or(DjWizardLogError)
+            .into_report()
+            .attach_printable(format!("Track {} is not in review", spotify_track_id))?
+            .rejected
+            .extend(soundeo_track_ids);
+        log.save_log()
+    }
+
+    fn resolve_pairing_review(
+        spotify_track_id: String,
+        decision: ReviewDecision,
+    ) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        let soundeo_track_id = match &decision {
+            ReviewDecision::Accepted(soundeo_track_id) => Some(soundeo_track_id.clone()),
+            ReviewDecision::NotOnSoundeo => None,
+        };
+        log.spotify
+            .soundeo_track_ids
+            .insert(spotify_track_id.clone(), soundeo_track_id);
+        log.spotify.pairing_reviews.remove(&spotify_track_id);
+        log.spotify
+            .review_decisions
+            .insert(spotify_track_id, decision);
+        log.save_log()
+    }
+
+    fn set_playlist_folder(playlist_id: String, folder_name: String) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        log.spotify.playlist_folders.insert(playlist_id, folder_name);
+        log.save_log()
+    }
+
+    fn set_managed_playlist(
+        managed_playlist: ManagedPlaylist,
+        spotify_playlist_id: String,
+    ) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        log.spotify
+            .managed_playlists
+            .insert(managed_playlist, spotify_playlist_id);
         log.save_log()
     }
 }
 
 impl UrlListCRUD for DjWizardLog {
-    fn add_url_to_url_list(soundeo_url: url::Url) -> DjWizardLogResult<bool> {
+    fn get_tracked_sources() -> DjWizardLogResult<HashMap<String, TrackedSource>> {
+        let log = Self::read_log()?;
+        Ok(log.tracked_sources)
+    }
+
+    fn add_tracked_source(tracked_source: TrackedSource) -> DjWizardLogResult<bool> {
         let mut log = Self::read_log()?;
+        if log.tracked_sources.contains_key(&tracked_source.url) {
+            return Ok(false);
+        }
         log.last_update = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .into_report()
             .change_context(DjWizardLogError)?
             .as_secs();
-        let result = log.url_list.insert(soundeo_url.to_string());
+        log.tracked_sources
+            .insert(tracked_source.url.clone(), tracked_source);
         log.save_log()?;
-        Ok(result)
+        Ok(true)
     }
 
-    fn remove_url_from_url_list(soundeo_url: String) -> DjWizardLogResult<bool> {
+    fn remove_tracked_source(url: &str) -> DjWizardLogResult<bool> {
         let mut log = Self::read_log()?;
         log.last_update = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .into_report()
             .change_context(DjWizardLogError)?
             .as_secs();
-        let result = log.url_list.remove(&soundeo_url);
+        let result = log.tracked_sources.remove(url).is_some();
         log.save_log()?;
         Ok(result)
     }
+
+    fn update_tracked_source_defaults(
+        url: &str,
+        priority: Priority,
+        download_target: DownloadTarget,
+    ) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        let tracked_source = log
+            .tracked_sources
+            .get_mut(url)
+            .ok_or(DjWizardLogError)
+            .into_report()
+            .attach_printable_lazy(|| format!("The url {} is not tracked", url))?;
+        tracked_source.priority = priority;
+        tracked_source.download_target = download_target;
+        log.last_update = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .into_report()
+            .change_context(DjWizardLogError)?
+            .as_secs();
+        log.save_log()
+    }
+
+    fn mark_tracked_source_checked(
+        url: &str,
+        found_track_ids: HashSet<String>,
+    ) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        let tracked_source = log
+            .tracked_sources
+            .get_mut(url)
+            .ok_or(DjWizardLogError)
+            .into_report()
+            .attach_printable_lazy(|| format!("The url {} is not tracked", url))?;
+        tracked_source.mark_checked(found_track_ids);
+        log.last_update = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .into_report()
+            .change_context(DjWizardLogError)?
+            .as_secs();
+        log.save_log()
+    }
 }
 
 impl GenreTrackerCRUD for DjWizardLog {
@@ -565,6 +771,22 @@ impl ArtistCRUD for DjWizardLog {
     }
 }
 
+impl HistoryCRUD for DjWizardLog {
+    fn get_run_history() -> DjWizardLogResult<Vec<RunRecord>> {
+        let log = Self::read_log()?;
+        Ok(log.run_history)
+    }
+
+    fn add_run_record(run_record: RunRecord) -> DjWizardLogResult<()> {
+        let mut log = Self::read_log()?;
+        log.run_history.push(run_record);
+        let overflow = log.run_history.len().saturating_sub(MAX_RUN_RECORDS);
+        log.run_history.drain(..overflow);
+        log.save_log()?;
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,245 @@
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use error_stack::{IntoReport, ResultExt};
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::user::User;
+
+#[derive(Debug)]
+pub struct LoggingError;
+
+impl fmt::Display for LoggingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Logging error")
+    }
+}
+
+impl std::error::Error for LoggingError {}
+
+pub type LoggingResult<T> = error_stack::Result<T, LoggingError>;
+
+const LOG_FILE_NAME: &str = "dj-wizard.log";
+const MAX_LOG_FILE_BYTES: u64 = 5 * 1024 * 1024;
+const ROTATED_LOG_FILES: usize = 3;
+const CRATE_TARGET: &str = "dj_wizard";
+
+/// Log levels per target, parsed from `RUST_LOG` style directives, e.g. `info,dj_wizard::queue=trace`
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default_level: LevelFilter,
+    directives: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Logs the crate at info, debug or trace depending on the `-v` count, and dependencies at warn
+    pub fn from_verbosity(verbosity: u8) -> Self {
+        let crate_level = match verbosity {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        };
+        Self {
+            default_level: LevelFilter::Warn,
+            directives: vec![(CRATE_TARGET.to_string(), crate_level)],
+        }
+    }
+
+    /// Unknown levels are ignored, a bare level sets the level of every target
+    pub fn parse(spec: &str) -> Self {
+        let mut filter = Self {
+            default_level: LevelFilter::Warn,
+            directives: vec![],
+        };
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = LevelFilter::from_str(level.trim()) {
+                        filter.directives.push((target.trim().to_string(), level));
+                    }
+                }
+                None => {
+                    if let Ok(level) = LevelFilter::from_str(directive) {
+                        filter.default_level = level;
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    /// The most specific directive matching the target wins
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.directives
+            .iter()
+            .filter(|(prefix, _)| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default_level)
+    }
+
+    pub fn max_level(&self) -> LevelFilter {
+        self.directives
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default_level, Ord::max)
+    }
+}
+
+struct LogFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl LogFile {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size + line.len() as u64 > MAX_LOG_FILE_BYTES {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts `dj-wizard.log.N` to `.N+1`, dropping the oldest one, and starts a new file
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        for index in (1..ROTATED_LOG_FILES).rev() {
+            let from = Self::rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, Self::rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, Self::rotated_path(&self.path, 1))?;
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+
+    fn rotated_path(path: &Path, index: usize) -> PathBuf {
+        PathBuf::from(format!("{}.{}", path.display(), index))
+    }
+}
+
+/// Writes the `log` records to a rotating file next to the config file
+pub struct FileLogger {
+    filter: LogFilter,
+    log_file: Mutex<LogFile>,
+}
+
+impl FileLogger {
+    /// `RUST_LOG` takes precedence over the `-v` count
+    pub fn init(verbosity: u8) -> LoggingResult<()> {
+        let filter = match std::env::var("RUST_LOG") {
+            Ok(spec) if !spec.trim().is_empty() => LogFilter::parse(&spec),
+            _ => LogFilter::from_verbosity(verbosity),
+        };
+        let log_path = Self::get_log_file_path()?;
+        let log_file = LogFile::open(log_path.clone())
+            .into_report()
+            .attach_printable(format!(
+                "Failed to open the log file at {}",
+                log_path.display()
+            ))
+            .change_context(LoggingError)?;
+        let max_level = filter.max_level();
+        log::set_boxed_logger(Box::new(Self {
+            filter,
+            log_file: Mutex::new(log_file),
+        }))
+        .into_report()
+        .change_context(LoggingError)?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+
+    pub fn get_log_file_path() -> LoggingResult<PathBuf> {
+        let config_path = User::get_config_file_path().change_context(LoggingError)?;
+        let logs_folder = Path::new(&config_path)
+            .parent()
+            .ok_or(LoggingError)
+            .into_report()?
+            .join("logs");
+        fs::create_dir_all(&logs_folder)
+            .into_report()
+            .attach_printable(format!(
+                "Failed to create the logs folder at {}",
+                logs_folder.display()
+            ))
+            .change_context(LoggingError)?;
+        Ok(logs_folder.join(LOG_FILE_NAME))
+    }
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!(
+            "{} {:<5} {}: {}\n",
+            chrono::Local::now().format("%Y-%m-%dT%H:%M:%S%.3f%:z"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        if let Ok(mut log_file) = self.log_file.lock() {
+            let _ = log_file.write_line(&line);
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut log_file) = self.log_file.lock() {
+            let _ = log_file.file.flush();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verbosity_only_raises_the_crate_level() {
+        let filter = LogFilter::from_verbosity(1);
+        assert_eq!(
+            filter.level_for("dj_wizard::queue::commands"),
+            LevelFilter::Debug
+        );
+        assert_eq!(filter.level_for("reqwest::connect"), LevelFilter::Warn);
+        assert_eq!(LogFilter::from_verbosity(3).max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn test_parse_uses_the_most_specific_directive() {
+        let filter = LogFilter::parse("error, dj_wizard=info,dj_wizard::queue=trace,bad=loud");
+        assert_eq!(
+            filter.level_for("dj_wizard::queue::display"),
+            LevelFilter::Trace
+        );
+        assert_eq!(filter.level_for("dj_wizard::queue_plan"), LevelFilter::Info);
+        assert_eq!(filter.level_for("dj_wizard"), LevelFilter::Info);
+        assert_eq!(filter.level_for("hyper"), LevelFilter::Error);
+        assert_eq!(filter.level_for("bad"), LevelFilter::Error);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+}
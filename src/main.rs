@@ -1,6 +1,6 @@
 use std::{env, fmt};
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand};
 use colored::Colorize;
 use error_stack::fmt::{Charset, ColorMode};
 use error_stack::{FutureExt, IntoReport, Report, ResultExt};
@@ -10,8 +10,14 @@ use crate::artist::commands::ArtistCommands;
 use crate::backup::commands::BackupCommands;
 use crate::cleaner::clean_repeated_files;
 use crate::dialoguer::Dialoguer;
+use crate::export::commands::ExportCommands;
 use crate::genre_tracker::commands::GenreTrackerCommands;
+use crate::history::commands::HistoryCommands;
+use crate::history::RunHistory;
+use crate::import::commands::ImportCommands;
 use crate::log::DjWizardLog;
+use crate::logging::FileLogger;
+use crate::output::OutputFormat;
 use crate::queue::commands::QueueCommands;
 use crate::soundeo::track::SoundeoTrack;
 use crate::spotify::commands::{SpotifyCli, SpotifyCommands};
@@ -24,12 +30,21 @@ mod cleaner;
 mod config;
 mod dialoguer;
 mod errors;
+mod export;
 mod genre_tracker;
+mod history;
+mod import;
 mod ipfs;
 mod log;
+mod logging;
+mod normalize;
+mod oauth;
+mod output;
 mod queue;
 mod soundeo;
 mod spotify;
+#[cfg(test)]
+mod stand_in;
 mod url_list;
 mod user;
 
@@ -49,6 +64,12 @@ pub type DjWizardResult<T> = error_stack::Result<T, DjWizardError>;
 struct Cli {
     #[command(subcommand)]
     command: DjWizardCommands,
+    /// Format of the reports printed by the commands
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+    /// Increase the detail of the log file (-v debug, -vv trace), RUST_LOG takes precedence
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    verbose: u8,
 }
 
 /// A simple program to download all files from a search in soundeo
@@ -65,23 +86,54 @@ enum DjWizardCommands {
         /// flag to repet already downloaded
         #[clap(long, short, action)]
         resume_queue: bool,
+        #[command(subcommand)]
+        command: Option<QueueCommands>,
     },
     /// Add all the tracks from a url to the Soundeo collection and queue them
-    Url,
+    Url {
+        #[command(subcommand)]
+        command: Option<UrlListCommands>,
+    },
     /// Clean all the repeated files starting on a path.
     /// Thought to be used to clean repeated files
     /// on DJ programs e.g. Rekordbox
     Clean,
     /// Get Soundeo track info by id
-    Info,
+    Info {
+        /// Soundeo track id, prompted if not provided
+        #[clap(long)]
+        track_id: Option<String>,
+    },
     /// Automatically download tracks from a Spotify playlist
     Spotify(SpotifyCli),
     /// Backup the log file to the cloud
     Backup,
     /// Track available tracks by genre
-    Genre,
+    Genre {
+        #[command(subcommand)]
+        command: Option<GenreTrackerCommands>,
+    },
     /// Manage favorite artists
-    Artist,
+    Artist {
+        #[command(subcommand)]
+        command: Option<ArtistCommands>,
+    },
+    /// Export the downloaded tracks and playlists to DJ software
+    Export {
+        #[command(subcommand)]
+        command: Option<ExportCommands>,
+    },
+    /// Mark the tracks of a DJ software collection as owned, so they are never queued
+    Import {
+        #[command(subcommand)]
+        command: Option<ImportCommands>,
+    },
+    /// Show the record of the previous runs
+    History {
+        /// Number of runs to show, newest first
+        #[clap(long, default_value_t = 20)]
+        limit: usize,
+    },
 }
 
 impl DjWizardCommands {
@@ -156,13 +208,16 @@ impl DjWizardCommands {
                 println!("Current config:\n{:#?}", soundeo_bot_config);
                 Ok(())
             }
-            DjWizardCommands::Queue { resume_queue } => {
-                QueueCommands::execute(*resume_queue)
+            DjWizardCommands::Queue {
+                resume_queue,
+                command,
+            } => {
+                QueueCommands::execute(*resume_queue, command.clone())
                     .change_context(DjWizardError)
                     .await
             }
-            DjWizardCommands::Url => {
-                UrlListCommands::execute()
+            DjWizardCommands::Url { command } => {
+                UrlListCommands::execute(command.clone())
                     .change_context(DjWizardError)
                     .await
             }
@@ -182,9 +237,14 @@ impl DjWizardCommands {
                 );
                 clean_repeated_files(selected_path).change_context(DjWizardError)
             }
-            DjWizardCommands::Info => {
-                let prompt_text = "Soundeo track id: ".to_string();
-                let track_id = Dialoguer::input(prompt_text).change_context(DjWizardError)?;
+            DjWizardCommands::Info { track_id } => {
+                let track_id = match track_id {
+                    Some(track_id) => track_id.clone(),
+                    None => {
+                        let prompt_text = "Soundeo track id: ".to_string();
+                        Dialoguer::input(prompt_text).change_context(DjWizardError)?
+                    }
+                };
                 let mut soundeo_user = SoundeoUser::new().change_context(DjWizardError)?;
                 soundeo_user
                     .login_and_update_user_info()
@@ -192,10 +252,15 @@ impl DjWizardCommands {
                     .change_context(DjWizardError)?;
                 let mut soundeo_track_full_info = SoundeoTrack::new(track_id.clone());
                 soundeo_track_full_info
-                    .get_info(&soundeo_user, true)
+                    .get_info(&soundeo_user, !OutputFormat::is_json())
                     .await
                     .change_context(DjWizardError)?;
-                println!("{:#?}", soundeo_track_full_info);
+                if OutputFormat::is_json() {
+                    OutputFormat::print_json(&soundeo_track_full_info)
+                        .change_context(DjWizardError)?;
+                } else {
+                    println!("{:#?}", soundeo_track_full_info);
+                }
                 Ok(())
             }
             DjWizardCommands::Spotify(cli) => {
@@ -208,14 +273,26 @@ impl DjWizardCommands {
                     .change_context(DjWizardError)
                     .await
             }
-            DjWizardCommands::Genre => {
-                GenreTrackerCommands::execute()
+            DjWizardCommands::Genre { command } => {
+                GenreTrackerCommands::execute(command.clone())
+                    .change_context(DjWizardError)
+                    .await
+            }
+            DjWizardCommands::Artist { command } => {
+                ArtistCommands::execute(command.clone())
                     .change_context(DjWizardError)
                     .await
             }
-            DjWizardCommands::Artist => {
-                ArtistCommands::execute()
+            DjWizardCommands::Export { command } => {
+                ExportCommands::execute(command.clone()).change_context(DjWizardError)
+            }
+            DjWizardCommands::Import { command } => {
+                ImportCommands::execute(command.clone())
                     .change_context(DjWizardError)
+                    .await
+            }
+            DjWizardCommands::History { limit } => {
+                HistoryCommands::execute(*limit).change_context(DjWizardError)
             }
         };
     }
@@ -231,13 +308,13 @@ impl DjWizardCommands {
             DjWizardCommands::Queue { .. } => {
                 format!("dj-wizard queue")
             }
-            DjWizardCommands::Url => {
+            DjWizardCommands::Url { .. } => {
                 format!("dj-wizard url")
             }
             DjWizardCommands::Clean => {
                 format!("dj-wizard clean")
             }
-            DjWizardCommands::Info => {
+            DjWizardCommands::Info { .. } => {
                 format!("dj-wizard info")
             }
             DjWizardCommands::Spotify(..) => {
@@ -249,12 +326,21 @@ impl DjWizardCommands {
             DjWizardCommands::Backup => {
                 format!("dj-wizard backup")
             }
-            DjWizardCommands::Genre => {
+            DjWizardCommands::Genre { .. } => {
                 format!("dj-wizard genre")
             }
-            DjWizardCommands::Artist => {
+            DjWizardCommands::Artist { .. } => {
                 format!("dj-wizard artist")
             }
+            DjWizardCommands::Export { .. } => {
+                "dj-wizard export".to_string()
+            }
+            DjWizardCommands::Import { .. } => {
+                "dj-wizard import".to_string()
+            }
+            DjWizardCommands::History { .. } => {
+                "dj-wizard history".to_string()
+            }
         }
     }
 }
@@ -273,10 +359,30 @@ impl Suggestion {
 
 async fn run() -> DjWizardResult<()> {
     let cli = Cli::parse();
+    OutputFormat::set_current(cli.output);
 
     Suggestion::set_report();
 
-    cli.command.execute().await?;
+    if let Err(error) = FileLogger::init(cli.verbose) {
+        eprintln!("{}", format!("Logging to a file is disabled: {:#}", error).yellow());
+    }
+
+    // Viewing the history is not recorded in it
+    let record_run = !matches!(cli.command, DjWizardCommands::History { .. });
+    if record_run {
+        let arguments: Vec<String> = env::args().skip(1).collect();
+        RunHistory::start(format!("dj-wizard {}", arguments.join(" ")));
+    }
+
+    let result = cli.command.execute().await;
+
+    if record_run {
+        if let Err(error) = &result {
+            ::log::error!("{:#}", error);
+        }
+        RunHistory::finish(result.as_ref().err().map(|error| format!("{:#}", error)));
+    }
+    result?;
 
     // Ok(())
     Ok(())
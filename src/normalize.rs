@@ -0,0 +1,410 @@
+//! Normalization of artist and track names, to compare the names Spotify, Soundeo and the
+//! DJ software give to the same track
+use std::collections::BTreeSet;
+
+/// Words that join artist names, ignored when comparing artists
+const ARTIST_JOINERS: [&str; 7] = ["and", "feat", "ft", "featuring", "x", "vs", "with"];
+
+/// Words that make the version of a track credit a remixer
+const REMIX_WORDS: [&str; 7] = ["remix", "mix", "edit", "bootleg", "rework", "flip", "vip"];
+
+/// Lowercase ASCII words: diacritics removed, "&" and "+" as "and", apostrophes dropped and
+/// any other symbol as a space
+pub fn normalize(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.to_lowercase().chars() {
+        match c {
+            '\'' | '’' | '`' => {}
+            '&' | '+' => folded.push_str(" and "),
+            c if c.is_ascii_alphanumeric() => folded.push(c),
+            c => match fold_diacritic(c) {
+                Some(ascii) => folded.push_str(ascii),
+                None if c.is_alphanumeric() => folded.push(c),
+                None => folded.push(' '),
+            },
+        }
+    }
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn fold_diacritic(c: char) -> Option<&'static str> {
+    let ascii = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'œ' => "oe",
+        'ř' => "r",
+        'ß' => "ss",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// Versions are compared without the trailing "mix": "Extended" and "Extended Mix" are the same
+pub fn normalize_version(version: &str) -> String {
+    let version = normalize(version);
+    match version.strip_suffix(" mix") {
+        Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+        _ => version,
+    }
+}
+
+/// Whether the words of `needle` appear one after the other in `haystack`, once both are
+/// normalized. "DRS" is found in "Calibre & DRS" but not in "Drsomething"
+pub fn contains_words(haystack: &str, needle: &str) -> bool {
+    let haystack = normalize(haystack);
+    let needle = normalize(needle);
+    if needle.is_empty() {
+        return false;
+    }
+    format!(" {} ", haystack).contains(&format!(" {} ", needle))
+}
+
+/// Dice coefficient of the normalized words of both texts, from 0 to 1
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a: BTreeSet<String> = normalize(a).split(' ').map(str::to_string).collect();
+    let b: BTreeSet<String> = normalize(b).split(' ').map(str::to_string).collect();
+    if a.iter().all(String::is_empty) || b.iter().all(String::is_empty) {
+        return 0.0;
+    }
+    2.0 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64
+}
+
+/// The parts of a track name, with the featured artists moved from the title to the artists
+/// and the remixers credited as artists removed from them
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackName {
+    pub artists: Vec<String>,
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl TrackName {
+    /// Parses an artist field ("Calibre, DRS", "Calibre & DRS feat. X") and a title
+    /// ("Name (feat. X) (Version)", "Name - Version")
+    pub fn parse(artists: &str, title: &str) -> Self {
+        let mut artist_names = split_featured(artists);
+        let (name, version, featured) = split_title(title);
+        for artist in featured {
+            if !artist_names
+                .iter()
+                .any(|name| normalize(name) == normalize(&artist))
+            {
+                artist_names.push(artist);
+            }
+        }
+        if let Some(version) = version.as_ref().filter(|version| credits_remixer(version)) {
+            let (remixers, main_artists): (Vec<String>, Vec<String>) = artist_names
+                .iter()
+                .cloned()
+                .partition(|artist| contains_words(version, artist));
+            if !remixers.is_empty() && !main_artists.is_empty() {
+                artist_names = main_artists;
+            }
+        }
+        Self {
+            artists: artist_names,
+            name,
+            version,
+        }
+    }
+
+    /// Soundeo titles already are "Artist - Name (Version)"
+    pub fn from_soundeo_title(title: &str) -> Self {
+        match title.split_once(" - ") {
+            Some((artists, title)) => Self::parse(artists, title),
+            None => Self::parse("", title),
+        }
+    }
+
+    /// Normalized words of the artists, without the words joining them, so "Calibre, DRS"
+    /// and "Calibre & DRS" are the same
+    pub fn artist_words(&self) -> BTreeSet<String> {
+        self.artists
+            .iter()
+            .flat_map(|artist| {
+                normalize(artist)
+                    .split(' ')
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            })
+            .filter(|word| !word.is_empty() && !ARTIST_JOINERS.contains(&word.as_str()))
+            .collect()
+    }
+
+    pub fn normalized_version(&self) -> Option<String> {
+        self.version.as_deref().map(normalize_version)
+    }
+
+    /// Key to find the same track in different sources
+    pub fn key(&self) -> String {
+        let artists = self
+            .artist_words()
+            .into_iter()
+            .collect::<Vec<_>>()
+            .join(" ");
+        match self.normalized_version() {
+            Some(version) => format!("{} - {} ({})", artists, normalize(&self.name), version),
+            None => format!("{} - {}", artists, normalize(&self.name)),
+        }
+    }
+
+    /// Search terms to try in order until one gets results: the cleaned up name first,
+    /// then fewer words each time
+    pub fn search_terms(&self) -> Vec<String> {
+        let main_artist = self.artists.first().cloned().unwrap_or_default();
+        let title = match &self.version {
+            Some(version) => format!("{} ({})", self.name, version),
+            None => self.name.clone(),
+        };
+        let terms = [
+            format!("{} - {}", self.artists.join(", "), title),
+            format!("{} - {}", main_artist, title),
+            format!("{} - {}", main_artist, self.name),
+            format!("{} {}", normalize(&main_artist), normalize(&self.name)),
+        ];
+        let mut seen = BTreeSet::new();
+        terms
+            .iter()
+            .map(|term| {
+                term.trim_matches(|c: char| c == ' ' || c == '-')
+                    .to_string()
+            })
+            .filter(|term| !term.is_empty() && seen.insert(term.to_lowercase()))
+            .collect()
+    }
+}
+
+/// Search terms for a track: the name as it comes first, then the ones of [`TrackName`]
+pub fn search_terms(artists: &str, title: &str) -> Vec<String> {
+    let original = format!("{} - {}", artists, title);
+    let mut terms = vec![original.clone()];
+    terms.extend(
+        TrackName::parse(artists, title)
+            .search_terms()
+            .into_iter()
+            .filter(|term| term.to_lowercase() != original.to_lowercase()),
+    );
+    terms
+}
+
+fn credits_remixer(version: &str) -> bool {
+    normalize(version)
+        .split(' ')
+        .any(|word| REMIX_WORDS.contains(&word))
+}
+
+/// Splits artist fields on commas and featuring credits
+fn split_featured(artists: &str) -> Vec<String> {
+    let mut artists = artists.to_string();
+    for separator in [" feat. ", " feat ", " ft. ", " ft ", " featuring "] {
+        artists = replace_ignoring_case(&artists, separator, ",");
+    }
+    artists
+        .split(',')
+        .map(|artist| artist.trim().to_string())
+        .filter(|artist| !artist.is_empty())
+        .collect()
+}
+
+fn replace_ignoring_case(text: &str, from: &str, to: &str) -> String {
+    let lowercase = text.to_lowercase();
+    // Lowercasing can change the byte length of some letters, then the text is kept as is
+    if lowercase.len() != text.len() {
+        return text.to_string();
+    }
+    let mut replaced = String::with_capacity(text.len());
+    let mut position = 0;
+    while let Some(found) = lowercase[position..].find(from) {
+        replaced.push_str(&text[position..position + found]);
+        replaced.push_str(to);
+        position += found + from.len();
+    }
+    replaced.push_str(&text[position..]);
+    replaced
+}
+
+/// Name, version and featured artists of a title. The version is the last group in brackets,
+/// or what follows the last " - " when there is none
+fn split_title(title: &str) -> (String, Option<String>, Vec<String>) {
+    let mut name = title.trim().to_string();
+    let mut version = None;
+    let mut featured = vec![];
+    strip_trailing_groups(&mut name, &mut version, &mut featured);
+    if version.is_none() {
+        if let Some((rest, suffix)) = name.clone().rsplit_once(" - ") {
+            match strip_featuring(suffix) {
+                Some(artists) => featured.extend(split_featured(&artists)),
+                None => version = Some(suffix.trim().to_string()),
+            }
+            name = rest.trim().to_string();
+            strip_trailing_groups(&mut name, &mut version, &mut featured);
+        }
+    }
+    let inline = split_featured(&name);
+    if inline.len() > 1 && !name.contains(',') {
+        name = inline[0].clone();
+        featured.extend(inline.into_iter().skip(1));
+    }
+    (name, version, featured)
+}
+
+/// Takes the featured artists and the version out of the groups in brackets closing the name.
+/// Only the last group is the version, the ones before it are part of the name
+fn strip_trailing_groups(
+    name: &mut String,
+    version: &mut Option<String>,
+    featured: &mut Vec<String>,
+) {
+    while let Some((rest, group)) = strip_trailing_group(name) {
+        match strip_featuring(&group) {
+            Some(artists) => featured.extend(split_featured(&artists)),
+            None if version.is_none() => *version = Some(group),
+            None => break,
+        }
+        *name = rest;
+    }
+}
+
+/// "Name (Group)" into "Name" and "Group", for round and square brackets
+fn strip_trailing_group(title: &str) -> Option<(String, String)> {
+    let close = title.chars().last()?;
+    let open = match close {
+        ')' => '(',
+        ']' => '[',
+        _ => return None,
+    };
+    let start = title.rfind(open)?;
+    if start == 0 {
+        return None;
+    }
+    let group = title[start + 1..title.len() - 1].trim().to_string();
+    Some((title[..start].trim().to_string(), group))
+}
+
+fn strip_featuring(group: &str) -> Option<String> {
+    let lowercase = group.to_lowercase();
+    ["feat. ", "feat ", "ft. ", "ft ", "featuring ", "with "]
+        .iter()
+        .find(|prefix| lowercase.starts_with(*prefix))
+        .map(|prefix| group[prefix.len()..].trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Spotify artists and title, and the Soundeo title of the same track
+    const SAME_TRACKS: [(&str, &str, &str); 8] = [
+        (
+            "Calibre, DRS",
+            "Mr Majestic - Extended Mix",
+            "Calibre & DRS - Mr Majestic (Extended Mix)",
+        ),
+        (
+            "Sub Focus, Wilkinson",
+            "Illuminate (feat. Empara Mi) - Extended Mix",
+            "Sub Focus & Wilkinson feat. Empara Mi - Illuminate (Extended Mix)",
+        ),
+        (
+            "Bou, Alix Perez",
+            "Poison - Alix Perez Remix",
+            "Bou - Poison (Alix Perez Remix)",
+        ),
+        (
+            "Chase & Status",
+            "Baddadan (feat. Irah, Flowdan, Takura & Trigga) - Extended Mix",
+            "Chase and Status feat. Irah, Flowdan, Takura & Trigga - Baddadan (Extended Mix)",
+        ),
+        (
+            "Röyksopp",
+            "Só Quero Dançar - Original Mix",
+            "Royksopp - So Quero Dancar (Original Mix)",
+        ),
+        (
+            "Pola & Bryson",
+            "Don't Let Go",
+            "Pola and Bryson - Dont Let Go",
+        ),
+        ("Kanine", "Tick Tock (VIP)", "Kanine - Tick Tock (VIP Mix)"),
+        (
+            "Hybrid Minds, Tom Walker",
+            "Lost - Extended",
+            "Hybrid Minds ft. Tom Walker - Lost (Extended Mix)",
+        ),
+    ];
+
+    /// Tracks that must not be taken as the same one
+    const DIFFERENT_TRACKS: [(&str, &str, &str); 3] = [
+        (
+            "Calibre",
+            "Mr Majestic - Extended Mix",
+            "Calibre - Mr Majestic (Alix Perez Remix)",
+        ),
+        ("Bou", "Poison", "Bou - Poison Dart"),
+        ("DRS", "Flowers", "DRSomething - Flowers"),
+    ];
+
+    #[test]
+    fn test_corpus_of_real_pairs_has_the_same_key() {
+        for (artists, title, soundeo_title) in SAME_TRACKS {
+            assert_eq!(
+                TrackName::parse(artists, title).key(),
+                TrackName::from_soundeo_title(soundeo_title).key(),
+                "{} - {}",
+                artists,
+                title
+            );
+        }
+        for (artists, title, soundeo_title) in DIFFERENT_TRACKS {
+            assert_ne!(
+                TrackName::parse(artists, title).key(),
+                TrackName::from_soundeo_title(soundeo_title).key(),
+                "{} - {}",
+                artists,
+                title
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_and_search_terms() {
+        let track_name =
+            TrackName::parse("Bou, Alix Perez", "Poison (feat. Slay) - Alix Perez Remix");
+
+        assert_eq!(
+            track_name,
+            TrackName {
+                artists: vec!["Bou".to_string(), "Slay".to_string()],
+                name: "Poison".to_string(),
+                version: Some("Alix Perez Remix".to_string()),
+            }
+        );
+        assert_eq!(
+            search_terms("Bou, Alix Perez", "Poison (feat. Slay) - Alix Perez Remix"),
+            vec![
+                "Bou, Alix Perez - Poison (feat. Slay) - Alix Perez Remix",
+                "Bou, Slay - Poison (Alix Perez Remix)",
+                "Bou - Poison (Alix Perez Remix)",
+                "Bou - Poison",
+                "bou poison",
+            ]
+        );
+        assert_eq!(normalize("Sigur Rós & Co."), "sigur ros and co");
+        assert!(contains_words("Calibre & DRS - Mr Majestic", "drs"));
+        assert!(!contains_words("DRSomething - Flowers", "DRS"));
+        assert_eq!(similarity("Mr Majestic", "Mr. Majestic"), 1.0);
+        assert_eq!(similarity("Mr Majestic", "Majestic"), 2.0 / 3.0);
+    }
+}
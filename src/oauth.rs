@@ -0,0 +1,520 @@
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::Engine;
+use colored::Colorize;
+use error_stack::{IntoReport, Report, ResultExt};
+use rand::RngCore;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tiny_http::Server;
+use url::Url;
+
+use crate::config::AppConfig;
+use crate::output::OutputFormat;
+use crate::user::User;
+
+#[derive(Debug)]
+pub struct OAuthError;
+
+impl fmt::Display for OAuthError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OAuth error")
+    }
+}
+
+impl std::error::Error for OAuthError {}
+
+pub type OAuthResult<T> = error_stack::Result<T, OAuthError>;
+
+/// Tokens expiring in less than this many seconds are refreshed before being used
+const REFRESH_MARGIN_SECS: u64 = 60;
+
+/// Services dj-wizard logs into, each one keeps its own token in the config file
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, strum_macros::Display,
+)]
+pub enum OAuthProvider {
+    Spotify,
+    GoogleDrive,
+}
+
+impl OAuthProvider {
+    fn authorize_url(&self) -> &'static str {
+        match self {
+            OAuthProvider::Spotify => "https://accounts.spotify.com/authorize",
+            OAuthProvider::GoogleDrive => "https://accounts.google.com/o/oauth2/v2/auth",
+        }
+    }
+
+    fn token_url(&self) -> &'static str {
+        match self {
+            OAuthProvider::Spotify => "https://accounts.spotify.com/api/token",
+            OAuthProvider::GoogleDrive => "https://oauth2.googleapis.com/token",
+        }
+    }
+
+    fn client_id(&self) -> &'static str {
+        match self {
+            OAuthProvider::Spotify => AppConfig::SPOTIFY_CLIENT_ID,
+            OAuthProvider::GoogleDrive => AppConfig::GOOGLE_CLIENT_ID,
+        }
+    }
+
+    fn client_secret(&self) -> Option<String> {
+        match self {
+            OAuthProvider::Spotify => None,
+            OAuthProvider::GoogleDrive => AppConfig::google_client_secret(),
+        }
+    }
+
+    /// Space separated scopes requested on login, users logged in with fewer are asked to log in again
+    pub fn scopes(&self) -> &'static str {
+        match self {
+            OAuthProvider::Spotify => concat!(
+                "playlist-read-private playlist-read-collaborative ",
+                "user-library-read user-follow-read ",
+                "playlist-modify-public playlist-modify-private"
+            ),
+            OAuthProvider::GoogleDrive => "https://www.googleapis.com/auth/drive.file",
+        }
+    }
+
+    /// Port of the login callback registered with the provider, overridable in the config file
+    pub fn default_callback_port(&self) -> u16 {
+        match self {
+            OAuthProvider::Spotify => 8888,
+            OAuthProvider::GoogleDrive => 8889,
+        }
+    }
+
+    /// Authorization parameters needed by the provider besides the standard ones
+    fn extra_authorize_params(&self) -> Vec<(&'static str, &'static str)> {
+        match self {
+            OAuthProvider::Spotify => vec![],
+            // Google only returns a refresh token when asked for offline access
+            OAuthProvider::GoogleDrive => vec![("access_type", "offline"), ("prompt", "consent")],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OAuthToken {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Seconds since the Unix epoch, 0 when unknown
+    #[serde(default)]
+    pub expires_at: u64,
+    /// Space separated scopes granted on login
+    #[serde(default)]
+    pub scopes: String,
+}
+
+impl OAuthToken {
+    pub fn expires_soon(&self, now: u64) -> bool {
+        self.expires_at != 0 && now + REFRESH_MARGIN_SECS >= self.expires_at
+    }
+
+    /// Whether the login granted every scope in `scopes`
+    pub fn has_scopes(&self, scopes: &str) -> bool {
+        let granted: Vec<&str> = self.scopes.split_whitespace().collect();
+        scopes
+            .split_whitespace()
+            .all(|scope| granted.contains(&scope))
+    }
+
+    fn update(&mut self, response: TokenResponse, now: u64) {
+        self.access_token = response.access_token;
+        // Providers may not rotate the refresh token, the previous one is still valid then
+        if let Some(refresh_token) = response.refresh_token {
+            self.refresh_token = refresh_token;
+        }
+        self.expires_at = response.expires_in.map_or(0, |expires_in| now + expires_in);
+        if let Some(scopes) = response.scope {
+            self.scopes = scopes;
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: Option<u64>,
+    scope: Option<String>,
+}
+
+/// Keeps the token of a provider fresh and sends authenticated requests with it
+pub struct TokenManager<'a> {
+    provider: OAuthProvider,
+    user_config: &'a mut User,
+    client: reqwest::Client,
+    token_url: String,
+    save_tokens: bool,
+}
+
+impl<'a> TokenManager<'a> {
+    pub fn new(provider: OAuthProvider, user_config: &'a mut User) -> Self {
+        Self {
+            provider,
+            user_config,
+            client: reqwest::Client::new(),
+            token_url: provider.token_url().to_string(),
+            save_tokens: true,
+        }
+    }
+
+    /// Uses `token_url` instead of the token endpoint of the provider, for a local stand-in.
+    /// Refreshed tokens are not saved to the config file
+    #[cfg(test)]
+    pub fn with_token_url(
+        provider: OAuthProvider,
+        user_config: &'a mut User,
+        token_url: String,
+    ) -> Self {
+        Self {
+            token_url,
+            save_tokens: false,
+            ..Self::new(provider, user_config)
+        }
+    }
+
+    pub fn token(&self) -> Option<&OAuthToken> {
+        self.user_config
+            .oauth_tokens
+            .get(&self.provider)
+            .filter(|token| !token.access_token.is_empty())
+    }
+
+    /// Whether there is a token with every scope the provider needs
+    pub fn has_required_scopes(&self) -> bool {
+        self.token()
+            .is_some_and(|token| token.has_scopes(self.provider.scopes()))
+    }
+
+    /// Access token to use now, refreshed first when it is about to expire
+    pub async fn access_token(&mut self) -> OAuthResult<String> {
+        let token = self
+            .token()
+            .cloned()
+            .ok_or(OAuthError)
+            .into_report()
+            .attach_printable(format!(
+                "Not logged into {}. Please log in first.",
+                self.provider
+            ))?;
+        if token.expires_soon(Self::now()?) {
+            self.refresh().await?;
+        }
+        Ok(self
+            .token()
+            .map(|token| token.access_token.clone())
+            .unwrap_or_default())
+    }
+
+    /// Sends the request built by `build_request` with the access token, refreshing the token
+    /// and sending it again once when the provider answers 401
+    pub async fn send<F>(&mut self, build_request: F) -> OAuthResult<Response>
+    where
+        F: Fn(&reqwest::Client) -> RequestBuilder,
+    {
+        let access_token = self.access_token().await?;
+        let response = build_request(&self.client)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .into_report()
+            .change_context(OAuthError)?;
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        self.refresh().await?;
+        let access_token = self.access_token().await?;
+        build_request(&self.client)
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .into_report()
+            .change_context(OAuthError)
+    }
+
+    pub async fn get(&mut self, url: &str) -> OAuthResult<Response> {
+        self.send(|client| client.get(url)).await
+    }
+
+    pub async fn refresh(&mut self) -> OAuthResult<()> {
+        OutputFormat::status(format!("Refreshing the {} access token...", self.provider));
+        let refresh_token = self
+            .token()
+            .map(|token| token.refresh_token.clone())
+            .filter(|refresh_token| !refresh_token.is_empty())
+            .ok_or(OAuthError)
+            .into_report()
+            .attach_printable("No refresh token available. Please log in again.")?;
+
+        let mut params = vec![
+            ("grant_type", "refresh_token".to_string()),
+            ("refresh_token", refresh_token),
+            ("client_id", self.provider.client_id().to_string()),
+        ];
+        if let Some(client_secret) = self.provider.client_secret() {
+            params.push(("client_secret", client_secret));
+        }
+        self.request_token(&params).await?;
+        OutputFormat::status(format!("{} token refreshed successfully.", self.provider).green());
+        Ok(())
+    }
+
+    /// Runs the authorization code flow with PKCE, catching the redirect on a local server
+    pub async fn login(&mut self) -> OAuthResult<()> {
+        let mut verifier_bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut verifier_bytes);
+        let code_verifier = base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(verifier_bytes);
+
+        let mut hasher = Sha256::new();
+        hasher.update(code_verifier.as_bytes());
+        let code_challenge =
+            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(hasher.finalize());
+
+        let port = self
+            .user_config
+            .oauth_callback_ports
+            .get(&self.provider)
+            .copied()
+            .unwrap_or_else(|| self.provider.default_callback_port());
+        let redirect_uri = format!("http://localhost:{}/callback", port);
+
+        let server = Server::http(format!("127.0.0.1:{}", port)).map_err(|error| {
+            Report::new(OAuthError).attach_printable(format!(
+                "Failed to listen for the login callback on port {}: {}",
+                port, error
+            ))
+        })?;
+
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", self.provider.client_id()),
+            ("scope", self.provider.scopes()),
+            ("redirect_uri", redirect_uri.as_str()),
+            ("code_challenge_method", "S256"),
+            ("code_challenge", code_challenge.as_str()),
+        ];
+        params.extend(self.provider.extra_authorize_params());
+        let auth_url = Url::parse_with_params(self.provider.authorize_url(), &params)
+            .into_report()
+            .change_context(OAuthError)?;
+
+        println!(
+            "\n{}\n",
+            format!(
+                "Please log in to {} in the browser window that just opened.",
+                self.provider
+            )
+            .yellow()
+        );
+        if webbrowser::open(auth_url.as_str()).is_err() {
+            println!(
+                "Could not automatically open browser. Please copy/paste this URL:\n{}",
+                auth_url.as_str().cyan()
+            );
+        }
+
+        let request = server.recv().into_report().change_context(OAuthError)?;
+        let callback_url = Url::parse(&format!("http://localhost:{}{}", port, request.url()))
+            .into_report()
+            .change_context(OAuthError)?;
+        let auth_code = callback_url
+            .query_pairs()
+            .find_map(|(key, value)| (key == "code").then(|| value.into_owned()))
+            .ok_or(
+                Report::new(OAuthError).attach_printable("Could not find 'code' in callback URL"),
+            )?;
+        request
+            .respond(tiny_http::Response::from_string(
+                "<h1>Authentication successful!</h1><p>You can close this browser tab now.</p>",
+            ))
+            .into_report()
+            .change_context(OAuthError)?;
+        println!("\nAuthorization code received successfully!");
+
+        let mut params = vec![
+            ("grant_type", "authorization_code".to_string()),
+            ("code", auth_code),
+            ("redirect_uri", redirect_uri),
+            ("client_id", self.provider.client_id().to_string()),
+            ("code_verifier", code_verifier),
+        ];
+        if let Some(client_secret) = self.provider.client_secret() {
+            params.push(("client_secret", client_secret));
+        }
+        // A new login replaces the previous token, including its scopes
+        self.user_config.oauth_tokens.remove(&self.provider);
+        self.request_token(&params).await?;
+
+        let scopes = self.provider.scopes();
+        if let Some(token) = self.user_config.oauth_tokens.get_mut(&self.provider) {
+            if token.scopes.is_empty() {
+                token.scopes = scopes.to_string();
+            }
+        }
+        self.save()?;
+        println!(
+            "{}",
+            format!(
+                "{} login successful! Your credentials have been saved.",
+                self.provider
+            )
+            .green()
+        );
+        Ok(())
+    }
+
+    async fn request_token(&mut self, params: &[(&str, String)]) -> OAuthResult<()> {
+        let response = self
+            .client
+            .post(&self.token_url)
+            .form(params)
+            .send()
+            .await
+            .into_report()
+            .change_context(OAuthError)?;
+        if !response.status().is_success() {
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(Report::new(OAuthError).attach_printable(format!(
+                "Failed to get a {} token. Response: {}",
+                self.provider, error_body
+            )));
+        }
+        let token_response: TokenResponse = response
+            .json()
+            .await
+            .into_report()
+            .change_context(OAuthError)?;
+
+        let now = Self::now()?;
+        self.user_config
+            .oauth_tokens
+            .entry(self.provider)
+            .or_default()
+            .update(token_response, now);
+        self.save()
+    }
+
+    fn save(&self) -> OAuthResult<()> {
+        if !self.save_tokens {
+            return Ok(());
+        }
+        self.user_config
+            .save_config_file()
+            .change_context(OAuthError)
+    }
+
+    fn now() -> OAuthResult<u64> {
+        Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .into_report()
+            .change_context(OAuthError)?
+            .as_secs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stand_in::{self, user_with_token, Requests};
+
+    /// Local stand-in for the token endpoint and an API accepting only "fresh" tokens,
+    /// served on "/token" and "/api"
+    fn start_stand_in() -> (String, Requests) {
+        stand_in::start(|request| {
+            if request.url == "/token" {
+                (
+                    200,
+                    r#"{"access_token":"fresh","expires_in":3600,"scope":"a b"}"#.to_string(),
+                )
+            } else if request.authorization == "Bearer fresh" {
+                (200, "{}".to_string())
+            } else {
+                (401, "{}".to_string())
+            }
+        })
+    }
+
+    /// Authorization header of every API request received by the stand-in
+    fn authorizations(requests: &Requests) -> Vec<String> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.url != "/token")
+            .map(|request| request.authorization.clone())
+            .collect()
+    }
+
+    fn user_with_stale_token(expires_at: u64) -> User {
+        user_with_token(OAuthToken {
+            access_token: "stale".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at,
+            scopes: "a".to_string(),
+        })
+    }
+
+    #[test]
+    fn test_token_expiry_and_scopes() {
+        let token = OAuthToken {
+            expires_at: 1000,
+            scopes: "playlist-read-private user-follow-read".to_string(),
+            ..OAuthToken::default()
+        };
+        assert!(!token.expires_soon(900));
+        assert!(token.expires_soon(950));
+        assert!(!OAuthToken::default().expires_soon(u64::MAX - REFRESH_MARGIN_SECS));
+        assert!(token.has_scopes("user-follow-read"));
+        assert!(!token.has_scopes("user-follow-read playlist-modify-private"));
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_before_expiry() {
+        let (url, requests) = start_stand_in();
+        let mut user = user_with_stale_token(1);
+        let mut tokens = TokenManager::with_token_url(
+            OAuthProvider::Spotify,
+            &mut user,
+            format!("{}/token", url),
+        );
+
+        let response = tokens.get(&format!("{}/api", url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(authorizations(&requests), vec!["Bearer fresh"]);
+        let token = user.oauth_tokens[&OAuthProvider::Spotify].clone();
+        assert_eq!(token.refresh_token, "refresh");
+        assert_eq!(token.scopes, "a b");
+        assert!(token.expires_at > 3600);
+    }
+
+    #[tokio::test]
+    async fn test_retries_once_on_unauthorized() {
+        let (url, requests) = start_stand_in();
+        let mut user = user_with_stale_token(0);
+        let mut tokens = TokenManager::with_token_url(
+            OAuthProvider::Spotify,
+            &mut user,
+            format!("{}/token", url),
+        );
+
+        let response = tokens.get(&format!("{}/api", url)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            authorizations(&requests),
+            vec!["Bearer stale", "Bearer fresh"]
+        );
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+use std::sync::OnceLock;
+
+use error_stack::{IntoReport, ResultExt};
+use serde::Serialize;
+
+#[derive(Debug)]
+pub struct OutputError;
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Output error")
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+pub type OutputResult<T> = error_stack::Result<T, OutputError>;
+
+static OUTPUT_FORMAT: OnceLock<OutputFormat> = OnceLock::new();
+
+/// Format used by the commands that print reports, selected with the global `--output` flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn set_current(output_format: OutputFormat) {
+        let _ = OUTPUT_FORMAT.set(output_format);
+    }
+
+    pub fn current() -> OutputFormat {
+        OUTPUT_FORMAT.get().copied().unwrap_or_default()
+    }
+
+    pub fn is_json() -> bool {
+        Self::current() == OutputFormat::Json
+    }
+
+    /// Prints a progress or status message, sent to stderr in json mode to keep stdout parseable
+    pub fn status(message: impl fmt::Display) {
+        if Self::is_json() {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    pub fn print_json<T: Serialize>(document: &T) -> OutputResult<()> {
+        let serialized = serde_json::to_string_pretty(document)
+            .into_report()
+            .attach_printable("Failed to serialize the output document")
+            .change_context(OutputError)?;
+        println!("{}", serialized);
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
+use clap::Subcommand;
 use colored::Colorize;
 use error_stack::{IntoReport, ResultExt};
 use inflector::Inflector;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashSet, VecDeque};
-use std::io::{self, Write};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Component, Path};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
     Arc,
@@ -14,15 +15,21 @@ use url::Url;
 
 use crate::artist::{ArtistCRUD, ArtistManager};
 use crate::dialoguer::Dialoguer;
+use crate::history::RunHistory;
 use crate::log::{DjWizardLog, Priority, QueuedTrack};
+use crate::normalize::contains_words;
+use crate::output::OutputFormat;
+use crate::queue::display::{QueueControls, QueueDisplay};
+use crate::queue::planner::{PlanCredit, PlanSkipReason, QueuePlan};
+use crate::queue::scheduler::SchedulingPolicy;
 use crate::queue::track_processor::TrackProcessor;
 use crate::queue::{QueueError, QueueResult};
-use crate::soundeo::track::SoundeoTrack;
+use crate::soundeo::track::{DownloadTarget, SoundeoTrack, TrackFormat};
 use crate::soundeo::track_list::SoundeoTracksList;
 use crate::soundeo::{Soundeo, SoundeoCRUD};
 use crate::spotify::playlist::SpotifyPlaylist;
-use crate::url_list::UrlListCRUD;
-use crate::user::SoundeoUser;
+use crate::url_list::commands::UrlListCommands;
+use crate::user::{SoundeoUser, User};
 
 #[derive(Debug, Clone)]
 enum TrackProcessResult {
@@ -109,88 +116,18 @@ impl ProcessingStats {
             download_counter
         )
     }
-}
-
-struct FixedDisplay {
-    stats_message: Arc<Mutex<String>>,
-    worker_messages: Arc<Mutex<Vec<String>>>,
-}
-
-impl FixedDisplay {
-    fn new() -> Self {
-        Self {
-            stats_message: Arc::new(Mutex::new(String::new())),
-            worker_messages: Arc::new(Mutex::new(vec![String::new(); 4])),
-        }
-    }
-
-    async fn update_stats(&self, message: String) {
-        {
-            let mut stats = self.stats_message.lock().await;
-            *stats = message.clone();
-        } // Release lock before render
-
-        // Update stats line (move up 5 lines to stats line)
-        print!("\x1B[5A"); // Move up to stats line
-        print!("\x1B[2K\r{}", message); // Clear line and print stats
-        print!("\x1B[5B"); // Move back down to bottom
-        io::stdout().flush().unwrap();
-    }
-
-    async fn update_worker(&self, worker_id: usize, message: String) {
-        let mut workers = self.worker_messages.lock().await;
-        workers[worker_id] = message;
-        let worker_message = workers[worker_id].clone();
-        drop(workers); // Release lock before render
-
-        // Update specific worker line
-        // Move up: 4 lines for workers below this one + 1 to get to this worker's line
-        let lines_up = 4 - worker_id;
-        print!("\x1B[{}A", lines_up); // Move up to worker line
-        print!("\x1B[2K\r{}", worker_message); // Clear line and print worker status
-        print!("\x1B[{}B", lines_up); // Move back down to bottom
-        io::stdout().flush().unwrap();
-    }
-
-    async fn render(&self) {
-        // This method is no longer used - updates are done individually
-    }
-
-    async fn initialize(&self) {
-        // Initialize with default messages and print all 5 lines once
-        let stats = self.stats_message.lock().await;
-        let workers = self.worker_messages.lock().await;
 
-        // Print stats line
-        println!(
-            "{}",
-            if stats.is_empty() {
-                "📊 Initializing..."
-            } else {
-                &stats
-            }
+    fn get_status_line(&self, download_counter: usize, reset_time: &str, paused: bool) -> String {
+        let status = format!(
+            "{} (resets in {})",
+            self.get_remaining_downloads(download_counter),
+            reset_time
         );
-
-        // Print worker lines
-        for (i, worker_msg) in workers.iter().enumerate() {
-            println!(
-                "{}",
-                if worker_msg.is_empty() {
-                    format!("⏳ Worker {}: Idle", i + 1)
-                } else {
-                    worker_msg.clone()
-                }
-            );
+        if paused {
+            format!("{} | {}", status, "PAUSED".yellow())
+        } else {
+            status
         }
-
-        // Now cursor is at the bottom, all future updates will use relative positioning
-        io::stdout().flush().unwrap();
-    }
-
-    async fn clear(&self) {
-        // Clear the display area
-        print!("\x1b[5A\x1b[J");
-        io::stdout().flush().unwrap();
     }
 }
 
@@ -199,30 +136,63 @@ struct WorkerState {
     download_counter: Arc<AtomicUsize>,
     stats: Arc<Mutex<ProcessingStats>>,
     soundeo_user: Arc<Mutex<SoundeoUser>>,
-    display: Arc<FixedDisplay>,
+    display: Arc<QueueDisplay>,
+    controls: Arc<QueueControls>,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, strum_macros::Display, strum_macros::EnumIter)]
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+    Subcommand,
+    PartialEq,
+)]
 pub enum QueueCommands {
+    /// Download the queued tracks with the remaining Soundeo credits.
     ResumeQueue,
+    /// Promote or filter queued tracks by artist, playlist or genre.
     ManageQueue,
+    /// Print a summary of the queued tracks.
     GetQueueInfo,
+    /// Preview what resuming the queue would download with the remaining credits.
+    #[command(name = "plan")]
+    PlanQueue,
+    /// Choose how the queue is ordered when downloading.
+    ConfigureScheduling,
+    /// Add the tracks from a url to the Soundeo collection.
     SaveToAvailableTracks,
+    /// Queue all the tracks from a Soundeo url.
     AddToQueueFromUrl,
+    /// Queue the new tracks of every url in the url list.
     AddToQueueFromUrlList,
+    /// Download only the tracks already added to the Soundeo collection.
     DownloadOnlyAvailableTracks,
+    /// Remove the already downloaded tracks from the queue.
     CleanDownloadedFromQueue,
 }
 
 impl QueueCommands {
-    pub async fn execute(resume_queue_flag: bool) -> QueueResult<()> {
+    pub async fn execute(
+        resume_queue_flag: bool,
+        command: Option<QueueCommands>,
+    ) -> QueueResult<()> {
         if resume_queue_flag {
             return Self::resume_queue(resume_queue_flag).await;
         }
-        let options = Self::get_options();
-        let selection = Dialoguer::select("What you want to do?".to_string(), options, None)
-            .change_context(QueueError)?;
-        return match Self::get_selection(selection) {
+        let command_to_run = match command {
+            Some(command) => command,
+            None => {
+                let options = Self::get_options();
+                let selection =
+                    Dialoguer::select("What you want to do?".to_string(), options, None)
+                        .change_context(QueueError)?;
+                Self::get_selection(selection)
+            }
+        };
+        return match command_to_run {
             QueueCommands::AddToQueueFromUrl => Self::add_to_queue_from_url(None, None).await,
             QueueCommands::AddToQueueFromUrlList => Self::add_to_queue_from_url_list().await,
             QueueCommands::ResumeQueue => Self::resume_queue(resume_queue_flag).await,
@@ -230,6 +200,8 @@ impl QueueCommands {
                 Self::add_to_available_downloads_parallel().await
             }
             QueueCommands::GetQueueInfo => Self::get_queue_information(),
+            QueueCommands::PlanQueue => Self::plan_queue().await,
+            QueueCommands::ConfigureScheduling => Self::configure_scheduling(),
             QueueCommands::ManageQueue => Self::manage_queue().await,
             QueueCommands::DownloadOnlyAvailableTracks => {
                 let mut soundeo_user = SoundeoUser::new().change_context(QueueError)?;
@@ -237,7 +209,10 @@ impl QueueCommands {
                     .login_and_update_user_info()
                     .await
                     .change_context(QueueError)?;
-                Self::download_available_tracks(&mut soundeo_user).await
+                let display = QueueDisplay::new(0, false);
+                let result = Self::download_available_tracks(&mut soundeo_user, &display).await;
+                display.finish();
+                result
             }
             QueueCommands::CleanDownloadedFromQueue => {
                 Self::clean_downloaded_from_queue().change_context(QueueError)
@@ -256,14 +231,15 @@ impl QueueCommands {
         options[selection].clone()
     }
 
-    fn prompt_for_priority() -> QueueResult<Priority> {
+    pub fn prompt_for_priority(prompt_text: String, current: Priority) -> QueueResult<Priority> {
         let priority_options = vec!["High (download first)", "Normal", "Low (download last)"];
-        let selection = Dialoguer::select(
-            "Choose a priority for this batch of songs".to_string(),
-            priority_options,
-            Some(1), // "Normal" as option by default
-        )
-        .change_context(QueueError)?;
+        let default_selection = match current {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        };
+        let selection = Dialoguer::select(prompt_text, priority_options, Some(default_selection))
+            .change_context(QueueError)?;
 
         let selected_priority = match selection {
             0 => Priority::High,
@@ -273,33 +249,70 @@ impl QueueCommands {
         Ok(selected_priority)
     }
 
-    async fn add_to_queue_from_url_list() -> QueueResult<()> {
-        let url_list = DjWizardLog::get_url_list().change_context(QueueError)?;
-        let prompt_text = format!("Do you want to download the already downloaded tracks again?");
-        let repeat_download =
-            Dialoguer::select_yes_or_no(prompt_text).change_context(QueueError)?;
+    /// Format and folder inside the download folder, starting from the current ones
+    pub fn prompt_for_download_target(current: &DownloadTarget) -> QueueResult<DownloadTarget> {
+        let formats: Vec<TrackFormat> = TrackFormat::iter().collect();
+        let format_selection = Dialoguer::select(
+            "Format of the downloaded files".to_string(),
+            formats.clone(),
+            formats.iter().position(|format| *format == current.format),
+        )
+        .change_context(QueueError)?;
 
-        for url in url_list {
-            println!("Queueing tracks from: {}", url.cyan());
-            match Self::add_to_queue_from_url(Some(url.clone()), Some(repeat_download)).await {
-                Ok(_) => {
-                    println!("Url successfully queued: {}", url.clone().green());
-                    let removed = DjWizardLog::remove_url_from_url_list(url.clone())
-                        .change_context(QueueError)?;
-                    if !removed {
-                        println!("Url could not be removed from the list: {}", url.red());
-                    }
-                }
-                Err(err) => {
-                    println!(
-                        "Url: {} \n Could not be queued due to: {}",
-                        url.clone().yellow(),
-                        err
-                    );
-                }
-            };
+        let folder = loop {
+            let folder_input = Dialoguer::optional_input(
+                "Folder inside the download folder (empty for the download folder)".to_string(),
+                current.folder.clone().unwrap_or_default(),
+            )
+            .change_context(QueueError)?;
+            if folder_input.is_empty() {
+                break None;
+            }
+            let folder_path = Path::new(&folder_input);
+            let inside_download_folder = folder_path.is_relative()
+                && folder_path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)));
+            if inside_download_folder {
+                break Some(folder_input);
+            }
+            println!(
+                "{}",
+                "The folder should be a path inside the download folder, e.g. DnB".red()
+            );
+        };
+
+        Ok(DownloadTarget {
+            format: formats[format_selection],
+            folder,
+        })
+    }
+
+    /// Pages of the url to walk, all of them when the input is empty
+    pub fn prompt_for_max_pages() -> QueueResult<Option<u32>> {
+        loop {
+            let max_pages = Dialoguer::optional_input(
+                "Maximum number of pages to read (empty for all of them)".to_string(),
+                String::new(),
+            )
+            .change_context(QueueError)?;
+            if max_pages.is_empty() {
+                return Ok(None);
+            }
+            match max_pages.parse::<u32>() {
+                Ok(max_pages) if max_pages > 0 => return Ok(Some(max_pages)),
+                _ => println!(
+                    "{}",
+                    "The number of pages should be a positive number".red()
+                ),
+            }
         }
-        Ok(())
+    }
+
+    async fn add_to_queue_from_url_list() -> QueueResult<()> {
+        UrlListCommands::sync_url_list()
+            .await
+            .change_context(QueueError)
     }
 
     async fn add_to_queue_from_url(
@@ -315,8 +328,12 @@ impl QueueCommands {
                 soundeo_url.to_string()
             }
         };
+        let max_pages = Self::prompt_for_max_pages()?;
 
-        let selected_priority = Self::prompt_for_priority()?;
+        let selected_priority = Self::prompt_for_priority(
+            "Choose a priority for this batch of songs".to_string(),
+            Priority::Normal,
+        )?;
 
         let repeat_download_result = match repeat_download {
             Some(repeat_download_bool) => repeat_download_bool,
@@ -334,12 +351,18 @@ impl QueueCommands {
             .login_and_update_user_info()
             .await
             .change_context(QueueError)?;
-        let mut track_list =
-            SoundeoTracksList::new(soundeo_url_string.clone()).change_context(QueueError)?;
+        let mut track_list = SoundeoTracksList::new(soundeo_url_string.clone())
+            .change_context(QueueError)?
+            .max_pages(max_pages);
         track_list
-            .get_tracks_id(&soundeo_user)
+            .get_tracks(&soundeo_user)
             .await
             .change_context(QueueError)?;
+        let track_ids: Vec<String> = track_list
+            .tracks
+            .into_iter()
+            .map(|listed_track| listed_track.id)
+            .collect();
         println!(
             "Queueing {} tracks",
             format!("{}", track_list.track_ids.len()).cyan()
@@ -349,9 +372,11 @@ impl QueueCommands {
         let context_description = format!("from URL: {}", soundeo_url_string);
 
         TrackProcessor::process_tracks_to_queue(
-            &track_list.track_ids,
+            &track_ids,
             &soundeo_user,
             selected_priority,
+            &DownloadTarget::default(),
+            Some("url".to_string()),
             repeat_download_result,
             &context_description,
         )
@@ -419,7 +444,10 @@ impl QueueCommands {
 
         // Clone soundeo_user for this async operation
         let mut user_clone = soundeo_user.clone();
-        let download_url_result = track_info.get_download_url(&mut user_clone).await;
+        let target = DjWizardLog::get_download_target(&track_id).change_context(QueueError)?;
+        let download_url_result = track_info
+            .get_download_url(&mut user_clone, target.format)
+            .await;
 
         match download_url_result {
             Ok(_) => {
@@ -443,20 +471,22 @@ impl QueueCommands {
         let prompt_text = format!("Soundeo url: ");
         let url = Dialoguer::input(prompt_text).change_context(QueueError)?;
         let soundeo_url = Url::parse(&url).into_report().change_context(QueueError)?;
+        let max_pages = Self::prompt_for_max_pages()?;
         let mut soundeo_user = SoundeoUser::new().change_context(QueueError)?;
         soundeo_user
             .login_and_update_user_info()
             .await
             .change_context(QueueError)?;
-        let mut track_list =
-            SoundeoTracksList::new(soundeo_url.to_string()).change_context(QueueError)?;
+        let mut track_list = SoundeoTracksList::new(soundeo_url.to_string())
+            .change_context(QueueError)?
+            .max_pages(max_pages);
         track_list
-            .get_tracks_id(&soundeo_user)
+            .get_tracks(&soundeo_user)
             .await
             .change_context(QueueError)?;
         println!(
             "Saving {} tracks using 4 parallel workers",
-            format!("{}", track_list.track_ids.len()).cyan()
+            format!("{}", track_list.tracks.len()).cyan()
         );
 
         let available_tracks = DjWizardLog::get_available_tracks().change_context(QueueError)?;
@@ -474,10 +504,15 @@ impl QueueCommands {
 
         // Phase 1: Process tracks in parallel (HTTP requests only)
         let semaphore = Arc::new(Semaphore::new(4));
-        let total_tracks = track_list.track_ids.len();
+        let total_tracks = track_list.tracks.len();
 
         let mut tasks = Vec::new();
-        for (index, track_id) in track_list.track_ids.into_iter().enumerate() {
+        for (index, track_id) in track_list
+            .tracks
+            .into_iter()
+            .map(|listed_track| listed_track.id)
+            .enumerate()
+        {
             let sem = semaphore.clone();
             let user = soundeo_user.clone();
             let available = available_tracks.clone();
@@ -561,9 +596,12 @@ impl QueueCommands {
                     println!("Error adding track to the collection:\n{}", error);
                     if should_queue {
                         println!("Adding to the queue with priority {:?}", fallback_priority);
-                        let queue_result =
-                            DjWizardLog::add_queued_track(track_id.clone(), fallback_priority)
-                                .change_context(QueueError)?;
+                        let queue_result = DjWizardLog::add_queued_track(
+                            track_id.clone(),
+                            fallback_priority,
+                            Some("url".to_string()),
+                        )
+                        .change_context(QueueError)?;
                         if queue_result {
                             println!("Track with id {} successfully queued", track_id.green(),);
                         } else {
@@ -588,7 +626,7 @@ impl QueueCommands {
         {
             let user = soundeo_user.lock().await;
             track_info
-                .get_info(&user, true)
+                .get_info(&user, false)
                 .await
                 .change_context(QueueError)?;
         }
@@ -606,9 +644,11 @@ impl QueueCommands {
         }
 
         // Try to get download URL (this requires mutable access to the user)
+        let target =
+            DjWizardLog::get_download_target(&queued_track.track_id).change_context(QueueError)?;
         let download_url_result = {
             let mut user = soundeo_user.lock().await;
-            track_info.get_download_url(&mut user).await
+            track_info.get_download_url(&mut user, target.format).await
         };
 
         match download_url_result {
@@ -619,7 +659,12 @@ impl QueueCommands {
                     title: track_info.title,
                 })
             }
-            Err(_) => {
+            Err(error) => {
+                log::warn!(
+                    "Failed to get the download url of track {}: {:?}",
+                    track_info.id,
+                    error
+                );
                 // Check if track is STEM when download URL fails
                 let stem_check_result = {
                     let user = soundeo_user.lock().await;
@@ -652,18 +697,40 @@ impl QueueCommands {
         }
     }
 
+    async fn refresh_stats(state: &WorkerState) {
+        let stats = state.stats.lock().await;
+        let reset_time = state
+            .soundeo_user
+            .lock()
+            .await
+            .remaining_time_to_reset
+            .clone();
+        state.display.update_stats(stats.get_status_line(
+            state.download_counter.load(Ordering::SeqCst),
+            &reset_time,
+            state.controls.is_paused(),
+        ));
+    }
+
     async fn worker_loop(worker_id: usize, state: Arc<WorkerState>) -> QueueResult<()> {
         loop {
-            // Update display: Worker X: Idle
-            state
-                .display
-                .update_worker(worker_id, format!("⏳ Worker {}: Idle", worker_id + 1))
-                .await;
+            if state.controls.is_paused() {
+                state
+                    .display
+                    .update_worker(worker_id, format!("⏸️  Worker {}: Paused", worker_id + 1));
+                Self::refresh_stats(&state).await;
+                while state.controls.is_paused() {
+                    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                }
+                Self::refresh_stats(&state).await;
+            }
 
             // Take next track from queue
             let track = {
                 let mut queue = state.track_queue.lock().await;
-                queue.pop_front()
+                let track = queue.pop_front();
+                state.controls.render_up_next(&queue);
+                track
             };
 
             let track = match track {
@@ -688,13 +755,10 @@ impl QueueCommands {
                 if real_downloads == 0 {
                     // Put track back and finish
                     state.track_queue.lock().await.push_front(track);
-                    state
-                        .display
-                        .update_worker(
-                            worker_id,
-                            format!("⏸️  Worker {}: No downloads left", worker_id + 1),
-                        )
-                        .await;
+                    state.display.update_worker(
+                        worker_id,
+                        format!("⏸️  Worker {}: No downloads left", worker_id + 1),
+                    );
                     break;
                 }
                 state
@@ -703,17 +767,11 @@ impl QueueCommands {
             }
 
             // Process track
-            let track_title = format!(
-                "'{}'",
-                &track.track_id[..std::cmp::min(20, track.track_id.len())]
+            let track_title = state.controls.title(&track.track_id);
+            state.display.update_worker(
+                worker_id,
+                format!("🔄 Worker {}: Processing {}", worker_id + 1, track_title),
             );
-            state
-                .display
-                .update_worker(
-                    worker_id,
-                    format!("🔄 Worker {}: Processing {}", worker_id + 1, track_title),
-                )
-                .await;
 
             let result = QueueCommands::process_single_track_for_queue(
                 track,
@@ -728,57 +786,57 @@ impl QueueCommands {
                 let mut stats = state.stats.lock().await;
                 stats.processed += 1;
 
-                match &result {
-                    TrackQueueResult::Downloaded { .. } => {
+                let (worker_message, event) = match &result {
+                    TrackQueueResult::Downloaded { title, .. } => {
                         state.download_counter.fetch_sub(1, Ordering::SeqCst);
                         stats.downloaded += 1;
-                        state
-                            .display
-                            .update_worker(
-                                worker_id,
-                                format!("✅ Worker {}: Completed", worker_id + 1),
-                            )
-                            .await;
+                        (
+                            format!("✅ Worker {}: Completed", worker_id + 1),
+                            format!("✅ Added to the collection: {}", title.green()),
+                        )
                     }
-                    TrackQueueResult::NotDownloadable { .. } => {
+                    TrackQueueResult::NotDownloadable {
+                        title, track_url, ..
+                    } => {
                         stats.not_downloadable += 1;
-                        state
-                            .display
-                            .update_worker(
-                                worker_id,
-                                format!("🚫 Worker {}: Not downloadable", worker_id + 1),
-                            )
-                            .await;
+                        (
+                            format!("🚫 Worker {}: Not downloadable", worker_id + 1),
+                            format!("🚫 Not downloadable: {} {}", title.yellow(), track_url),
+                        )
                     }
-                    TrackQueueResult::StemTrack { .. } => {
+                    TrackQueueResult::StemTrack {
+                        title, track_url, ..
+                    } => {
                         stats.stem_tracks += 1;
-                        state
-                            .display
-                            .update_worker(
-                                worker_id,
-                                format!("🎛️  Worker {}: STEM track", worker_id + 1),
-                            )
-                            .await;
+                        (
+                            format!("🎛️  Worker {}: STEM track", worker_id + 1),
+                            format!("🎛️  STEM track: {} {}", title.yellow(), track_url),
+                        )
                     }
-                    TrackQueueResult::Failed { .. } => {
+                    TrackQueueResult::Failed {
+                        title, track_url, ..
+                    } => {
                         stats.failed += 1;
-                        state
-                            .display
-                            .update_worker(
-                                worker_id,
-                                format!("❌ Worker {}: Failed", worker_id + 1),
-                            )
-                            .await;
+                        RunHistory::track_failed();
+                        (
+                            format!("❌ Worker {}: Failed", worker_id + 1),
+                            format!(
+                                "❌ Failed, kept in the queue: {} {}",
+                                title.red(),
+                                track_url
+                            ),
+                        )
                     }
-                }
-
-                // Update stats display
-                let downloads_left = state.download_counter.load(Ordering::SeqCst);
-                state
-                    .display
-                    .update_stats(stats.get_remaining_downloads(downloads_left))
-                    .await;
+                };
+                state.display.update_worker(worker_id, worker_message);
+                state.display.log_event(event);
+                log::info!(
+                    "Worker {} processed a queued track: {:?}",
+                    worker_id + 1,
+                    result
+                );
             }
+            Self::refresh_stats(&state).await;
 
             // Apply log changes
             Self::apply_queue_batch_results(vec![result]).await?;
@@ -786,8 +844,7 @@ impl QueueCommands {
 
         state
             .display
-            .update_worker(worker_id, format!("✅ Worker {}: Finished", worker_id + 1))
-            .await;
+            .update_worker(worker_id, format!("✅ Worker {}: Finished", worker_id + 1));
         Ok(())
     }
 
@@ -800,20 +857,13 @@ impl QueueCommands {
             Dialoguer::select_yes_or_no("Do you want to filter by genre".to_string())
                 .change_context(QueueError)?
         };
-        let mut queued_tracks = if filtered_by_genre {
+        let queued_tracks = if filtered_by_genre {
             Self::filter_queue()?
         } else {
             DjWizardLog::get_queued_tracks().change_context(QueueError)?
         };
 
-        // Sort the queue by priority and then by order_key
-        queued_tracks.sort_by(|a, b| {
-            let priority_ord = a.priority.cmp(&b.priority);
-            if priority_ord != std::cmp::Ordering::Equal {
-                return priority_ord;
-            }
-            a.order_key.partial_cmp(&b.order_key).unwrap()
-        });
+        let queued_tracks = Self::schedule_queued_tracks(queued_tracks)?;
 
         let mut soundeo_user = SoundeoUser::new().change_context(QueueError)?;
         soundeo_user
@@ -834,7 +884,9 @@ impl QueueCommands {
                 "No downloads available, skipping queue processing".yellow()
             );
             // Skip queue processing but continue to download available tracks
-            Self::download_available_tracks(&mut soundeo_user).await?;
+            let display = QueueDisplay::new(0, false);
+            Self::download_available_tracks(&mut soundeo_user, &display).await?;
+            display.finish();
             return Ok(());
         }
 
@@ -844,9 +896,18 @@ impl QueueCommands {
             format!("{}", total_tracks).cyan()
         );
 
-        // Setup fixed display
-        let display = Arc::new(FixedDisplay::new());
-        display.initialize().await;
+        let Soundeo { tracks_info, .. } = DjWizardLog::get_soundeo().change_context(QueueError)?;
+        let titles: HashMap<String, String> = queued_tracks
+            .iter()
+            .filter_map(|track| {
+                tracks_info
+                    .get(&track.track_id)
+                    .map(|info| (track.track_id.clone(), info.title.clone()))
+            })
+            .collect();
+
+        let display = Arc::new(QueueDisplay::new(4, true));
+        let controls = QueueControls::new(display.clone(), titles);
 
         // Initialize shared state
         let track_queue = Arc::new(Mutex::new(VecDeque::from(queued_tracks)));
@@ -855,20 +916,17 @@ impl QueueCommands {
         let soundeo_user_shared = Arc::new(Mutex::new(soundeo_user));
 
         let state = Arc::new(WorkerState {
-            track_queue,
+            track_queue: track_queue.clone(),
             download_counter,
             stats: stats.clone(),
             soundeo_user: soundeo_user_shared.clone(),
             display: display.clone(),
+            controls: controls.clone(),
         });
 
-        // Initialize stats display
-        {
-            let stats_guard = stats.lock().await;
-            display
-                .update_stats(stats_guard.get_remaining_downloads(initial_downloads as usize))
-                .await;
-        }
+        Self::refresh_stats(&state).await;
+        controls.render_up_next(&*track_queue.lock().await);
+        controls.listen(track_queue.clone());
 
         // Spawn 4 workers
         let mut worker_handles = Vec::new();
@@ -879,18 +937,35 @@ impl QueueCommands {
             worker_handles.push(handle);
         }
 
-        // Wait for all workers to complete
+        // Wait for all workers to complete, stopping the key listener even if one failed
+        let mut worker_results = Vec::new();
         for handle in worker_handles {
-            handle.await.unwrap()?;
+            worker_results.push(handle.await.unwrap());
+        }
+        controls.finish();
+        for result in worker_results {
+            result?;
         }
 
-        // Clear the fixed display
-        display.clear().await;
+        // Persist the tracks bumped during the run that are still queued
+        let queued_ids: HashSet<String> = DjWizardLog::get_queued_tracks()
+            .change_context(QueueError)?
+            .into_iter()
+            .map(|track| track.track_id)
+            .collect();
+        let bumped_tracks: Vec<String> = controls
+            .bumped_tracks()
+            .into_iter()
+            .filter(|track_id| queued_ids.contains(track_id))
+            .collect();
+        if !bumped_tracks.is_empty() {
+            DjWizardLog::promote_tracks_to_top(&bumped_tracks).change_context(QueueError)?;
+        }
 
         // Print final summary
         let final_stats = stats.lock().await;
-        println!("{}", "Queue processing completed!".green());
-        println!(
+        display.log_event("Queue processing completed!".green().to_string());
+        display.log_event(format!(
             "📊 Final stats: {}/{} processed | {} downloaded | {} failed | {} stem | {} not-downloadable",
             final_stats.processed,
             final_stats.total_tracks,
@@ -898,21 +973,25 @@ impl QueueCommands {
             final_stats.failed,
             final_stats.stem_tracks,
             final_stats.not_downloadable
-        );
+        ));
 
         // Continue with downloading available tracks
         let available_downloads = DjWizardLog::get_available_tracks().change_context(QueueError)?;
 
         if available_downloads.is_empty() {
+            display.finish();
             println!("{}", "No available tracks to download".yellow());
             return Ok(());
         }
 
-        println!(
+        display.log_event(format!(
             "\n{} tracks found, proceeding to download",
             format!("{}", available_downloads.len()).cyan()
-        );
-        println!("Downloading from the {} queue", "available tracks".green());
+        ));
+        display.log_event(format!(
+            "Downloading from the {} queue",
+            "available tracks".green()
+        ));
 
         // Extract the user for downloading phase
         let mut soundeo_user_final = {
@@ -920,7 +999,185 @@ impl QueueCommands {
             user_guard.clone()
         };
 
-        Self::download_available_tracks(&mut soundeo_user_final).await?;
+        Self::download_available_tracks(&mut soundeo_user_final, &display).await?;
+        display.finish();
+        Ok(())
+    }
+
+    /// Orders the queue with the scheduling policy from the config, the order used to download it
+    fn schedule_queued_tracks(queued_tracks: Vec<QueuedTrack>) -> QueueResult<Vec<QueuedTrack>> {
+        let mut user_config = User::new();
+        user_config.read_config_file().change_context(QueueError)?;
+        let soundeo = DjWizardLog::get_soundeo().change_context(QueueError)?;
+        Ok(user_config
+            .queue_scheduler
+            .schedule(queued_tracks, &soundeo.tracks_info))
+    }
+
+    fn configure_scheduling() -> QueueResult<()> {
+        let mut user_config = User::new();
+        user_config.read_config_file().change_context(QueueError)?;
+        let policies = SchedulingPolicy::iter().collect::<Vec<_>>();
+        let options = vec![
+            "Strict: priority first, then the order in which tracks were queued",
+            "Fair: old tracks move up one priority level over time and sources take turns",
+        ];
+        let current = policies
+            .iter()
+            .position(|policy| *policy == user_config.queue_scheduler.policy);
+        let selection = Dialoguer::select(
+            "Select the queue scheduling policy".to_string(),
+            options,
+            current,
+        )
+        .change_context(QueueError)?;
+        user_config.queue_scheduler.policy = policies[selection];
+
+        if user_config.queue_scheduler.policy == SchedulingPolicy::Fair {
+            let prompt_text = format!(
+                "Days a track waits before moving up one priority level (current: {}): ",
+                user_config.queue_scheduler.aging_interval_days
+            );
+            let aging_interval_days = Dialoguer::input(prompt_text).change_context(QueueError)?;
+            user_config.queue_scheduler.aging_interval_days = aging_interval_days
+                .trim()
+                .parse::<u64>()
+                .into_report()
+                .attach_printable("The aging interval should be a number of days")
+                .change_context(QueueError)?;
+        }
+        user_config.save_config_file().change_context(QueueError)?;
+        println!(
+            "Queue scheduling policy set to {}",
+            user_config.queue_scheduler.policy.to_string().green()
+        );
+        Ok(())
+    }
+
+    async fn plan_queue() -> QueueResult<()> {
+        let queued_tracks = DjWizardLog::get_queued_tracks().change_context(QueueError)?;
+        if queued_tracks.is_empty() {
+            println!("{}", "The queue is empty, nothing to plan".yellow());
+            return Ok(());
+        }
+        let queued_tracks = Self::schedule_queued_tracks(queued_tracks)?;
+        let soundeo = DjWizardLog::get_soundeo().change_context(QueueError)?;
+        let available_tracks = DjWizardLog::get_available_tracks().change_context(QueueError)?;
+
+        let mut user_config = User::new();
+        user_config.read_config_file().change_context(QueueError)?;
+        let mut soundeo_user = SoundeoUser::new().change_context(QueueError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(QueueError)?;
+        let (main_downloads, bonus_downloads) = soundeo_user
+            .check_remaining_downloads()
+            .await
+            .change_context(QueueError)?;
+
+        let plan = QueuePlan::build(
+            &queued_tracks,
+            &soundeo.tracks_info,
+            &available_tracks,
+            main_downloads,
+            bonus_downloads,
+            user_config.daily_download_quota,
+        );
+
+        println!(
+            "Scheduling policy: {}",
+            user_config.queue_scheduler.policy.to_string().cyan()
+        );
+        println!(
+            "Remaining downloads: {} main + {} bonus (main resets in {})",
+            plan.main_credits.to_string().cyan(),
+            plan.bonus_credits.to_string().cyan(),
+            soundeo_user.remaining_time_to_reset.cyan()
+        );
+        println!(
+            "\n{} tracks would be downloaded before the next reset:",
+            plan.fits_before_reset().to_string().green()
+        );
+        for (index, planned_track) in plan
+            .planned
+            .iter()
+            .filter(|track| !matches!(track.credit, PlanCredit::AfterReset(_)))
+            .enumerate()
+        {
+            let credit = match planned_track.credit {
+                PlanCredit::Main => "main",
+                _ => "bonus",
+            };
+            println!(
+                "  {}. [{:?}] {} ({})",
+                index + 1,
+                planned_track.priority,
+                planned_track
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("track id {}", planned_track.track_id)),
+                credit
+            );
+        }
+
+        for skipped_track in plan
+            .skipped
+            .iter()
+            .filter(|track| track.reason != PlanSkipReason::AlreadyAvailable)
+        {
+            println!(
+                "Skipping {} ({:?})",
+                skipped_track
+                    .title
+                    .clone()
+                    .unwrap_or_else(|| format!("track id {}", skipped_track.track_id))
+                    .yellow(),
+                skipped_track.reason
+            );
+        }
+
+        println!("\n{}", "Queue plan summary".green());
+        println!(
+            "  Queued tracks: {}",
+            queued_tracks.len().to_string().cyan()
+        );
+        println!(
+            "  To download: {} ({} main, {} bonus, {} after a reset)",
+            plan.planned.len().to_string().cyan(),
+            plan.count_by_credit(PlanCredit::Main),
+            plan.count_by_credit(PlanCredit::Bonus),
+            plan.planned.len() - plan.fits_before_reset()
+        );
+        println!(
+            "  Skipped: {} not downloadable, {} STEM, {} already downloaded",
+            plan.count_skipped(PlanSkipReason::NotDownloadable),
+            plan.count_skipped(PlanSkipReason::Stem),
+            plan.count_skipped(PlanSkipReason::AlreadyDownloaded)
+        );
+        println!(
+            "  Already available (no credits needed): {}",
+            plan.count_skipped(PlanSkipReason::AlreadyAvailable)
+        );
+        if plan.unresolved > 0 {
+            println!(
+                "  {} tracks have no cached info and are assumed downloadable",
+                plan.unresolved.to_string().yellow()
+            );
+        }
+        let reset_cycles = plan.reset_cycles_needed();
+        if reset_cycles == 0 {
+            println!(
+                "{}",
+                "The whole queue fits in the remaining downloads".green()
+            );
+        } else {
+            println!(
+                "  Reset cycles needed: {} (assuming {} main downloads per reset)",
+                reset_cycles.to_string().yellow(),
+                plan.daily_quota
+            );
+        }
         Ok(())
     }
 
@@ -966,46 +1223,63 @@ impl QueueCommands {
         Ok(())
     }
 
-    async fn download_available_tracks(soundeo_user: &mut SoundeoUser) -> QueueResult<()> {
+    async fn download_available_tracks(
+        soundeo_user: &mut SoundeoUser,
+        display: &QueueDisplay,
+    ) -> QueueResult<()> {
         let available_tracks: HashSet<String> = DjWizardLog::get_available_tracks()
             .change_context(QueueError)?
             .into_iter()
             .collect();
 
         if available_tracks.is_empty() {
-            println!("No tracks available to download");
+            display.log_event("No tracks available to download".to_string());
             return Ok(());
         }
 
-        println!(
+        display.log_event(format!(
             "{} tracks available to download",
             format!("{}", available_tracks.len()).cyan()
-        );
+        ));
 
         for (available_id_index, available_id) in available_tracks.clone().into_iter().enumerate() {
-            println!(
-                "Downloading track {} of {}",
+            display.update_stats(format!(
+                "📥 Downloading track {} of {} | {}",
                 (available_id_index + 1).to_string().cyan(),
-                available_tracks.len().to_string().cyan()
-            );
+                available_tracks.len().to_string().cyan(),
+                soundeo_user.get_remamining_downloads_string()
+            ));
             let mut track_info = SoundeoTrack::new(available_id.clone());
+            let target =
+                DjWizardLog::get_download_target(&available_id).change_context(QueueError)?;
             let download_result = track_info
-                .download_track(soundeo_user, false, false)
+                .download_track_with_progress(
+                    soundeo_user,
+                    false,
+                    false,
+                    display.multi_progress(),
+                    &target,
+                )
                 .await
                 .change_context(QueueError);
             match download_result {
                 Ok(_) => {
                     DjWizardLog::remove_available_track(available_id.clone())
                         .change_context(QueueError)?;
+                    DjWizardLog::set_download_target(
+                        available_id.clone(),
+                        DownloadTarget::default(),
+                    )
+                    .change_context(QueueError)?;
                 }
                 Err(error) => {
-                    println!(
+                    display.log_event(format!(
                         "Track {} ({}) with id {} was not downloaded",
                         track_info.title.red(),
                         track_info.get_track_url().yellow(),
                         available_id.clone().red()
-                    );
-                    println!("Error: {:?}", error)
+                    ));
+                    display.log_event(format!("Error: {:?}", error));
                 }
             }
         }
@@ -1026,12 +1300,31 @@ impl QueueCommands {
         }
         let mut genres = genres_hash_set.into_iter().collect::<Vec<String>>();
         genres.sort();
-        for genre in genres {
-            let amount = q_tracks_info
-                .clone()
-                .into_iter()
-                .filter(|track| track.genre == genre)
-                .count();
+        let genre_counts: Vec<(String, usize)> = genres
+            .into_iter()
+            .map(|genre| {
+                let amount = q_tracks_info
+                    .iter()
+                    .filter(|track| track.genre == genre)
+                    .count();
+                (genre, amount)
+            })
+            .collect();
+
+        if OutputFormat::is_json() {
+            let genres_json: Vec<serde_json::Value> = genre_counts
+                .iter()
+                .map(|(genre, amount)| serde_json::json!({ "genre": genre, "tracks": amount }))
+                .collect();
+            return OutputFormat::print_json(&serde_json::json!({
+                "genres": genres_json,
+                "total": q_tracks_info.len(),
+                "queued_tracks": queued_tracks.len(),
+            }))
+            .change_context(QueueError);
+        }
+
+        for (genre, amount) in genre_counts {
             println!("{}: {} tracks", genre.cyan(), amount);
         }
         println!(
@@ -1269,11 +1562,7 @@ impl QueueCommands {
                     soundeo_info
                         .tracks_info
                         .get(&q_track.track_id)
-                        .map_or(false, |info| {
-                            info.title
-                                .to_lowercase()
-                                .contains(&artist_query.to_lowercase())
-                        })
+                        .map_or(false, |info| contains_words(&info.title, artist_query))
                 })
                 .collect();
 
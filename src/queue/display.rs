@@ -0,0 +1,367 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use colored::Colorize;
+use dialoguer::console::{Key, Term};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tokio::sync::Mutex;
+
+use crate::log::QueuedTrack;
+
+const UP_NEXT_ROWS: usize = 5;
+/// How long the key listener waits for a key before checking if the run finished
+const KEY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+struct DisplayPanes {
+    multi: MultiProgress,
+    stats: ProgressBar,
+    workers: Vec<ProgressBar>,
+    up_next: Vec<ProgressBar>,
+}
+
+/// Renders a queue run: a stats line with the live quota, one pane per worker,
+/// the upcoming tracks and a scrolling event log above them.
+/// When stdout is not a terminal every update is printed as a plain line instead
+pub struct QueueDisplay {
+    panes: Option<DisplayPanes>,
+}
+
+impl QueueDisplay {
+    pub fn new(worker_count: usize, show_up_next: bool) -> Self {
+        if !std::io::stdout().is_terminal() {
+            return Self { panes: None };
+        }
+        let multi = MultiProgress::new();
+        let line_style = ProgressStyle::with_template("{msg}").unwrap();
+        let worker_style = ProgressStyle::with_template("{spinner:.green} {msg}").unwrap();
+
+        let stats = multi.add(ProgressBar::new_spinner().with_style(line_style.clone()));
+        stats.set_message("📊 Initializing...");
+        let workers = (0..worker_count)
+            .map(|worker_id| {
+                let worker = multi.add(ProgressBar::new_spinner().with_style(worker_style.clone()));
+                worker.set_message(format!("⏳ Worker {}: Idle", worker_id + 1));
+                worker.enable_steady_tick(Duration::from_millis(120));
+                worker
+            })
+            .collect();
+        let up_next = if show_up_next {
+            (0..=UP_NEXT_ROWS)
+                .map(|_| multi.add(ProgressBar::new_spinner().with_style(line_style.clone())))
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Self {
+            panes: Some(DisplayPanes {
+                multi,
+                stats,
+                workers,
+                up_next,
+            }),
+        }
+    }
+
+    pub fn is_interactive(&self) -> bool {
+        self.panes.is_some()
+    }
+
+    /// Lets download progress bars render inside the display
+    pub fn multi_progress(&self) -> Option<&MultiProgress> {
+        self.panes.as_ref().map(|panes| &panes.multi)
+    }
+
+    pub fn update_stats(&self, message: String) {
+        match &self.panes {
+            Some(panes) => panes.stats.set_message(message),
+            None => println!("{}", message),
+        }
+    }
+
+    pub fn update_worker(&self, worker_id: usize, message: String) {
+        match &self.panes {
+            Some(panes) => {
+                if let Some(worker) = panes.workers.get(worker_id) {
+                    worker.set_message(message);
+                }
+            }
+            None => println!("{}", message),
+        }
+    }
+
+    /// Prints a line above the live panes, so it scrolls with the rest of the output
+    pub fn log_event(&self, message: String) {
+        match &self.panes {
+            Some(panes) => {
+                let _ = panes.multi.println(message);
+            }
+            None => println!("{}", message),
+        }
+    }
+
+    fn update_up_next(&self, upcoming: &[String], selected: usize) {
+        let Some(panes) = &self.panes else {
+            return;
+        };
+        let Some((header, rows)) = panes.up_next.split_first() else {
+            return;
+        };
+        header.set_message(format!(
+            "{} {}",
+            "Up next".cyan(),
+            "[p] pause/resume  [↑/↓] select  [s] skip for this run  [b] bump to the top".dimmed()
+        ));
+        for (index, row) in rows.iter().enumerate() {
+            match upcoming.get(index) {
+                Some(title) if index == selected => {
+                    row.set_message(format!("  ▶ {}. {}", index + 1, title.yellow()))
+                }
+                Some(title) => row.set_message(format!("    {}. {}", index + 1, title)),
+                None => row.set_message(""),
+            }
+        }
+    }
+
+    pub fn finish(&self) {
+        if let Some(panes) = &self.panes {
+            for bar in panes
+                .workers
+                .iter()
+                .chain(panes.up_next.iter())
+                .chain(std::iter::once(&panes.stats))
+            {
+                bar.finish_and_clear();
+            }
+            let _ = panes.multi.clear();
+        }
+    }
+}
+
+/// Keyboard controls of a queue run, shared between the workers and the key listener
+pub struct QueueControls {
+    display: Arc<QueueDisplay>,
+    titles: HashMap<String, String>,
+    paused: AtomicBool,
+    finished: AtomicBool,
+    selected: AtomicUsize,
+    bumped: std::sync::Mutex<Vec<String>>,
+}
+
+impl QueueControls {
+    pub fn new(display: Arc<QueueDisplay>, titles: HashMap<String, String>) -> Arc<Self> {
+        Arc::new(Self {
+            display,
+            titles,
+            paused: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            selected: AtomicUsize::new(0),
+            bumped: std::sync::Mutex::new(vec![]),
+        })
+    }
+
+    pub fn title(&self, track_id: &str) -> String {
+        self.titles
+            .get(track_id)
+            .cloned()
+            .unwrap_or_else(|| format!("track id {}", track_id))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Tracks bumped during the run, to be promoted in the log once the workers finish
+    pub fn bumped_tracks(&self) -> Vec<String> {
+        self.bumped.lock().unwrap().clone()
+    }
+
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::SeqCst);
+    }
+
+    pub fn render_up_next(&self, track_queue: &VecDeque<QueuedTrack>) {
+        let upcoming: Vec<String> = track_queue
+            .iter()
+            .take(UP_NEXT_ROWS)
+            .map(|track| self.title(&track.track_id))
+            .collect();
+        let selected = self
+            .selected
+            .load(Ordering::SeqCst)
+            .min(upcoming.len().saturating_sub(1));
+        self.selected.store(selected, Ordering::SeqCst);
+        self.display.update_up_next(&upcoming, selected);
+    }
+
+    /// Reads keys on a dedicated thread while the queue runs, until `finish` is called.
+    /// Only enabled when both stdin and stdout are terminals
+    pub fn listen(self: &Arc<Self>, track_queue: Arc<Mutex<VecDeque<QueuedTrack>>>) {
+        if !self.display.is_interactive() || !std::io::stdin().is_terminal() {
+            return;
+        }
+        let controls = self.clone();
+        std::thread::spawn(move || {
+            let term = Term::stdout();
+            let _key_input = KeyInput::enable();
+            while !controls.finished.load(Ordering::SeqCst) {
+                if !KeyInput::wait_for_key(KEY_POLL_INTERVAL) {
+                    continue;
+                }
+                let Ok(key) = term.read_key() else {
+                    break;
+                };
+                if controls.finished.load(Ordering::SeqCst) {
+                    break;
+                }
+                let mut queue = track_queue.blocking_lock();
+                controls.handle_key(key, &mut queue);
+                controls.render_up_next(&queue);
+            }
+        });
+    }
+
+    fn handle_key(&self, key: Key, queue: &mut VecDeque<QueuedTrack>) {
+        let selected = self.selected.load(Ordering::SeqCst);
+        match key {
+            Key::Char('p') => {
+                let paused = !self.paused.fetch_xor(true, Ordering::SeqCst);
+                let message = if paused {
+                    "⏸️  Paused, workers stop after their current track".yellow()
+                } else {
+                    "▶️  Resumed".green()
+                };
+                self.display.log_event(message.to_string());
+            }
+            Key::ArrowUp | Key::Char('k') => {
+                self.selected
+                    .store(selected.saturating_sub(1), Ordering::SeqCst);
+            }
+            Key::ArrowDown | Key::Char('j') => {
+                self.selected.store(selected + 1, Ordering::SeqCst);
+            }
+            Key::Char('s') => {
+                if let Some(track) = queue.remove(selected) {
+                    self.display.log_event(format!(
+                        "⏭️  Skipped {} for this run, it stays in the queue",
+                        self.title(&track.track_id).yellow()
+                    ));
+                }
+            }
+            Key::Char('b') => {
+                if let Some(track) = queue.remove(selected) {
+                    self.display.log_event(format!(
+                        "⏫ Bumped {} to the top of the queue",
+                        self.title(&track.track_id).green()
+                    ));
+                    self.bumped.lock().unwrap().push(track.track_id.clone());
+                    queue.push_front(track);
+                    self.selected.store(0, Ordering::SeqCst);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Stdin without line buffering and echo while the key listener runs, so a key press can be
+/// waited for with a timeout instead of blocking the listener after the run finished
+struct KeyInput {
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+}
+
+impl KeyInput {
+    #[cfg(unix)]
+    fn enable() -> Self {
+        let fd = libc::STDIN_FILENO;
+        let mut termios = std::mem::MaybeUninit::<libc::termios>::uninit();
+        if unsafe { libc::tcgetattr(fd, termios.as_mut_ptr()) } != 0 {
+            return Self { original: None };
+        }
+        let original = unsafe { termios.assume_init() };
+        let mut unbuffered = original;
+        unbuffered.c_lflag &= !(libc::ICANON | libc::ECHO);
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &unbuffered) };
+        Self {
+            original: Some(original),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn enable() -> Self {
+        Self {}
+    }
+
+    /// Whether a key was pressed within `timeout`
+    #[cfg(unix)]
+    fn wait_for_key(timeout: Duration) -> bool {
+        let mut stdin = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        unsafe { libc::poll(&mut stdin, 1, timeout.as_millis() as libc::c_int) > 0 }
+    }
+
+    /// Without a way to poll stdin, `read_key` blocks until the next key
+    #[cfg(not(unix))]
+    fn wait_for_key(_timeout: Duration) -> bool {
+        true
+    }
+}
+
+impl Drop for KeyInput {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(original) = self.original {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &original) };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log::Priority;
+
+    fn queue_of(track_ids: &[&str]) -> VecDeque<QueuedTrack> {
+        track_ids
+            .iter()
+            .map(|track_id| QueuedTrack {
+                track_id: track_id.to_string(),
+                priority: Priority::Normal,
+                order_key: 0.0,
+                added_at: 0,
+                source: None,
+            })
+            .collect()
+    }
+
+    fn ids(queue: &VecDeque<QueuedTrack>) -> Vec<&str> {
+        queue.iter().map(|track| track.track_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_keys_select_skip_and_bump_upcoming_tracks() {
+        let controls = QueueControls::new(Arc::new(QueueDisplay::new(0, false)), HashMap::new());
+        let mut queue = queue_of(&["1", "2", "3", "4"]);
+
+        controls.handle_key(Key::ArrowDown, &mut queue);
+        controls.handle_key(Key::Char('s'), &mut queue);
+        assert_eq!(ids(&queue), vec!["1", "3", "4"]);
+
+        controls.handle_key(Key::ArrowDown, &mut queue);
+        controls.handle_key(Key::Char('b'), &mut queue);
+        assert_eq!(ids(&queue), vec!["4", "1", "3"]);
+        assert_eq!(controls.bumped_tracks(), vec!["4".to_string()]);
+
+        controls.handle_key(Key::Char('p'), &mut queue);
+        assert!(controls.is_paused());
+        controls.handle_key(Key::Char('p'), &mut queue);
+        assert!(!controls.is_paused());
+    }
+}
@@ -1,4 +1,7 @@
 pub mod commands;
+pub mod display;
+pub mod planner;
+pub mod scheduler;
 pub mod track_processor;
 
 use ::serde::{Deserialize, Serialize};
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::log::{Priority, QueuedTrack};
+use crate::soundeo::track::SoundeoTrack;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanSkipReason {
+    NotDownloadable,
+    Stem,
+    AlreadyDownloaded,
+    AlreadyAvailable,
+}
+
+/// Which pool of credits a planned track is expected to consume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanCredit {
+    Main,
+    Bonus,
+    /// The track only fits after the given number of quota resets
+    AfterReset(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedTrack {
+    pub track_id: String,
+    pub title: Option<String>,
+    pub priority: Priority,
+    pub credit: PlanCredit,
+}
+
+#[derive(Debug, Clone)]
+pub struct SkippedTrack {
+    pub track_id: String,
+    pub title: Option<String>,
+    pub reason: PlanSkipReason,
+}
+
+/// Projection of what `resume_queue` would download, built only from the cached log data
+#[derive(Debug, Clone)]
+pub struct QueuePlan {
+    pub planned: Vec<PlannedTrack>,
+    pub skipped: Vec<SkippedTrack>,
+    /// Queued tracks without cached info, assumed to be downloadable
+    pub unresolved: usize,
+    pub main_credits: u32,
+    pub bonus_credits: u32,
+    pub daily_quota: u32,
+}
+
+impl QueuePlan {
+    /// Walks an already sorted queue and assigns each downloadable track to a credit pool.
+    /// Main credits are spent first, then bonus credits, then one `daily_quota` per reset.
+    pub fn build(
+        sorted_queue: &[QueuedTrack],
+        tracks_info: &HashMap<String, SoundeoTrack>,
+        available_tracks: &HashSet<String>,
+        main_credits: u32,
+        bonus_credits: u32,
+        daily_quota: u32,
+    ) -> Self {
+        let mut planned = vec![];
+        let mut skipped = vec![];
+        let mut unresolved = 0;
+        let today_credits = (main_credits + bonus_credits) as usize;
+
+        for queued_track in sorted_queue {
+            let track_info = tracks_info.get(&queued_track.track_id);
+            let title = track_info.map(|info| info.title.clone());
+            if let Some(reason) = Self::skip_reason(queued_track, track_info, available_tracks) {
+                skipped.push(SkippedTrack {
+                    track_id: queued_track.track_id.clone(),
+                    title,
+                    reason,
+                });
+                continue;
+            }
+            if track_info.is_none() {
+                unresolved += 1;
+            }
+
+            let position = planned.len();
+            let credit = if position < main_credits as usize {
+                PlanCredit::Main
+            } else if position < today_credits {
+                PlanCredit::Bonus
+            } else {
+                let pending = (position - today_credits) as u32;
+                PlanCredit::AfterReset(pending / daily_quota.max(1) + 1)
+            };
+            planned.push(PlannedTrack {
+                track_id: queued_track.track_id.clone(),
+                title,
+                priority: queued_track.priority,
+                credit,
+            });
+        }
+
+        Self {
+            planned,
+            skipped,
+            unresolved,
+            main_credits,
+            bonus_credits,
+            daily_quota,
+        }
+    }
+
+    fn skip_reason(
+        queued_track: &QueuedTrack,
+        track_info: Option<&SoundeoTrack>,
+        available_tracks: &HashSet<String>,
+    ) -> Option<PlanSkipReason> {
+        if available_tracks.contains(&queued_track.track_id) {
+            return Some(PlanSkipReason::AlreadyAvailable);
+        }
+        let track_info = track_info?;
+        if !track_info.downloadable {
+            return Some(PlanSkipReason::NotDownloadable);
+        }
+        // STEM-only tracks have a single format, so the cached AIFF size is missing
+        if track_info.size.is_none() {
+            return Some(PlanSkipReason::Stem);
+        }
+        if track_info.already_downloaded {
+            return Some(PlanSkipReason::AlreadyDownloaded);
+        }
+        None
+    }
+
+    pub fn count_by_credit(&self, credit: PlanCredit) -> usize {
+        self.planned
+            .iter()
+            .filter(|track| track.credit == credit)
+            .count()
+    }
+
+    pub fn count_skipped(&self, reason: PlanSkipReason) -> usize {
+        self.skipped
+            .iter()
+            .filter(|track| track.reason == reason)
+            .count()
+    }
+
+    pub fn fits_before_reset(&self) -> usize {
+        self.count_by_credit(PlanCredit::Main) + self.count_by_credit(PlanCredit::Bonus)
+    }
+
+    /// Number of quota resets needed to download the whole queue
+    pub fn reset_cycles_needed(&self) -> u32 {
+        self.planned
+            .iter()
+            .filter_map(|track| match track.credit {
+                PlanCredit::AfterReset(cycle) => Some(cycle),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(track_id: &str) -> QueuedTrack {
+        QueuedTrack {
+            track_id: track_id.to_string(),
+            priority: Priority::Normal,
+            order_key: 0.0,
+            added_at: 0,
+            source: None,
+        }
+    }
+
+    fn track_info(track_id: &str) -> SoundeoTrack {
+        let mut track = SoundeoTrack::new(track_id.to_string());
+        track.title = format!("Artist - Track {}", track_id);
+        track.size = Some("100 MB".to_string());
+        track.downloadable = true;
+        track
+    }
+
+    #[test]
+    fn test_build_assigns_main_bonus_and_reset_credits() {
+        let queue: Vec<QueuedTrack> = (0..7).map(|i| queued(&i.to_string())).collect();
+        let tracks_info = queue
+            .iter()
+            .map(|t| (t.track_id.clone(), track_info(&t.track_id)))
+            .collect();
+
+        let plan = QueuePlan::build(&queue, &tracks_info, &HashSet::new(), 2, 1, 2);
+
+        let credits: Vec<PlanCredit> = plan.planned.iter().map(|t| t.credit).collect();
+        assert_eq!(
+            credits,
+            vec![
+                PlanCredit::Main,
+                PlanCredit::Main,
+                PlanCredit::Bonus,
+                PlanCredit::AfterReset(1),
+                PlanCredit::AfterReset(1),
+                PlanCredit::AfterReset(2),
+                PlanCredit::AfterReset(2),
+            ]
+        );
+        assert_eq!(plan.fits_before_reset(), 3);
+        assert_eq!(plan.reset_cycles_needed(), 2);
+    }
+
+    #[test]
+    fn test_build_skips_tracks_that_would_not_be_downloaded() {
+        let queue = vec![
+            queued("not_downloadable"),
+            queued("stem"),
+            queued("downloaded"),
+            queued("available"),
+            queued("uncached"),
+            queued("ok"),
+        ];
+        let mut tracks_info = HashMap::new();
+        let mut not_downloadable = track_info("not_downloadable");
+        not_downloadable.downloadable = false;
+        tracks_info.insert(not_downloadable.id.clone(), not_downloadable);
+        let mut stem = track_info("stem");
+        stem.size = None;
+        tracks_info.insert(stem.id.clone(), stem);
+        let mut downloaded = track_info("downloaded");
+        downloaded.already_downloaded = true;
+        tracks_info.insert(downloaded.id.clone(), downloaded);
+        tracks_info.insert("ok".to_string(), track_info("ok"));
+        let available = HashSet::from(["available".to_string()]);
+
+        let plan = QueuePlan::build(&queue, &tracks_info, &available, 10, 0, 10);
+
+        assert_eq!(plan.count_skipped(PlanSkipReason::NotDownloadable), 1);
+        assert_eq!(plan.count_skipped(PlanSkipReason::Stem), 1);
+        assert_eq!(plan.count_skipped(PlanSkipReason::AlreadyDownloaded), 1);
+        assert_eq!(plan.count_skipped(PlanSkipReason::AlreadyAvailable), 1);
+        assert_eq!(plan.unresolved, 1);
+        let planned_ids: Vec<&str> = plan.planned.iter().map(|t| t.track_id.as_str()).collect();
+        assert_eq!(planned_ids, vec!["uncached", "ok"]);
+        assert_eq!(plan.reset_cycles_needed(), 0);
+    }
+}
@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::log::{Priority, QueuedTrack};
+use crate::soundeo::track::SoundeoTrack;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+)]
+pub enum SchedulingPolicy {
+    /// Priority first, then insertion order
+    #[default]
+    Strict,
+    /// Old tracks gain priority over time and sources take turns within a priority
+    Fair,
+}
+
+/// Decides the order in which the queue is downloaded
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct QueueScheduler {
+    #[serde(default)]
+    pub policy: SchedulingPolicy,
+    /// Days a track has to wait in the queue to move up one priority level
+    #[serde(default = "QueueScheduler::default_aging_interval_days")]
+    pub aging_interval_days: u64,
+}
+
+impl Default for QueueScheduler {
+    fn default() -> Self {
+        Self {
+            policy: SchedulingPolicy::default(),
+            aging_interval_days: Self::default_aging_interval_days(),
+        }
+    }
+}
+
+impl QueueScheduler {
+    fn default_aging_interval_days() -> u64 {
+        7
+    }
+
+    pub fn schedule(
+        &self,
+        queued_tracks: Vec<QueuedTrack>,
+        tracks_info: &HashMap<String, SoundeoTrack>,
+    ) -> Vec<QueuedTrack> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        self.schedule_at(queued_tracks, tracks_info, now)
+    }
+
+    pub fn schedule_at(
+        &self,
+        mut queued_tracks: Vec<QueuedTrack>,
+        tracks_info: &HashMap<String, SoundeoTrack>,
+        now: u64,
+    ) -> Vec<QueuedTrack> {
+        Self::sort_strict(&mut queued_tracks);
+        match self.policy {
+            SchedulingPolicy::Strict => queued_tracks,
+            SchedulingPolicy::Fair => self.schedule_fair(queued_tracks, tracks_info, now),
+        }
+    }
+
+    /// Sorts the queue by priority and then by order_key
+    fn sort_strict(queued_tracks: &mut [QueuedTrack]) {
+        queued_tracks.sort_by(|a, b| {
+            let priority_ord = a.priority.cmp(&b.priority);
+            if priority_ord != std::cmp::Ordering::Equal {
+                return priority_ord;
+            }
+            a.order_key.partial_cmp(&b.order_key).unwrap()
+        });
+    }
+
+    fn effective_level(&self, queued_track: &QueuedTrack, now: u64) -> u64 {
+        let level = match queued_track.priority {
+            Priority::High => 0,
+            Priority::Normal => 1,
+            Priority::Low => 2,
+        };
+        let aging_interval = self.aging_interval_days.max(1) * SECONDS_PER_DAY;
+        let promotions = now.saturating_sub(queued_track.added_at) / aging_interval;
+        level - promotions.min(level)
+    }
+
+    /// The source a track was queued from, falling back to its genre for old entries
+    fn source_key(
+        queued_track: &QueuedTrack,
+        tracks_info: &HashMap<String, SoundeoTrack>,
+    ) -> String {
+        if let Some(source) = &queued_track.source {
+            return source.clone();
+        }
+        tracks_info
+            .get(&queued_track.track_id)
+            .map(|track| format!("genre:{}", track.genre))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    /// Expects the tracks sorted strictly, so the relative order inside every source is kept
+    fn schedule_fair(
+        &self,
+        queued_tracks: Vec<QueuedTrack>,
+        tracks_info: &HashMap<String, SoundeoTrack>,
+        now: u64,
+    ) -> Vec<QueuedTrack> {
+        let mut levels: Vec<u64> = queued_tracks
+            .iter()
+            .map(|track| self.effective_level(track, now))
+            .collect();
+        levels.sort();
+        levels.dedup();
+
+        let mut scheduled = Vec::with_capacity(queued_tracks.len());
+        for level in levels {
+            let mut source_order: Vec<String> = vec![];
+            let mut by_source: HashMap<String, VecDeque<QueuedTrack>> = HashMap::new();
+            for queued_track in queued_tracks
+                .iter()
+                .filter(|track| self.effective_level(track, now) == level)
+            {
+                let source = Self::source_key(queued_track, tracks_info);
+                if !by_source.contains_key(&source) {
+                    source_order.push(source.clone());
+                }
+                by_source
+                    .entry(source)
+                    .or_default()
+                    .push_back(queued_track.clone());
+            }
+
+            let mut pending = true;
+            while pending {
+                pending = false;
+                for source in source_order.iter() {
+                    if let Some(track) = by_source.get_mut(source).and_then(|q| q.pop_front()) {
+                        scheduled.push(track);
+                        pending = true;
+                    }
+                }
+            }
+        }
+        scheduled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(track_id: &str, priority: Priority, order_key: f64, source: &str) -> QueuedTrack {
+        QueuedTrack {
+            track_id: track_id.to_string(),
+            priority,
+            order_key,
+            added_at: 10 * SECONDS_PER_DAY,
+            source: Some(source.to_string()),
+        }
+    }
+
+    fn ids(tracks: &[QueuedTrack]) -> Vec<&str> {
+        tracks.iter().map(|t| t.track_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_strict_sorts_by_priority_then_order_key() {
+        let scheduler = QueueScheduler::default();
+        let queue = vec![
+            queued("low", Priority::Low, 1.0, "genre"),
+            queued("normal_2", Priority::Normal, 2.0, "genre"),
+            queued("high", Priority::High, 5.0, "genre"),
+            queued("normal_1", Priority::Normal, 1.0, "genre"),
+        ];
+
+        let scheduled = scheduler.schedule_at(queue, &HashMap::new(), 10 * SECONDS_PER_DAY);
+
+        assert_eq!(ids(&scheduled), vec!["high", "normal_1", "normal_2", "low"]);
+    }
+
+    #[test]
+    fn test_fair_round_robins_sources_within_a_priority() {
+        let scheduler = QueueScheduler {
+            policy: SchedulingPolicy::Fair,
+            aging_interval_days: 7,
+        };
+        let queue = vec![
+            queued("genre_1", Priority::Normal, 1.0, "genre:Drum and Bass"),
+            queued("genre_2", Priority::Normal, 2.0, "genre:Drum and Bass"),
+            queued("genre_3", Priority::Normal, 3.0, "genre:Drum and Bass"),
+            queued("playlist_1", Priority::Normal, 4.0, "spotify"),
+            queued("playlist_2", Priority::Normal, 5.0, "spotify"),
+            queued("high", Priority::High, 1.0, "spotify"),
+        ];
+
+        let scheduled = scheduler.schedule_at(queue, &HashMap::new(), 10 * SECONDS_PER_DAY);
+
+        assert_eq!(
+            ids(&scheduled),
+            vec![
+                "high",
+                "genre_1",
+                "playlist_1",
+                "genre_2",
+                "playlist_2",
+                "genre_3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fair_ages_old_tracks_up_one_level_per_interval() {
+        let scheduler = QueueScheduler {
+            policy: SchedulingPolicy::Fair,
+            aging_interval_days: 7,
+        };
+        let mut old_low = queued("old_low", Priority::Low, 1.0, "genre");
+        old_low.added_at = 0;
+        let mut very_old_low = queued("very_old_low", Priority::Low, 2.0, "url");
+        very_old_low.added_at = 0;
+        let queue = vec![
+            queued("high", Priority::High, 1.0, "spotify"),
+            queued("normal", Priority::Normal, 1.0, "spotify"),
+            old_low,
+            very_old_low,
+        ];
+
+        // 8 days later the low tracks compete with the normal ones
+        let scheduled = scheduler.schedule_at(queue.clone(), &HashMap::new(), 8 * SECONDS_PER_DAY);
+        assert_eq!(
+            ids(&scheduled),
+            vec!["high", "normal", "old_low", "very_old_low"]
+        );
+
+        // 15 days later they reached the high level
+        let scheduled = scheduler.schedule_at(queue, &HashMap::new(), 15 * SECONDS_PER_DAY);
+        assert_eq!(
+            ids(&scheduled),
+            vec!["high", "old_low", "very_old_low", "normal"]
+        );
+    }
+
+    #[test]
+    fn test_fair_falls_back_to_the_cached_genre() {
+        let scheduler = QueueScheduler {
+            policy: SchedulingPolicy::Fair,
+            aging_interval_days: 7,
+        };
+        let mut tracks_info = HashMap::new();
+        let mut queue = vec![];
+        for (id, genre) in [
+            ("dnb_1", "Drum & Bass"),
+            ("dnb_2", "Drum & Bass"),
+            ("house_1", "House"),
+        ] {
+            let mut track = SoundeoTrack::new(id.to_string());
+            track.genre = genre.to_string();
+            tracks_info.insert(id.to_string(), track);
+            let mut queued_track = queued(id, Priority::Normal, queue.len() as f64, "");
+            queued_track.source = None;
+            queue.push(queued_track);
+        }
+
+        let scheduled = scheduler.schedule_at(queue, &tracks_info, 10 * SECONDS_PER_DAY);
+
+        assert_eq!(ids(&scheduled), vec!["dnb_1", "house_1", "dnb_2"]);
+    }
+}
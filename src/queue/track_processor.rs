@@ -5,7 +5,7 @@ use error_stack::ResultExt;
 
 use crate::log::{DjWizardLog, Priority};
 use crate::queue::{QueueError, QueueResult};
-use crate::soundeo::track::SoundeoTrack;
+use crate::soundeo::track::{DownloadTarget, SoundeoTrack};
 use crate::soundeo::SoundeoCRUD;
 use crate::user::SoundeoUser;
 
@@ -15,16 +15,18 @@ impl TrackProcessor {
     /// Processes a collection of track IDs and adds them to the queue
     /// Shows detailed progress for each track similar to the existing queue functionality
     pub async fn process_tracks_to_queue(
-        track_ids: &HashSet<String>,
+        track_ids: &[String],
         soundeo_user: &SoundeoUser,
         priority: Priority,
+        target: &DownloadTarget,
+        source: Option<String>,
         repeat_download: bool,
         context_description: &str, // e.g., "from Drum and Bass genre", "from playlist"
     ) -> QueueResult<(usize, usize)> {
         let available_tracks = DjWizardLog::get_available_tracks().change_context(QueueError)?;
         let queued_tracks = DjWizardLog::get_queued_tracks().change_context(QueueError)?;
         let queued_ids: HashSet<String> = queued_tracks.iter().map(|t| t.track_id.clone()).collect();
-        let _soundeo_info = DjWizardLog::get_soundeo().change_context(QueueError)?;
+        let soundeo_info = DjWizardLog::get_soundeo().change_context(QueueError)?;
 
         let total_tracks = track_ids.len();
         let mut total_added = 0;
@@ -75,6 +77,17 @@ impl TrackProcessor {
                 continue;
             }
 
+            // Skip if the file is already in the DJ software library, even when repeating downloads
+            if let Some(owned_path) = soundeo_info.owned_tracks.get(track_id) {
+                println!(
+                    "Track with id {} is already in your library, skipping: {}",
+                    track_id.clone().yellow(),
+                    owned_path.display().to_string().yellow()
+                );
+                total_skipped += 1;
+                continue;
+            }
+
             // Get detailed track info
             let mut track_info = SoundeoTrack::new(track_id.clone());
             track_info
@@ -131,10 +144,13 @@ impl TrackProcessor {
             }
 
             // Add to queue
-            let queue_result = DjWizardLog::add_queued_track(track_id.clone(), priority)
-                .change_context(QueueError)?;
+            let queue_result =
+                DjWizardLog::add_queued_track(track_id.clone(), priority, source.clone())
+                    .change_context(QueueError)?;
             
             if queue_result {
+                DjWizardLog::set_download_target(track_id.clone(), target.clone())
+                    .change_context(QueueError)?;
                 println!(
                     "Track {} successfully queued: {}",
                     track_info.title.green(),
@@ -18,7 +18,7 @@ pub type SoundeoAPIResult<T> = error_stack::Result<T, SoundeoAPIError>;
 
 pub enum SoundeoAPI {
     GetTrackInfo { track_id: String },
-    GetTrackDownloadUrl { track_id: String },
+    GetTrackDownloadUrl { track_id: String, format_id: u8 },
     GetSearchBarResult { term: String },
 }
 
@@ -30,8 +30,11 @@ impl SoundeoAPI {
                 let response = self.api_get(url, soundeo_user).await?;
                 Ok(response)
             }
-            SoundeoAPI::GetTrackDownloadUrl { track_id } => {
-                let url = format!("https://soundeo.com/download/{}/3", track_id);
+            SoundeoAPI::GetTrackDownloadUrl {
+                track_id,
+                format_id,
+            } => {
+                let url = format!("https://soundeo.com/download/{}/{}", track_id, format_id);
                 let response = self.api_get(url, soundeo_user).await?;
                 Ok(response)
             }
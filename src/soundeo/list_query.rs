@@ -0,0 +1,157 @@
+use url::Url;
+
+const TRACKS_LIST_URL: &str = "https://soundeo.com/list/tracks";
+
+/// Builds the url of a Soundeo track list with its filters
+#[derive(Debug, Clone, PartialEq)]
+pub struct SoundeoListQuery {
+    available_only: bool,
+    genre_id: Option<u32>,
+    date_range: Option<(String, String)>,
+    bpm_range: Option<(u32, u32)>,
+    key: Option<String>,
+    label_id: Option<u32>,
+    artist_id: Option<u32>,
+    page: Option<u32>,
+}
+
+impl Default for SoundeoListQuery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoundeoListQuery {
+    /// A query for the tracks available for download, without any other filter
+    pub fn new() -> Self {
+        Self {
+            available_only: true,
+            genre_id: None,
+            date_range: None,
+            bpm_range: None,
+            key: None,
+            label_id: None,
+            artist_id: None,
+            page: None,
+        }
+    }
+
+    pub fn available_only(mut self, available_only: bool) -> Self {
+        self.available_only = available_only;
+        self
+    }
+
+    pub fn genre(mut self, genre_id: u32) -> Self {
+        self.genre_id = Some(genre_id);
+        self
+    }
+
+    /// Release dates, both inclusive, formatted as %Y-%m-%d
+    pub fn date_range(mut self, start_date: &str, end_date: &str) -> Self {
+        self.date_range = Some((start_date.to_string(), end_date.to_string()));
+        self
+    }
+
+    pub fn bpm_range(mut self, min_bpm: u32, max_bpm: u32) -> Self {
+        self.bpm_range = Some((min_bpm.min(max_bpm), min_bpm.max(max_bpm)));
+        self
+    }
+
+    /// Key as Soundeo writes it, e.g. "E♭min"
+    pub fn key(mut self, key: &str) -> Self {
+        self.key = Some(key.to_string());
+        self
+    }
+
+    pub fn label(mut self, label_id: u32) -> Self {
+        self.label_id = Some(label_id);
+        self
+    }
+
+    pub fn artist(mut self, artist_id: u32) -> Self {
+        self.artist_id = Some(artist_id);
+        self
+    }
+
+    pub fn page(mut self, page: u32) -> Self {
+        self.page = Some(page);
+        self
+    }
+
+    pub fn build(&self) -> String {
+        let mut url = Url::parse(TRACKS_LIST_URL).unwrap();
+        {
+            let mut query = url.query_pairs_mut();
+            query.append_pair(
+                "availableFilter",
+                if self.available_only { "1" } else { "0" },
+            );
+            if let Some(artist_id) = self.artist_id {
+                query.append_pair("artistFilter", &artist_id.to_string());
+            }
+            if let Some(label_id) = self.label_id {
+                query.append_pair("labelFilter", &label_id.to_string());
+            }
+            if let Some(genre_id) = self.genre_id {
+                query.append_pair("genreFilter", &genre_id.to_string());
+            }
+            if let Some((start_date, end_date)) = &self.date_range {
+                query.append_pair("timeFilter", &format!("r_{}_{}", start_date, end_date));
+            }
+            if let Some((min_bpm, max_bpm)) = self.bpm_range {
+                query.append_pair("bpmFilter", &format!("{},{}", min_bpm, max_bpm));
+            }
+            if let Some(key) = &self.key {
+                query.append_pair("keyFilter", key);
+            }
+            if let Some(page) = self.page {
+                query.append_pair("page", &page.to_string());
+            }
+        }
+        url.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_genre_date_range_query() {
+        let url = SoundeoListQuery::new()
+            .genre(1)
+            .date_range("2024-01-01", "2024-02-01")
+            .page(3)
+            .build();
+
+        assert_eq!(
+            url,
+            "https://soundeo.com/list/tracks?availableFilter=1&genreFilter=1&timeFilter=r_2024-01-01_2024-02-01&page=3"
+        );
+    }
+
+    #[test]
+    fn test_build_query_with_every_filter() {
+        let url = SoundeoListQuery::new()
+            .available_only(false)
+            .genre(18)
+            .bpm_range(178, 170)
+            .key("E♭min")
+            .label(2398)
+            .artist(41)
+            .build();
+
+        assert_eq!(
+            url,
+            "https://soundeo.com/list/tracks?availableFilter=0&artistFilter=41&labelFilter=2398&genreFilter=18&bpmFilter=170%2C178&keyFilter=E%E2%99%ADmin"
+        );
+    }
+
+    #[test]
+    fn test_build_default_query() {
+        assert_eq!(
+            SoundeoListQuery::default().build(),
+            "https://soundeo.com/list/tracks?availableFilter=1"
+        );
+    }
+}
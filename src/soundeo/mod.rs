@@ -1,15 +1,17 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::path::PathBuf;
 
 use colorize::AnsiColor;
 use error_stack::ResultExt;
 use serde::{Deserialize, Serialize};
 
 use crate::log::{DjWizardLog, DjWizardLogResult};
-use crate::soundeo::track::SoundeoTrack;
+use crate::soundeo::track::{SoundeoTrack, TrackFormat};
 use crate::user::SoundeoUser;
 
 pub mod api;
+pub mod list_query;
 pub mod search_bar;
 pub mod track;
 pub mod track_list;
@@ -30,12 +32,35 @@ pub type SoundeoResult<T> = error_stack::Result<T, SoundeoError>;
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Soundeo {
     pub tracks_info: HashMap<String, SoundeoTrack>,
+    /// Tracks already in the DJ software library, with the path of their file
+    #[serde(default)]
+    pub owned_tracks: HashMap<String, PathBuf>,
 }
 
 impl Soundeo {
     pub fn new() -> Self {
         Self {
             tracks_info: HashMap::new(),
+            owned_tracks: HashMap::new(),
+        }
+    }
+
+    /// Adds the owned files to an index of `SoundeoTrack::index_local_files`,
+    /// the files found in the download folder take precedence.
+    /// An owned file is named after the track in its own format, and keeps its real name
+    /// when it is in a format Soundeo doesn't offer
+    pub fn add_owned_files(&self, local_files: &mut HashMap<String, PathBuf>) {
+        for (soundeo_track_id, path) in &self.owned_tracks {
+            if let Some(track) = self.tracks_info.get(soundeo_track_id) {
+                let file_name = match TrackFormat::from_path(path) {
+                    Some(format) => track.local_file_name(format),
+                    None => match path.file_name().and_then(|name| name.to_str()) {
+                        Some(file_name) => file_name.to_string(),
+                        None => continue,
+                    },
+                };
+                local_files.entry(file_name).or_insert_with(|| path.clone());
+            }
         }
     }
 }
@@ -46,4 +71,7 @@ pub trait SoundeoCRUD {
     fn mark_track_as_downloaded(soundeo_track_id: String) -> DjWizardLogResult<()>;
     fn reset_track_already_downloaded(soundeo_track_id: String) -> DjWizardLogResult<()>;
     fn mark_track_as_not_downloadable(soundeo_track_id: String) -> DjWizardLogResult<()>;
+    /// Stores the file of each track and marks it as downloaded, so it is never queued again.
+    /// The tracks already queued are removed from the queue, returns how many were removed
+    fn mark_tracks_as_owned(owned_tracks: HashMap<String, PathBuf>) -> DjWizardLogResult<usize>;
 }
@@ -1,15 +1,20 @@
 use colored::Colorize;
 use std::cmp::min;
-use std::fs::File;
+use std::collections::HashMap;
+use std::fs::{self, File};
 use std::io::Write;
+use std::path::{Path, PathBuf};
 
 use colorize::AnsiColor;
 use error_stack::{IntoReport, Report, ResultExt};
 use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
+use strum::IntoEnumIterator;
+use walkdir::WalkDir;
 
+use crate::history::RunHistory;
 use crate::log::DjWizardLog;
 use crate::soundeo::api::SoundeoAPI;
 use crate::soundeo::{SoundeoCRUD, SoundeoError, SoundeoResult};
@@ -44,6 +49,80 @@ pub struct SoundeoTrack {
     pub already_downloaded: bool,
 }
 
+/// Audio formats a track can be downloaded in
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+)]
+pub enum TrackFormat {
+    #[default]
+    Aiff,
+    Wav,
+    Mp3,
+}
+
+impl TrackFormat {
+    /// Id of the format in the Soundeo download url, as in the `data-track-format` of the
+    /// download links. The list pages saved in the fixtures only offer MP3 and AIFF, so the
+    /// WAV id is not checked by the tests
+    pub fn download_id(&self) -> u8 {
+        match self {
+            TrackFormat::Mp3 => 1,
+            TrackFormat::Wav => 2,
+            TrackFormat::Aiff => 3,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            TrackFormat::Aiff => "AIFF",
+            TrackFormat::Wav => "WAV",
+            TrackFormat::Mp3 => "MP3",
+        }
+    }
+
+    /// Format of an audio file from its extension, in any case
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        TrackFormat::iter().find(|format| format.extension().eq_ignore_ascii_case(extension))
+    }
+}
+
+/// Format and folder a track is downloaded to
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DownloadTarget {
+    #[serde(default)]
+    pub format: TrackFormat,
+    /// Folder relative to the download path, the download path itself when None
+    #[serde(default)]
+    pub folder: Option<String>,
+}
+
+impl DownloadTarget {
+    pub fn directory(&self, download_path: &str) -> PathBuf {
+        match &self.folder {
+            Some(folder) => Path::new(download_path).join(folder),
+            None => PathBuf::from(download_path),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match &self.folder {
+            Some(folder) => format!("{} in {}", self.format, folder),
+            None => self.format.to_string(),
+        }
+    }
+}
+
 /// Detects if a track is a STEM track based on the raw JSON API response
 /// A track is STEM if it has only 1 format and that format is STEM
 pub fn detect_stem(api_response: &str) -> SoundeoResult<bool> {
@@ -118,9 +197,14 @@ impl SoundeoTrack {
         };
     }
 
-    pub async fn get_download_url(&self, soundeo_user: &mut SoundeoUser) -> SoundeoResult<String> {
+    pub async fn get_download_url(
+        &self,
+        soundeo_user: &mut SoundeoUser,
+        format: TrackFormat,
+    ) -> SoundeoResult<String> {
         let response_text = SoundeoAPI::GetTrackDownloadUrl {
             track_id: self.id.clone(),
+            format_id: format.download_id(),
         }
         .get(soundeo_user)
         .await
@@ -192,26 +276,73 @@ impl SoundeoTrack {
         soundeo_user: &mut SoundeoUser,
         print_remaining_downloads: bool,
         force_redownload: bool,
+    ) -> SoundeoResult<()> {
+        self.download_track_with_progress(
+            soundeo_user,
+            print_remaining_downloads,
+            force_redownload,
+            None,
+            &DownloadTarget::default(),
+        )
+        .await
+    }
+
+    /// Downloads the track, adding the progress bar to `multi_progress` when it is provided
+    /// so it renders together with the other bars instead of printing over them
+    pub async fn download_track_with_progress(
+        &mut self,
+        soundeo_user: &mut SoundeoUser,
+        print_remaining_downloads: bool,
+        force_redownload: bool,
+        multi_progress: Option<&MultiProgress>,
+        target: &DownloadTarget,
+    ) -> SoundeoResult<()> {
+        let result = self
+            .download_with_progress(
+                soundeo_user,
+                print_remaining_downloads,
+                force_redownload,
+                multi_progress,
+                target,
+            )
+            .await;
+        if let Err(error) = &result {
+            RunHistory::track_failed();
+            log::error!("Failed to download track {}: {:?}", self.id, error);
+        }
+        result
+    }
+
+    async fn download_with_progress(
+        &mut self,
+        soundeo_user: &mut SoundeoUser,
+        print_remaining_downloads: bool,
+        force_redownload: bool,
+        multi_progress: Option<&MultiProgress>,
+        target: &DownloadTarget,
     ) -> SoundeoResult<()> {
         // Get info
-        self.get_info(&soundeo_user, true).await?;
+        self.get_info(&soundeo_user, multi_progress.is_none())
+            .await?;
         // Check if can be downloaded
         if self.already_downloaded {
             if force_redownload {
                 self.print_downloading_again();
             } else {
                 self.print_already_downloaded();
+                log::debug!("Skipped track {}, already downloaded", self.id);
                 return Ok(());
             }
         }
 
         if !self.downloadable {
             self.print_not_downloadable();
+            log::debug!("Skipped track {}, not downloadable", self.id);
             return Ok(());
         }
 
         // Download
-        let download_url = self.get_download_url(soundeo_user).await?;
+        let download_url = self.get_download_url(soundeo_user, target.format).await?;
         if print_remaining_downloads {
             let remaining_downloads = soundeo_user.get_remamining_downloads_string();
             println!("{}", remaining_downloads);
@@ -226,8 +357,11 @@ impl SoundeoTrack {
             .change_context(SoundeoError)?;
 
         let total_size = response.content_length().unwrap();
-        let file_name = self.get_file_name();
-        let pb = ProgressBar::new(total_size);
+        let file_name = self.get_file_name(target.format);
+        let pb = match multi_progress {
+            Some(multi_progress) => multi_progress.add(ProgressBar::new(total_size)),
+            None => ProgressBar::new(total_size),
+        };
         pb.set_style(ProgressStyle::default_bar()
             .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.white/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})").into_report().change_context(SoundeoError)?
             .progress_chars("█  "));
@@ -238,11 +372,11 @@ impl SoundeoTrack {
         ));
 
         let mut stream = response.bytes_stream();
-        let file_path = format!(
-            "{}/{}",
-            soundeo_user.download_path,
-            file_name.replace("/", ",")
-        );
+        let download_dir = target.directory(&soundeo_user.download_path);
+        fs::create_dir_all(&download_dir)
+            .into_report()
+            .change_context(SoundeoError)?;
+        let file_path = download_dir.join(self.local_file_name(target.format));
         let mut dest = File::create(file_path.clone())
             .into_report()
             .change_context(SoundeoError)?;
@@ -257,10 +391,18 @@ impl SoundeoTrack {
             pb.set_position(new);
         }
         let message = format!("{} successfully downloaded", file_name.clone().green());
-        pb.finish_with_message(message);
+        match multi_progress {
+            Some(multi_progress) => {
+                pb.finish_and_clear();
+                let _ = multi_progress.println(message);
+            }
+            None => pb.finish_with_message(message),
+        }
 
         // Mark as downloaded
         DjWizardLog::mark_track_as_downloaded(self.id.clone()).change_context(SoundeoError)?;
+        RunHistory::track_downloaded();
+        log::info!("Downloaded {} (track id {})", file_name, self.id);
         soundeo_user
             .login_and_update_user_info()
             .await
@@ -304,8 +446,48 @@ impl SoundeoTrack {
         );
     }
 
-    fn get_file_name(&self) -> String {
-        format!("{}.AIFF", self.title)
+    fn get_file_name(&self, format: TrackFormat) -> String {
+        format!("{}.{}", self.title, format.extension())
+    }
+
+    /// Name of the downloaded file in `format`, the title can't be used as is because of the slashes
+    pub fn local_file_name(&self, format: TrackFormat) -> String {
+        self.get_file_name(format).replace('/', ",")
+    }
+
+    /// Finds the file of the track in an index of `index_local_files`, in any format,
+    /// returning its name and path
+    pub fn find_local_file<'a>(
+        &self,
+        local_files: &'a HashMap<String, PathBuf>,
+    ) -> Option<(String, &'a PathBuf)> {
+        TrackFormat::iter().find_map(|format| {
+            let file_name = self.local_file_name(format);
+            local_files
+                .get(&file_name)
+                .map(|path| (file_name.clone(), path))
+        })
+    }
+
+    /// Maps the audio file names under the download folder to their path,
+    /// preferring the files at the top level over the copies inside playlist folders
+    pub fn index_local_files(download_dir: &Path) -> HashMap<String, PathBuf> {
+        let mut local_files: HashMap<String, PathBuf> = HashMap::new();
+        for entry in WalkDir::new(download_dir)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let path = entry.path();
+            if !path.is_file() || TrackFormat::from_path(path).is_none() {
+                continue;
+            }
+            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
+                if entry.depth() == 1 || !local_files.contains_key(file_name) {
+                    local_files.insert(file_name.to_string(), path.to_path_buf());
+                }
+            }
+        }
+        local_files
     }
 
     /// Check if this track is a STEM track by getting its info from the API
@@ -333,6 +515,7 @@ impl SoundeoTrack {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::soundeo::Soundeo;
 
     #[tokio::test]
     async fn test_get_info() {
@@ -499,4 +682,89 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_find_local_file_in_any_format() {
+        let mut soundeo_track = SoundeoTrack::new("1".to_string());
+        soundeo_track.title = "Artist - Title (AC/DC Remix)".to_string();
+        let local_files = HashMap::from([(
+            "Artist - Title (AC,DC Remix).MP3".to_string(),
+            PathBuf::from("/music/DnB/Artist - Title (AC,DC Remix).MP3"),
+        )]);
+
+        let (file_name, path) = soundeo_track.find_local_file(&local_files).unwrap();
+
+        assert_eq!(file_name, "Artist - Title (AC,DC Remix).MP3");
+        assert_eq!(
+            path,
+            &PathBuf::from("/music/DnB/Artist - Title (AC,DC Remix).MP3")
+        );
+    }
+
+    #[test]
+    fn test_owned_files_keep_their_format() {
+        let mut soundeo_track = SoundeoTrack::new("1".to_string());
+        soundeo_track.title = "Artist - Title".to_string();
+        let mut soundeo = Soundeo::new();
+        soundeo
+            .tracks_info
+            .insert("1".to_string(), soundeo_track.clone());
+        soundeo
+            .owned_tracks
+            .insert("1".to_string(), PathBuf::from("/library/01 Title.mp3"));
+        let mut local_files = HashMap::new();
+
+        soundeo.add_owned_files(&mut local_files);
+
+        let (file_name, path) = soundeo_track.find_local_file(&local_files).unwrap();
+        assert_eq!(file_name, "Artist - Title.MP3");
+        assert_eq!(path, &PathBuf::from("/library/01 Title.mp3"));
+    }
+
+    #[test]
+    fn test_download_ids_match_the_download_links() {
+        let page = scraper::Html::parse_document(include_str!("../../webpage_test.html"));
+        let link_selector = scraper::Selector::parse("a.track-download-lnk").unwrap();
+        let label_selector = scraper::Selector::parse("span").unwrap();
+        let mut checked_formats = HashMap::new();
+        for link in page.select(&link_selector) {
+            let format_id: u8 = link
+                .value()
+                .attr("data-track-format")
+                .unwrap()
+                .parse()
+                .unwrap();
+            let label = link
+                .select(&label_selector)
+                .next()
+                .unwrap()
+                .text()
+                .collect::<String>();
+            let format = TrackFormat::iter()
+                .find(|format| format.extension() == label.trim())
+                .unwrap();
+            assert_eq!(format.download_id(), format_id, "{} download id", format);
+            checked_formats.insert(format, format_id);
+        }
+
+        assert_eq!(
+            checked_formats,
+            HashMap::from([(TrackFormat::Mp3, 1), (TrackFormat::Aiff, 3)])
+        );
+    }
+
+    #[test]
+    fn test_download_target_directory() {
+        let target = DownloadTarget {
+            format: TrackFormat::Wav,
+            folder: Some("DnB".to_string()),
+        };
+
+        assert_eq!(target.directory("/music"), PathBuf::from("/music/DnB"));
+        assert_eq!(target.describe(), "Wav in DnB");
+        assert_eq!(
+            DownloadTarget::default().directory("/music"),
+            PathBuf::from("/music")
+        );
+    }
 }
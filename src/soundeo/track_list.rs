@@ -22,10 +22,26 @@ use crate::soundeo::{SoundeoError, SoundeoResult};
 use crate::user::SoundeoUser;
 use crate::Suggestion;
 
+/// A track as shown on a Soundeo list page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SoundeoListedTrack {
+    pub id: String,
+    /// "Artists - Title (Mix)", like the title of a `SoundeoTrack`
+    pub title: String,
+    pub artists: Vec<String>,
+    pub label: Option<String>,
+    /// Release date, formatted as %Y-%m-%d
+    pub date: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct SoundeoTracksList {
     pub url: String,
     pub track_ids: HashSet<String>,
+    /// Tracks of the walked pages, in page order
+    pub tracks: Vec<SoundeoListedTrack>,
+    /// Pages walked at most, all of them when None
+    pub max_pages: Option<u32>,
 }
 
 impl SoundeoTracksList {
@@ -33,23 +49,188 @@ impl SoundeoTracksList {
         Ok(Self {
             url,
             track_ids: HashSet::new(),
+            tracks: Vec::new(),
+            max_pages: None,
         })
     }
 
-    pub async fn get_tracks_id(&mut self, user: &SoundeoUser) -> SoundeoResult<()> {
-        let retrieved_page = self.retrieve_html(&user, self.url.clone()).await?;
-        let page_body = Html::parse_document(&retrieved_page);
-        let track_download_link_element = Selector::parse("a.track-download-lnk").unwrap();
-        for track_element in page_body.select(&track_download_link_element) {
-            let track_id = track_element
+    pub fn max_pages(mut self, max_pages: Option<u32>) -> Self {
+        self.max_pages = max_pages;
+        self
+    }
+
+    /// Walks every page of the list, up to `max_pages`
+    pub async fn get_tracks(&mut self, user: &SoundeoUser) -> SoundeoResult<()> {
+        self.get_tracks_while(user, |_| true).await
+    }
+
+    /// Walks the pages of the list in order, up to `max_pages`, while `keep_walking`
+    /// returns true for the tracks of the last page that were not on the previous ones
+    pub async fn get_tracks_while<F>(
+        &mut self,
+        user: &SoundeoUser,
+        mut keep_walking: F,
+    ) -> SoundeoResult<()>
+    where
+        F: FnMut(&[SoundeoListedTrack]) -> bool,
+    {
+        let max_pages = self.max_pages.unwrap_or(u32::MAX).max(1);
+        let mut last_page = 1;
+        let mut page = 1;
+        loop {
+            let page_url = Self::page_url(&self.url, page)?;
+            let retrieved_page = self.retrieve_html(user, page_url).await?;
+            // The paginator may only link the pages around the current one
+            if let Some(paginator_last_page) = Self::parse_last_page(&retrieved_page) {
+                last_page = last_page.max(paginator_last_page);
+            }
+            let page_tracks: Vec<SoundeoListedTrack> = Self::parse_tracks(&retrieved_page)
+                .into_iter()
+                .filter(|track| self.track_ids.insert(track.id.clone()))
+                .collect();
+            self.tracks.extend(page_tracks.iter().cloned());
+            if page >= last_page.min(max_pages) || !keep_walking(&page_tracks) {
+                return Ok(());
+            }
+            page += 1;
+        }
+    }
+
+    /// Url of a page of a list, replacing the page of the url if it has one
+    pub fn page_url(url: &str, page: u32) -> SoundeoResult<String> {
+        let mut page_url = Url::parse(url)
+            .into_report()
+            .change_context(SoundeoError)
+            .attach_printable_lazy(|| format!("Invalid url: {}", url))?;
+        let query_pairs: Vec<(String, String)> = page_url
+            .query_pairs()
+            .filter(|(key, _)| key != "page")
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        page_url
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(query_pairs)
+            .append_pair("page", &page.to_string());
+        Ok(page_url.to_string())
+    }
+
+    /// Tracks of a list page, in the order they are shown
+    pub fn parse_tracks(page: &str) -> Vec<SoundeoListedTrack> {
+        let page_body = Html::parse_document(page);
+        let track_item_selector = Selector::parse(".trackitem[data-track-id]").unwrap();
+        let title_selector = Selector::parse(".info strong a").unwrap();
+        let details_selector = Selector::parse(".info span").unwrap();
+        let link_selector = Selector::parse("a[href]").unwrap();
+        page_body
+            .select(&track_item_selector)
+            .filter_map(|track_element| {
+                let id = track_element.value().attr("data-track-id")?.to_string();
+                let title = track_element
+                    .select(&title_selector)
+                    .next()?
+                    .text()
+                    .collect::<String>()
+                    .trim()
+                    .to_string();
+                let artists = title
+                    .split_once(" - ")
+                    .map(|(artists, _)| {
+                        artists
+                            .split(", ")
+                            .map(|artist| artist.trim().to_string())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let details_element = track_element.select(&details_selector).next();
+                let label = details_element.and_then(|details_element| {
+                    details_element
+                        .select(&link_selector)
+                        .find(|link_element| Self::has_label_filter(link_element))
+                        .map(|link_element| {
+                            link_element.text().collect::<String>().trim().to_string()
+                        })
+                });
+                let date = details_element.and_then(|details_element| {
+                    regex!(r"\d{4}-\d{2}-\d{2}")
+                        .find(&details_element.text().collect::<String>())
+                        .map(|date| date.as_str().to_string())
+                });
+                Some(SoundeoListedTrack {
+                    id,
+                    title,
+                    artists,
+                    label,
+                    date,
+                })
+            })
+            .collect()
+    }
+
+    fn has_label_filter(link_element: &ElementRef) -> bool {
+        link_element
+            .value()
+            .attr("href")
+            .and_then(|href| Url::parse(href).ok())
+            .map(|url| {
+                url.query_pairs()
+                    .any(|(key, value)| key == "labelFilter" && !value.is_empty())
+            })
+            .unwrap_or(false)
+    }
+
+    /// Genres offered by the genre filter of the list page, as (id, name)
+    pub async fn get_genres(&self, user: &SoundeoUser) -> SoundeoResult<Vec<(u32, String)>> {
+        let retrieved_page = self.retrieve_html(user, self.url.clone()).await?;
+        Self::parse_genres(&retrieved_page)
+    }
+
+    pub fn parse_genres(page: &str) -> SoundeoResult<Vec<(u32, String)>> {
+        let page_body = Html::parse_document(page);
+        let genre_option_selector =
+            Selector::parse(r#"select[name="genreFilter"] option"#).unwrap();
+        let mut genres = Vec::new();
+        for option_element in page_body.select(&genre_option_selector) {
+            let Some(genre_id) = option_element
                 .value()
-                .attr("data-track-id")
-                .ok_or(SoundeoError)
-                .into_report()?
-                .to_string();
-            self.track_ids.insert(track_id);
+                .attr("value")
+                .and_then(|value| value.parse::<u32>().ok())
+            else {
+                continue;
+            };
+            let genre_name = option_element.text().collect::<String>().trim().to_string();
+            if !genre_name.is_empty() {
+                genres.push((genre_id, genre_name));
+            }
         }
-        Ok(())
+        if genres.is_empty() {
+            return Err(Report::new(SoundeoError)
+                .attach_printable("The genre filter was not found on the Soundeo page")
+                .attach(Suggestion(
+                    "Check that the Soundeo credentials are valid by running dj-wizard login"
+                        .to_string(),
+                )));
+        }
+        Ok(genres)
+    }
+
+    /// Last page linked by the paginator, None when the list has a single page
+    pub fn parse_last_page(page: &str) -> Option<u32> {
+        let page_body = Html::parse_document(page);
+        let paginator_link_selector = Selector::parse(".pagination a[href]").unwrap();
+        page_body
+            .select(&paginator_link_selector)
+            .filter_map(|link_element| {
+                let url = Url::parse(link_element.value().attr("href")?).ok()?;
+                let page_number = url
+                    .query_pairs()
+                    .find(|(key, _)| key == "page")?
+                    .1
+                    .parse::<u32>()
+                    .ok()?;
+                Some(page_number)
+            })
+            .max()
     }
 
     async fn retrieve_html(
@@ -75,3 +256,67 @@ impl SoundeoTracksList {
         Ok(response)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_genres_from_filter() {
+        let page = include_str!("../../webpage_test.html");
+        let genres = SoundeoTracksList::parse_genres(page).unwrap();
+
+        assert_eq!(genres.len(), 49);
+        assert!(genres.contains(&(1, "Drum & Bass".to_string())));
+        assert!(genres.contains(&(18, "Dubstep".to_string())));
+        assert!(genres.contains(&(95, "140 / Deep Dubstep / Grime".to_string())));
+        assert_eq!(genres[0].0, 95);
+    }
+
+    #[test]
+    fn test_parse_last_page_from_paginator() {
+        let page = include_str!("../../webpage_test.html");
+        assert_eq!(SoundeoTracksList::parse_last_page(page), Some(241));
+        assert_eq!(
+            SoundeoTracksList::parse_last_page("<html><body></body></html>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_tracks_in_page_order() {
+        let page = include_str!("../../webpage_test.html");
+        let tracks = SoundeoTracksList::parse_tracks(page);
+
+        assert_eq!(tracks.len(), 100);
+        assert_eq!(
+            tracks[0],
+            SoundeoListedTrack {
+                id: "20403100".to_string(),
+                title: "Urbandawn, Tyson Kelly - Come Together VIP (Original Mix)".to_string(),
+                artists: vec!["Urbandawn".to_string(), "Tyson Kelly".to_string()],
+                label: Some("Hospital Records".to_string()),
+                date: Some("2025-09-18".to_string()),
+            }
+        );
+        assert_eq!(tracks[1].id, "20397489");
+        assert_eq!(tracks[1].label, Some("Positiva".to_string()));
+    }
+
+    #[test]
+    fn test_page_url_replaces_page() {
+        assert_eq!(
+            SoundeoTracksList::page_url(
+                "https://soundeo.com/list/tracks?labelFilter=2398&page=3",
+                2
+            )
+            .unwrap(),
+            "https://soundeo.com/list/tracks?labelFilter=2398&page=2"
+        );
+    }
+
+    #[test]
+    fn test_parse_genres_without_filter() {
+        assert!(SoundeoTracksList::parse_genres("<html><body></body></html>").is_err());
+    }
+}
@@ -1,29 +1,32 @@
-use base64::Engine;
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use comfy_table::{Cell, Color, Table};
 use error_stack::{IntoReport, Report, ResultExt};
 use inflector::Inflector;
-use rand::RngCore;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
 use strum::IntoEnumIterator;
-use tiny_http::{Response, Server};
 use url::Url;
-use walkdir::WalkDir;
-use webbrowser;
 
-use crate::config::AppConfig;
+use crate::artist::ArtistCRUD;
 use crate::dialoguer::Dialoguer;
+use crate::export::m3u8::{self, M3u8PathMode};
+use crate::export::{ExportLibrary, PlaylistGroup};
 use crate::log::DjWizardLog;
 use crate::log::Priority;
+use crate::oauth::{OAuthProvider, TokenManager};
+use crate::output::OutputFormat;
 use crate::queue::commands::QueueCommands;
 use crate::soundeo::track::SoundeoTrack;
-use crate::spotify::playlist::SpotifyPlaylist;
+use crate::spotify::library::{self, LIKED_SONGS_PLAYLIST_ID};
+use crate::spotify::managed::{ManagedPlaylist, ManagedPlaylistsWriter, SPOTIFY_API_URL};
+use crate::spotify::organizer::{self, LinkMode};
+use crate::spotify::playlist::{PlaylistChangeKind, PlaylistSource, SpotifyPlaylist};
+use crate::spotify::review::{self, PairingReview};
 use crate::spotify::track::AutoPairResult;
 use crate::spotify::track::SpotifyTrack;
 use crate::spotify::SpotifyCRUD;
@@ -32,6 +35,7 @@ use crate::spotify::SpotifyResult;
 use crate::user::{SoundeoUser, User};
 use crate::Suggestion;
 
+#[derive(Debug, Serialize)]
 struct PlaylistStatusRow {
     name: String,
     total: usize,
@@ -85,18 +89,30 @@ pub enum SpotifyCommands {
     DownloadFromAllPlaylists,
     /// Manually review and pair unpaired tracks from a specific playlist.
     ManuallyPairSpotifyTracksWithSoundeoTracks,
+    /// Review the tracks of all playlists that could not be paired automatically.
+    ReviewPendingPairings,
     /// Organize downloaded tracks into folders, one for each playlist.
     OrganizeDownloadedTracksByPlaylistInFolders,
+    /// Choose whether playlist folders hold hard links, symbolic links or copies.
+    ConfigurePlaylistFolderLinks,
     /// Download tracks from one or more playlists by pairing them with Soundeo.
     DownloadFromMultiplePlaylists,
     /// Get a comprehensive status report for all playlists.
     GetPlaylistsStatus,
     /// Fetch your public Spotify playlists with the local log.
     FetchMyPublicPlaylists,
+    /// Sync your Spotify Liked Songs as a playlist.
+    SyncLikedSongs,
+    /// Sync the albums saved in your Spotify library as playlists.
+    SyncSavedAlbums,
+    /// Import the artists you follow on Spotify as favorite artists.
+    ImportFollowedArtists,
     /// Add a new Spotify playlist to track by providing its URL.
     AddNewPlaylistFromUrl,
     /// Remove one or more playlists from the local log.
     DeletePlaylists,
+    /// Export playlists as .m3u8 files pointing at the downloaded tracks.
+    ExportPlaylistsToM3u8,
     // /// Refresh the track list and metadata for an existing local playlist.
     // UpdatePlaylistData,
     // /// Add all paired tracks from a playlist to the download queue.
@@ -114,7 +130,10 @@ impl SpotifyCommands {
             .read_config_file()
             .change_context(SpotifyError)?;
 
-        if user_config.spotify_access_token.is_empty() {
+        let spotify_tokens = TokenManager::new(OAuthProvider::Spotify, &mut user_config);
+        let is_logged_in = spotify_tokens.token().is_some();
+        let has_required_scopes = spotify_tokens.has_required_scopes();
+        if !is_logged_in {
             let wants_to_login = Dialoguer::confirm(
                 "You are not logged into Spotify. Would you like to log in now?".to_string(),
                 Some(true),
@@ -130,10 +149,20 @@ impl SpotifyCommands {
                 );
                 return Ok(());
             }
+        } else if !has_required_scopes {
+            let wants_to_login = Dialoguer::confirm(
+                "Your Spotify login is missing permissions needed by some commands. Would you like to log in again now?".to_string(),
+                Some(true),
+            )
+            .change_context(SpotifyError)?;
+
+            if wants_to_login {
+                Self::perform_spotify_login(&mut user_config).await?;
+            }
         }
 
         // --- Auto-queue any paired tracks that are not yet in the queue ---
-        println!("\nChecking for paired tracks that need to be queued...");
+        OutputFormat::status("\nChecking for paired tracks that need to be queued...");
         let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
         let soundeo_log = DjWizardLog::get_soundeo().change_context(SpotifyError)?;
         let queued_tracks = DjWizardLog::get_queued_tracks().change_context(SpotifyError)?;
@@ -157,17 +186,21 @@ impl SpotifyCommands {
             .collect();
 
         if !tracks_to_enqueue.is_empty() {
-            println!(
+            OutputFormat::status(format!(
                 "Found {} paired tracks to add to the High priority queue.",
                 tracks_to_enqueue.len().to_string().green()
-            );
+            ));
             for soundeo_id in tracks_to_enqueue {
-                DjWizardLog::add_queued_track(soundeo_id, Priority::High)
-                    .change_context(SpotifyError)?;
+                DjWizardLog::add_queued_track(
+                    soundeo_id,
+                    Priority::High,
+                    Some("spotify".to_string()),
+                )
+                .change_context(SpotifyError)?;
             }
-            println!("{}", "Auto-queueing complete.".green());
+            OutputFormat::status("Auto-queueing complete.".green());
         } else {
-            println!("No new paired tracks to queue.");
+            OutputFormat::status("No new paired tracks to queue.");
         }
         // --- End of auto-queue logic ---
 
@@ -187,7 +220,9 @@ impl SpotifyCommands {
                 Self::sync_all_my_playlists(&mut user_config).await
             }
             SpotifyCommands::GetPlaylistsStatus => Self::get_playlists_status(),
-            SpotifyCommands::DownloadFromAllPlaylists => Self::download_from_all_playlists().await,
+            SpotifyCommands::DownloadFromAllPlaylists => {
+                Self::download_from_all_playlists(&mut user_config).await
+            }
             SpotifyCommands::AddNewPlaylistFromUrl => {
                 Self::add_new_playlist(&mut user_config).await
             }
@@ -195,9 +230,15 @@ impl SpotifyCommands {
             SpotifyCommands::FetchMyPublicPlaylists => {
                 Self::sync_public_playlists(&mut user_config).await
             }
+            SpotifyCommands::SyncLikedSongs => Self::sync_liked_songs(&mut user_config).await,
+            SpotifyCommands::SyncSavedAlbums => Self::sync_saved_albums(&mut user_config).await,
+            SpotifyCommands::ImportFollowedArtists => {
+                Self::import_followed_artists(&mut user_config).await
+            }
             SpotifyCommands::ManuallyPairSpotifyTracksWithSoundeoTracks => {
                 Self::pair_and_queue_unpaired_tracks().await
             }
+            SpotifyCommands::ReviewPendingPairings => Self::review_pending_pairings().await,
             // SpotifyCommands::QueueTracksFromPlaylist => Self::queue_tracks_from_playlist().await,
             // SpotifyCommands::PrintDownloadedTracksByPlaylist => {
             //     Self::print_downloaded_songs_by_playlist()
@@ -210,6 +251,8 @@ impl SpotifyCommands {
             SpotifyCommands::OrganizeDownloadedTracksByPlaylistInFolders => {
                 Self::organize_by_playlist().await
             }
+            SpotifyCommands::ConfigurePlaylistFolderLinks => Self::configure_link_mode(),
+            SpotifyCommands::ExportPlaylistsToM3u8 => Self::create_spotify_playlist_file(),
         };
     }
 
@@ -283,51 +326,12 @@ impl SpotifyCommands {
 
     async fn sync_public_playlists(user_config: &mut User) -> SpotifyResult<()> {
         println!("Fetching your public playlists from Spotify...");
-        let client = reqwest::Client::new();
         let mut all_playlists: Vec<ApiSimplePlaylist> = Vec::new();
         let mut next_url = Some("https://api.spotify.com/v1/me/playlists?limit=50".to_string());
 
         while let Some(url) = next_url {
-            let mut response = client
-                .get(&url)
-                .bearer_auth(&user_config.spotify_access_token)
-                .send()
-                .await
-                .into_report()
-                .change_context(SpotifyError)?;
-
-            if response.status() == reqwest::StatusCode::UNAUTHORIZED {
-                user_config
-                    .refresh_spotify_token()
-                    .await
-                    .change_context(SpotifyError)?;
-                response = client
-                    .get(&url)
-                    .bearer_auth(&user_config.spotify_access_token)
-                    .send()
-                    .await
-                    .into_report()
-                    .change_context(SpotifyError)?;
-            }
-
-            if !response.status().is_success() {
-                let error_body = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Could not read error body".to_string());
-                return Err(Report::new(SpotifyError)
-                    .attach_printable(format!("Spotify API returned an error: {}", error_body))
-                    .attach(Suggestion(
-                        "Your refresh token might be invalid. Please log in again.".to_string(),
-                    )));
-            }
-
-            let paginated_response: PaginatedPlaylistsResponse = response
-                .json()
-                .await
-                .into_report()
-                .change_context(SpotifyError)?;
-
+            let paginated_response: PaginatedPlaylistsResponse =
+                library::get_json(user_config, &url).await?;
             all_playlists.extend(paginated_response.items);
             next_url = paginated_response.next;
         }
@@ -429,7 +433,8 @@ impl SpotifyCommands {
             .playlists
             .values()
             .filter(|local_playlist| {
-                !remote_playlist_ids.contains(&local_playlist.spotify_playlist_id)
+                local_playlist.source == PlaylistSource::Playlist
+                    && !remote_playlist_ids.contains(&local_playlist.spotify_playlist_id)
             })
             .cloned()
             .collect();
@@ -461,6 +466,110 @@ impl SpotifyCommands {
         Ok(())
     }
 
+    async fn sync_liked_songs(user_config: &mut User) -> SpotifyResult<()> {
+        println!("Fetching your Liked Songs from Spotify...");
+        let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        let stored_playlist = spotify_log.playlists.get(LIKED_SONGS_PLAYLIST_ID).cloned();
+        let is_new = stored_playlist.is_none();
+        let mut playlist = stored_playlist.unwrap_or_else(SpotifyPlaylist::liked_songs);
+
+        let previous_tracks = playlist.tracks.clone();
+        playlist.get_playlist_info(user_config, false).await?;
+        if is_new {
+            println!(
+                "Stored {} with {} tracks.",
+                playlist.name.clone().green(),
+                playlist.tracks.len().to_string().cyan()
+            );
+        } else {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .into_report()
+                .change_context(SpotifyError)?
+                .as_secs();
+            let changes = playlist.record_changes(&previous_tracks, timestamp);
+            let added_count = changes
+                .iter()
+                .filter(|change| change.kind == PlaylistChangeKind::Added)
+                .count();
+            println!(
+                "{} added to {}, {} removed",
+                added_count.to_string().green(),
+                playlist.name.cyan(),
+                (changes.len() - added_count).to_string().yellow()
+            );
+        }
+        DjWizardLog::update_spotify_playlist(playlist).change_context(SpotifyError)?;
+        Ok(())
+    }
+
+    async fn sync_saved_albums(user_config: &mut User) -> SpotifyResult<()> {
+        println!("Fetching your saved albums from Spotify...");
+        let saved_albums = library::get_saved_albums(user_config, SPOTIFY_API_URL).await?;
+        let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        let new_albums: Vec<library::SavedAlbum> = saved_albums
+            .into_iter()
+            .filter(|album| !spotify_log.playlists.contains_key(&album.spotify_album_id))
+            .collect();
+
+        if new_albums.is_empty() {
+            println!("{}", "No new saved albums to sync.".yellow());
+            return Ok(());
+        }
+
+        let album_names: Vec<String> = new_albums
+            .iter()
+            .map(|album| format!("{} - {}", album.artists, album.name))
+            .collect();
+        let selections = Dialoguer::multiselect(
+            "Select albums to sync (space to select, enter to confirm)".to_string(),
+            album_names,
+            Some(&vec![true; new_albums.len()]),
+            false,
+        )
+        .change_context(SpotifyError)?;
+
+        if selections.is_empty() {
+            println!("No albums selected. Operation cancelled.");
+            return Ok(());
+        }
+
+        for album in selections.into_iter().map(|i| new_albums[i].clone()) {
+            println!("Syncing album: {}", album.name.clone().green());
+            let mut playlist = SpotifyPlaylist::saved_album(album.spotify_album_id, album.name);
+            playlist.get_playlist_info(user_config, false).await?;
+            DjWizardLog::update_spotify_playlist(playlist).change_context(SpotifyError)?;
+        }
+
+        println!("\n{}", "Sync complete.".green());
+        Ok(())
+    }
+
+    async fn import_followed_artists(user_config: &mut User) -> SpotifyResult<()> {
+        println!("Fetching the artists you follow on Spotify...");
+        let followed_artists = library::get_followed_artists(user_config, SPOTIFY_API_URL).await?;
+        let mut artist_manager = DjWizardLog::get_artist_manager().change_context(SpotifyError)?;
+
+        let mut added_count = 0;
+        for artist_name in &followed_artists {
+            if artist_manager.get_artist(artist_name).is_some() {
+                continue;
+            }
+            artist_manager
+                .add_artist(artist_name, None)
+                .change_context(SpotifyError)?;
+            added_count += 1;
+        }
+        DjWizardLog::save_artist_manager(artist_manager).change_context(SpotifyError)?;
+
+        println!(
+            "Imported {} new favorite artists out of {} followed on Spotify.",
+            added_count.to_string().green(),
+            followed_artists.len().to_string().cyan()
+        );
+        Ok(())
+    }
+
     async fn sync_all_my_playlists(user_config: &mut User) -> SpotifyResult<()> {
         println!("\nStarting to sync all locally stored playlists...");
 
@@ -476,6 +585,15 @@ impl SpotifyCommands {
 
         println!("Found {} playlists to sync.", total_playlists);
 
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .into_report()
+            .change_context(SpotifyError)?
+            .as_secs();
+        let mut unchanged_count = 0;
+        let mut changed_summaries: Vec<String> = Vec::new();
+        let mut added_tracks: Vec<SpotifyTrack> = Vec::new();
+
         for (i, mut playlist) in playlists_to_sync.into_iter().enumerate() {
             println!(
                 "\n({}/{}) Syncing playlist: {}",
@@ -484,17 +602,60 @@ impl SpotifyCommands {
                 playlist.name.clone().cyan()
             );
 
+            // Playlists with the same snapshot id did not change, their tracks are not fetched
+            if let Some(stored_snapshot_id) = playlist.snapshot_id.clone() {
+                match playlist.get_snapshot_id(user_config).await {
+                    Ok(snapshot_id) if snapshot_id == stored_snapshot_id => {
+                        println!("  └─ Unchanged since the last sync, skipped.");
+                        unchanged_count += 1;
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        println!(
+                            "  └─ {} Failed to sync '{}'. Error: {:?}",
+                            "✖".red(),
+                            playlist.name.red(),
+                            e
+                        );
+                        continue;
+                    }
+                }
+            }
+
+            let previous_tracks = playlist.tracks.clone();
             // The get_playlist_info function modifies the playlist in place
             match playlist.get_playlist_info(user_config, false).await {
                 Ok(_) => {
+                    let changes = playlist.record_changes(&previous_tracks, timestamp);
                     // Save the updated playlist back to the log
                     DjWizardLog::update_spotify_playlist(playlist.clone())
                         .change_context(SpotifyError)?;
+                    let added: Vec<SpotifyTrack> = changes
+                        .iter()
+                        .filter(|change| change.kind == PlaylistChangeKind::Added)
+                        .filter_map(|change| playlist.tracks.get(&change.spotify_track_id))
+                        .cloned()
+                        .collect();
+                    let removed_count = changes.len() - added.len();
                     println!(
-                        "  └─ {} Successfully synced '{}'.",
+                        "  └─ {} Successfully synced '{}': {} added, {} removed.",
                         "✔".green(),
-                        playlist.name.green()
+                        playlist.name.green(),
+                        added.len(),
+                        removed_count
                     );
+                    if changes.is_empty() {
+                        unchanged_count += 1;
+                    } else {
+                        changed_summaries.push(format!(
+                            "{} added to {}, {} removed",
+                            added.len().to_string().green(),
+                            playlist.name.cyan(),
+                            removed_count.to_string().yellow()
+                        ));
+                    }
+                    added_tracks.extend(added);
                 }
                 Err(e) => {
                     println!(
@@ -507,101 +668,122 @@ impl SpotifyCommands {
             }
         }
 
+        println!("\n--- Sync Summary ---");
+        for summary in &changed_summaries {
+            println!("- {}", summary);
+        }
+        println!(
+            "{} playlists unchanged.",
+            unchanged_count.to_string().cyan()
+        );
+
+        Self::pair_new_tracks(added_tracks).await?;
+
         println!("\n{}", "All playlists have been processed.".green());
         Ok(())
     }
 
-    async fn pair_and_queue_unpaired_tracks() -> SpotifyResult<()> {
-        let mut playlist = SpotifyPlaylist::prompt_select_playlist(
-            "Select a playlist to manually pair tracks from",
-        )?;
-        let mut soundeo_user = SoundeoUser::new().change_context(SpotifyError)?;
-        soundeo_user
-            .login_and_update_user_info()
-            .await
-            .change_context(SpotifyError)?;
-
+    /// Pairs the tracks just added to the playlists, queueing the paired ones with High priority
+    /// and sending the rest to the review queue
+    async fn pair_new_tracks(tracks: Vec<SpotifyTrack>) -> SpotifyResult<()> {
         let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
-
-        let unpaired_tracks: Vec<_> = playlist
-            .tracks
-            .iter()
-            .filter(|(id, _)| !spotify_log.soundeo_track_ids.contains_key(*id))
-            .map(|(id, track)| (id.clone(), track.clone()))
+        let mut seen = HashSet::new();
+        let unpaired_tracks: Vec<SpotifyTrack> = tracks
+            .into_iter()
+            .filter(|track| {
+                !spotify_log
+                    .soundeo_track_ids
+                    .contains_key(&track.spotify_track_id)
+                    && !spotify_log
+                        .pairing_reviews
+                        .contains_key(&track.spotify_track_id)
+                    && seen.insert(track.spotify_track_id.clone())
+            })
             .collect();
-
         if unpaired_tracks.is_empty() {
-            println!("\nNo unpaired tracks found in '{}'.", playlist.name.cyan());
             return Ok(());
         }
 
         println!(
-            "Found {} unpaired tracks in '{}'.",
-            unpaired_tracks.len().to_string().cyan(),
-            playlist.name.yellow()
+            "\nPairing {} new tracks...",
+            unpaired_tracks.len().to_string().cyan()
         );
+        let mut soundeo_user = SoundeoUser::new().change_context(SpotifyError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(SpotifyError)?;
 
-        // --- Phase 1: Automatic Pairing Pass ---
-        println!("\n--- Phase 1: Automatic Pairing Pass ---");
         let mut paired_count = 0;
-        let mut needs_manual_review: Vec<(String, SpotifyTrack)> = Vec::new();
-
-        let unpaired_len = unpaired_tracks.len();
-        for (i, (spotify_track_id, mut spotify_track)) in unpaired_tracks.into_iter().enumerate() {
-            println!(
-                "Processing track {}/{}: {} by {}",
-                format!("{}", i + 1).cyan(),
-                format!("{}", unpaired_len).cyan(),
-                spotify_track.title.yellow(),
-                spotify_track.artists.yellow()
-            );
-            let result = spotify_track.find_single_soundeo_match(&soundeo_user).await;
-
-            match result {
-                Ok(pair_result) => match pair_result {
-                    AutoPairResult::Paired(soundeo_id) => {
-                        paired_count += 1;
-                        println!("  └─ {} Paired automatically.", "✔".green());
-                        DjWizardLog::update_spotify_to_soundeo_track(
-                            spotify_track_id.clone(),
-                            Some(soundeo_id.clone()),
-                        )
+        let mut review_count = 0;
+        for mut spotify_track in unpaired_tracks {
+            match spotify_track.find_single_soundeo_match(&soundeo_user).await {
+                Ok(AutoPairResult::Paired(soundeo_id)) => {
+                    println!(
+                        "  └─ {} Paired: {} - {}",
+                        "✔".green(),
+                        spotify_track.artists.cyan(),
+                        spotify_track.title.cyan()
+                    );
+                    DjWizardLog::update_spotify_to_soundeo_track(
+                        spotify_track.spotify_track_id.clone(),
+                        Some(soundeo_id.clone()),
+                    )
+                    .change_context(SpotifyError)?;
+                    DjWizardLog::add_queued_track(
+                        soundeo_id,
+                        Priority::High,
+                        Some("spotify".to_string()),
+                    )
+                    .change_context(SpotifyError)?;
+                    paired_count += 1;
+                }
+                Ok(unpaired_result) => {
+                    println!(
+                        "  └─ {} Needs review: {} - {}",
+                        "…".yellow(),
+                        spotify_track.artists.cyan(),
+                        spotify_track.title.cyan()
+                    );
+                    let candidates = match unpaired_result {
+                        AutoPairResult::MultipleMatches(candidates) => candidates,
+                        _ => vec![],
+                    };
+                    DjWizardLog::update_spotify_to_soundeo_track(
+                        spotify_track.spotify_track_id.clone(),
+                        None,
+                    )
+                    .change_context(SpotifyError)?;
+                    DjWizardLog::add_pairing_review(PairingReview::new(&spotify_track, candidates))
                         .change_context(SpotifyError)?;
-
-                        DjWizardLog::add_queued_track(soundeo_id, Priority::High)
-                            .change_context(SpotifyError)?;
-                    }
-                    AutoPairResult::NoMatch | AutoPairResult::MultipleMatches(_) => {
-                        println!("  └─ {} Needs manual review.", "…".yellow());
-                        needs_manual_review.push((spotify_track_id, spotify_track));
-                    }
-                },
+                    review_count += 1;
+                }
                 Err(_) => {
                     println!(
-                        "  └─ {} Error during search. Needs manual review.",
-                        "✖".red()
+                        "  └─ {} Error pairing track: {}",
+                        "✖".red(),
+                        spotify_track.title.cyan()
                     );
-                    needs_manual_review.push((spotify_track_id, spotify_track));
                 }
             }
         }
-
         println!(
-            "\nAutomatic pass complete. Paired and queued {} tracks.",
-            paired_count.to_string().green()
+            "Paired and queued {} new tracks, {} sent to the review queue.",
+            paired_count.to_string().green(),
+            review_count.to_string().yellow()
         );
+        Ok(())
+    }
 
-        // --- Phase 2: Manual Pairing Pass ---
-        if needs_manual_review.is_empty() {
-            println!("{}", "No tracks require manual review.".green());
-            return Ok(());
-        }
-
-        println!("\n--- Phase 2: Manual Pairing Pass ---");
-        println!(
-            "There are {} tracks that need manual review. Starting interactive session...",
-            needs_manual_review.len().to_string().yellow()
-        );
+    async fn pair_and_queue_unpaired_tracks() -> SpotifyResult<()> {
+        let mut playlist = SpotifyPlaylist::prompt_select_playlist(
+            "Select a playlist to manually pair tracks from",
+        )?;
+        let mut soundeo_user = SoundeoUser::new().change_context(SpotifyError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(SpotifyError)?;
 
         playlist
             .pair_unpaired_tracks(&mut soundeo_user)
@@ -611,6 +793,15 @@ impl SpotifyCommands {
         Ok(())
     }
 
+    async fn review_pending_pairings() -> SpotifyResult<()> {
+        let mut soundeo_user = SoundeoUser::new().change_context(SpotifyError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(SpotifyError)?;
+        review::review_pairings(None, &soundeo_user).await
+    }
+
     async fn queue_tracks_from_playlist() -> SpotifyResult<()> {
         let playlist =
             SpotifyPlaylist::prompt_select_playlist("Select the playlist to queue tracks from")?;
@@ -676,8 +867,12 @@ impl SpotifyCommands {
         let mut queued_count = 0;
         let mut skipped_count = 0;
         for soundeo_id in soundeo_ids_to_queue {
-            if DjWizardLog::add_queued_track(soundeo_id, Priority::High)
-                .change_context(SpotifyError)?
+            if DjWizardLog::add_queued_track(
+                soundeo_id,
+                Priority::High,
+                Some("spotify".to_string()),
+            )
+            .change_context(SpotifyError)?
             {
                 queued_count += 1;
             } else {
@@ -895,13 +1090,29 @@ impl SpotifyCommands {
         println!("{table}");
     }
 
+    fn print_status_json(rows: Vec<PlaylistStatusRow>) -> SpotifyResult<()> {
+        let total = PlaylistStatusRow {
+            name: "GRAND TOTAL".to_string(),
+            total: rows.iter().map(|row| row.total).sum(),
+            downloaded: rows.iter().map(|row| row.downloaded).sum(),
+            queued: rows.iter().map(|row| row.queued).sum(),
+            pending_pairing: rows.iter().map(|row| row.pending_pairing).sum(),
+            no_match: rows.iter().map(|row| row.no_match).sum(),
+        };
+        OutputFormat::print_json(&serde_json::json!({
+            "playlists": rows,
+            "total": total,
+        }))
+        .change_context(SpotifyError)
+    }
+
     fn get_playlists_status() -> SpotifyResult<()> {
         // 1. Load all necessary data from the logs
         let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
         let soundeo_log = DjWizardLog::get_soundeo().change_context(SpotifyError)?;
         let queued_tracks = DjWizardLog::get_queued_tracks().change_context(SpotifyError)?;
 
-        if spotify_log.playlists.is_empty() {
+        if spotify_log.playlists.is_empty() && !OutputFormat::is_json() {
             println!("{}", "No playlists found in the log.".yellow());
             return Ok(());
         }
@@ -961,12 +1172,57 @@ impl SpotifyCommands {
             });
         }
 
+        if OutputFormat::is_json() {
+            return Self::print_status_json(status_rows);
+        }
+
         Self::print_status_table(status_rows);
 
         Ok(())
     }
 
-    async fn download_from_all_playlists() -> SpotifyResult<()> {
+    async fn download_from_all_playlists(user_config: &mut User) -> SpotifyResult<()> {
+        Self::pair_and_download_all_playlists().await?;
+        if let Err(e) = Self::update_managed_playlists(user_config).await {
+            println!(
+                "{} Failed to update the managed Spotify playlists. Error: {:?}",
+                "✖".red(),
+                e
+            );
+        }
+        Ok(())
+    }
+
+    /// Writes the "not on Soundeo" and "ready to play" playlists to the Spotify account
+    async fn update_managed_playlists(user_config: &mut User) -> SpotifyResult<()> {
+        println!("\nUpdating the playlists managed by dj-wizard on Spotify...");
+        let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        let soundeo_log = DjWizardLog::get_soundeo().change_context(SpotifyError)?;
+        let mut writer = ManagedPlaylistsWriter::new(user_config);
+        for managed_playlist in ManagedPlaylist::iter() {
+            let track_ids = managed_playlist.track_ids(&spotify_log, &soundeo_log);
+            let stored_id = spotify_log
+                .managed_playlists
+                .get(&managed_playlist)
+                .cloned();
+            let playlist_id = writer
+                .update(managed_playlist, stored_id.clone(), &track_ids)
+                .await?;
+            if stored_id.as_ref() != Some(&playlist_id) {
+                DjWizardLog::set_managed_playlist(managed_playlist, playlist_id)
+                    .change_context(SpotifyError)?;
+            }
+            println!(
+                "  └─ {} {} now has {} tracks.",
+                "✔".green(),
+                managed_playlist.name().cyan(),
+                track_ids.len().to_string().green()
+            );
+        }
+        Ok(())
+    }
+
+    async fn pair_and_download_all_playlists() -> SpotifyResult<()> {
         // 1. Login to Soundeo
         println!("\nLogging into Soundeo to pair and queue tracks...");
         let mut soundeo_user = SoundeoUser::new().change_context(SpotifyError)?;
@@ -1061,10 +1317,15 @@ impl SpotifyCommands {
                                     None,
                                 )
                                 .change_context(SpotifyError)?;
+                                DjWizardLog::add_pairing_review(PairingReview::new(
+                                    &spotify_track,
+                                    vec![],
+                                ))
+                                .change_context(SpotifyError)?;
                                 skipped_in_playlist += 1;
                                 any_tracks_failed_pairing = true;
                             }
-                            crate::spotify::track::AutoPairResult::MultipleMatches(results) => {
+                            crate::spotify::track::AutoPairResult::MultipleMatches(candidates) => {
                                 println!(
                                     "    └─ ({}/{}) {} Multiple matches for: {} - {}",
                                     format!("{}", i + 1).cyan(),
@@ -1078,10 +1339,10 @@ impl SpotifyCommands {
                                     None,
                                 )
                                 .change_context(SpotifyError)?;
-                                DjWizardLog::add_to_multiple_matches_cache(
-                                    spotify_track.spotify_track_id.clone(),
-                                    results,
-                                )
+                                DjWizardLog::add_pairing_review(PairingReview::new(
+                                    &spotify_track,
+                                    candidates,
+                                ))
                                 .change_context(SpotifyError)?;
                                 skipped_in_playlist += 1;
                                 any_tracks_failed_pairing = true;
@@ -1116,7 +1377,7 @@ impl SpotifyCommands {
             if any_tracks_failed_pairing {
                 println!(
                     "{}",
-                    "Some tracks could not be paired automatically. You can try pairing them using the 'Manually pair tracks' command.".yellow()
+                    "Some tracks could not be paired automatically. You can review them using the 'Review pending pairings' command.".yellow()
                 );
             }
             return Ok(());
@@ -1130,15 +1391,19 @@ impl SpotifyCommands {
         if any_tracks_failed_pairing {
             println!(
                 "{}",
-                "Some tracks could not be paired automatically. You can try pairing them using the 'Manually pair tracks' command.".yellow()
+                "Some tracks could not be paired automatically. You can review them using the 'Review pending pairings' command.".yellow()
             );
         }
 
         println!("Adding newly paired tracks to the queue with High priority...");
         let mut queued_count = 0;
         for soundeo_id in newly_paired_soundeo_ids {
-            if DjWizardLog::add_queued_track(soundeo_id, Priority::High)
-                .change_context(SpotifyError)?
+            if DjWizardLog::add_queued_track(
+                soundeo_id,
+                Priority::High,
+                Some("spotify".to_string()),
+            )
+            .change_context(SpotifyError)?
             {
                 queued_count += 1;
             }
@@ -1262,8 +1527,12 @@ impl SpotifyCommands {
                                 )
                                 .change_context(SpotifyError)?;
 
-                                if DjWizardLog::add_queued_track(soundeo_id, selected_priority)
-                                    .change_context(SpotifyError)?
+                                if DjWizardLog::add_queued_track(
+                                    soundeo_id,
+                                    selected_priority,
+                                    Some("spotify".to_string()),
+                                )
+                                .change_context(SpotifyError)?
                                 {
                                     println!(
                                         "        └─ Added to download queue with {} priority.",
@@ -1274,7 +1543,7 @@ impl SpotifyCommands {
                                     println!("        └─ Already in download queue.");
                                 }
                             }
-                            _ => {
+                            unpaired_result => {
                                 println!(
                                     "      └─ {} Not auto-paired (multiple matches or no match found).",
                                     "✖".red()
@@ -1285,6 +1554,15 @@ impl SpotifyCommands {
                                     None,
                                 )
                                 .change_context(SpotifyError)?;
+                                let candidates = match unpaired_result {
+                                    AutoPairResult::MultipleMatches(candidates) => candidates,
+                                    _ => vec![],
+                                };
+                                DjWizardLog::add_pairing_review(PairingReview::new(
+                                    &spotify_track,
+                                    candidates,
+                                ))
+                                .change_context(SpotifyError)?;
                                 total_failed += 1;
                             }
                         }
@@ -1312,7 +1590,7 @@ impl SpotifyCommands {
                 "Failed to automatically pair {} tracks.",
                 total_failed.to_string().yellow()
             );
-            println!("You can try pairing them manually later.");
+            println!("You can review them later with the 'Review pending pairings' command.");
         }
 
         if total_queued > 0 {
@@ -1327,131 +1605,111 @@ impl SpotifyCommands {
     }
 
     fn create_spotify_playlist_file() -> SpotifyResult<()> {
-        let prompt_text = "Select the playlist to create the m3u8 file";
-        let _playlist = SpotifyPlaylist::prompt_select_playlist(prompt_text)?;
-        let _file_content = "#EXTM3U";
-
-        Ok(())
-    }
-
-    async fn perform_spotify_login(user: &mut User) -> SpotifyResult<()> {
-        // --- PKCE Step 1: Create a Code Verifier and Code Challenge ---
-        let mut verifier_bytes = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut verifier_bytes);
-        let code_verifier =
-            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(&verifier_bytes);
-
-        let mut hasher = Sha256::new();
-        hasher.update(code_verifier.as_bytes());
-        let challenge_bytes = hasher.finalize();
-        let code_challenge =
-            base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(challenge_bytes);
-
-        // --- Standard Auth Flow Steps ---
-        let client_id = AppConfig::SPOTIFY_CLIENT_ID.to_string();
-        let redirect_uri = "http://localhost:8888/callback";
-        let scopes = "playlist-read-private playlist-read-collaborative";
-
-        // 2. Start a temporary local server to catch the redirect
-        let server = Server::http("127.0.0.1:8888").unwrap();
-
-        // 3. Construct the authorization URL and open it in the browser
-        let auth_url = format!(
-            "https://accounts.spotify.com/authorize?response_type=code&client_id={}&scope={}&redirect_uri={}&code_challenge_method=S256&code_challenge={}",
-            client_id,
-            scopes.replace(' ', "%20"),
-            redirect_uri,
-            code_challenge
-        );
+        let mut user_config = User::new();
+        user_config
+            .read_config_file()
+            .change_context(SpotifyError)?;
+        let download_dir = PathBuf::from(&user_config.download_path);
 
-        println!(
-            "\n{}\n",
-            "Please log in to Spotify in the browser window that just opened.".yellow()
-        );
-        if webbrowser::open(&auth_url).is_err() {
+        let export_options = vec!["Select a playlist", "Export all playlists"];
+        let export_all = Dialoguer::select(
+            "Which playlists do you want to export?".to_string(),
+            export_options,
+            None,
+        )
+        .change_context(SpotifyError)?
+            == 1;
+        let playlists = if export_all {
+            let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+            let mut playlists: Vec<SpotifyPlaylist> =
+                spotify_log.playlists.values().cloned().collect();
+            playlists.sort_by_key(|playlist| playlist.name.to_lowercase());
+            playlists
+        } else {
+            let prompt_text = "Select the playlist to create the m3u8 file";
+            vec![SpotifyPlaylist::prompt_select_playlist(prompt_text)?]
+        };
+        if playlists.is_empty() {
             println!(
-                "Could not automatically open browser. Please copy/paste this URL:\n{}",
-                auth_url.cyan()
+                "{}",
+                "No Spotify playlists found. Please sync or add a playlist first.".yellow()
             );
+            return Ok(());
         }
 
-        // 4. Wait for the user to log in and for Spotify to redirect back to our server
-        let request = server.recv().into_report().change_context(SpotifyError)?;
-        let full_url = format!("http://localhost:8888{}", request.url());
-        let parsed_url = Url::parse(&full_url)
-            .into_report()
-            .change_context(SpotifyError)?;
-        let auth_code = parsed_url
-            .query_pairs()
-            .find_map(|(key, value)| {
-                if key == "code" {
-                    Some(value.into_owned())
-                } else {
-                    None
-                }
-            })
-            .ok_or(
-                Report::new(SpotifyError).attach_printable("Could not find 'code' in callback URL"),
-            )?;
-
-        let response = Response::from_string(
-            "<h1>Authentication successful!</h1><p>You can close this browser tab now.</p>",
-        );
-        request
-            .respond(response)
-            .into_report()
-            .change_context(SpotifyError)?;
-        println!("\nAuthorization code received successfully!");
-
-        // 5. Exchange the code for a token
-        let client = reqwest::Client::new();
-        let params = [
-            ("grant_type", "authorization_code".to_string()),
-            ("code", auth_code),
-            ("redirect_uri", redirect_uri.to_string()),
-            ("client_id", client_id),
-            ("code_verifier", code_verifier),
-        ];
+        let path_modes: Vec<M3u8PathMode> = M3u8PathMode::iter().collect();
+        let path_mode_names: Vec<String> = path_modes
+            .iter()
+            .map(|mode| format!("{} paths", mode).to_sentence_case())
+            .collect();
+        let path_mode_selection = Dialoguer::select(
+            "How should the tracks be referenced?".to_string(),
+            path_mode_names,
+            None,
+        )
+        .change_context(SpotifyError)?;
+        let path_mode = path_modes[path_mode_selection];
 
-        let token_response: serde_json::Value = client
-            .post("https://accounts.spotify.com/api/token")
-            .form(&params)
-            .send()
-            .await
-            .into_report()
-            .change_context(SpotifyError)?
-            .json()
-            .await
+        let mut local_files = SoundeoTrack::index_local_files(&download_dir);
+        let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        let soundeo_log = DjWizardLog::get_soundeo().change_context(SpotifyError)?;
+        soundeo_log.add_owned_files(&mut local_files);
+        let library = ExportLibrary::build(&soundeo_log.tracks_info, &spotify_log, &local_files);
+        let playlists_folder = download_dir.join("m3u8");
+        fs::create_dir_all(&playlists_folder)
             .into_report()
             .change_context(SpotifyError)?;
 
-        // 6. Store the credentials in the user config
-        user.spotify_access_token = token_response["access_token"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
-        user.spotify_refresh_token = token_response["refresh_token"]
-            .as_str()
-            .unwrap_or("")
-            .to_string();
-
-        if user.spotify_access_token.is_empty() {
-            return Err(Report::new(SpotifyError).attach_printable(format!(
-                "Failed to get access token. Response: {:?}",
-                token_response
-            )));
+        // Names are computed over every playlist, so a file keeps its name whatever the selection
+        let file_names = m3u8::file_names(spotify_log.playlists.values().map(|playlist| {
+            (
+                playlist.spotify_playlist_id.as_str(),
+                playlist.name.as_str(),
+            )
+        }));
+        for playlist in playlists {
+            let Some(logical_playlist) = library.playlists.iter().find(|logical_playlist| {
+                logical_playlist.group == PlaylistGroup::Spotify
+                    && logical_playlist.spotify_playlist_id.as_ref()
+                        == Some(&playlist.spotify_playlist_id)
+            }) else {
+                continue;
+            };
+            let file_path = playlists_folder.join(&file_names[&playlist.spotify_playlist_id]);
+            let content = m3u8::render(&library, logical_playlist, &playlists_folder, path_mode);
+            fs::write(&file_path, content)
+                .into_report()
+                .attach_printable(format!(
+                    "Failed to write the playlist file at {}",
+                    file_path.display()
+                ))
+                .change_context(SpotifyError)?;
+            println!(
+                "{} of {} tracks from '{}' exported to {}",
+                logical_playlist.track_positions().len().to_string().green(),
+                logical_playlist.entries.len(),
+                playlist.name.cyan(),
+                file_path.display()
+            );
+            if playlist.track_order.is_empty() && !playlist.tracks.is_empty() {
+                println!(
+                    "{}",
+                    "The playlist order is unknown, sync the playlist to export it in Spotify order"
+                        .yellow()
+                );
+            }
         }
 
-        user.save_config_file().change_context(SpotifyError)?;
-
-        println!(
-            "{}",
-            "Spotify login successful! Your credentials have been saved.".green()
-        );
-
         Ok(())
     }
 
+    async fn perform_spotify_login(user: &mut User) -> SpotifyResult<()> {
+        TokenManager::new(OAuthProvider::Spotify, user)
+            .login()
+            .await
+            .change_context(SpotifyError)
+    }
+
     async fn organize_by_playlist() -> SpotifyResult<()> {
         // Phase 1: Preparation and Selection
         let mut user_config = User::new();
@@ -1503,56 +1761,55 @@ impl SpotifyCommands {
 
         // Scan local files recursively to build a master index
         println!("\nScanning local download directory (recursively)...");
-        let mut local_files: HashMap<String, PathBuf> = HashMap::new();
-        for entry in WalkDir::new(&download_dir)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let path = entry.path();
-            if path.is_file() {
-                if let Some(extension) = path.extension() {
-                    if extension == "AIFF" || extension == "aiff" {
-                        if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                            local_files.insert(file_name.to_string(), path.to_path_buf());
-                        }
-                    }
-                }
-            }
-        }
-        println!("Found {} local .AIFF files in total.", local_files.len());
+        let soundeo_log = DjWizardLog::get_soundeo().change_context(SpotifyError)?;
+        let mut local_files = SoundeoTrack::index_local_files(&download_dir);
+        soundeo_log.add_owned_files(&mut local_files);
+        println!("Found {} local audio files in total.", local_files.len());
 
         // Phase 2: Processing and Classification
-        let soundeo_log = DjWizardLog::get_soundeo().change_context(SpotifyError)?;
+        let link_mode = user_config.playlist_link_mode;
         let mut tracks_to_force_redownload: Vec<SoundeoTrack> = Vec::new();
         let mut tracks_to_report_missing: HashMap<String, Vec<String>> = HashMap::new();
 
-        println!("\nAnalyzing playlists and organizing files...");
+        println!(
+            "\nAnalyzing playlists and syncing folders ({} mode)...",
+            link_mode.to_string().to_lowercase().cyan()
+        );
 
         for playlist in selected_playlists {
-            let sanitized_playlist_name = Self::sanitize_filename(&playlist.name);
+            let sanitized_playlist_name =
+                organizer::sanitize_name(&playlist.name, &playlist.spotify_playlist_id);
             let playlist_folder_path = download_dir.join(&sanitized_playlist_name);
-            fs::create_dir_all(&playlist_folder_path)
+            if let Some(previous_folder_name) = spotify_log
+                .playlist_folders
+                .get(&playlist.spotify_playlist_id)
+            {
+                let renamed = organizer::rename_folder(
+                    &download_dir.join(previous_folder_name),
+                    &playlist_folder_path,
+                )
                 .into_report()
                 .change_context(SpotifyError)?;
+                if renamed {
+                    println!(
+                        "Renamed folder '{}' to '{}'",
+                        previous_folder_name.yellow(),
+                        sanitized_playlist_name.cyan()
+                    );
+                }
+            }
 
-            let mut present_songs_count = 0;
-
+            let mut expected_files: HashMap<String, PathBuf> = HashMap::new();
             for spotify_track in playlist.tracks.values() {
                 if let Some(Some(soundeo_id)) = spotify_log
                     .soundeo_track_ids
                     .get(&spotify_track.spotify_track_id)
                 {
                     if let Some(soundeo_track) = soundeo_log.tracks_info.get(soundeo_id) {
-                        let expected_filename = format!("{}.AIFF", soundeo_track.title);
-
-                        if let Some(source_path) = local_files.get(&expected_filename) {
-                            present_songs_count += 1;
-                            let dest_path = playlist_folder_path.join(&expected_filename);
-                            if !dest_path.exists() {
-                                fs::copy(source_path, &dest_path)
-                                    .into_report()
-                                    .change_context(SpotifyError)?;
-                            }
+                        if let Some((expected_filename, source_path)) =
+                            soundeo_track.find_local_file(&local_files)
+                        {
+                            expected_files.insert(expected_filename, source_path.clone());
                         } else {
                             // The file is genuinely missing from the disk.
                             if soundeo_track.already_downloaded {
@@ -1587,12 +1844,42 @@ impl SpotifyCommands {
                 }
             }
 
+            let folder_sync = organizer::sync_folder(
+                &download_dir,
+                &playlist_folder_path,
+                &expected_files,
+                link_mode,
+            )
+            .into_report()
+            .attach_printable(format!(
+                "Failed to sync the playlist folder at {}",
+                playlist_folder_path.display()
+            ))
+            .change_context(SpotifyError)?;
+            DjWizardLog::set_playlist_folder(
+                playlist.spotify_playlist_id.clone(),
+                sanitized_playlist_name,
+            )
+            .change_context(SpotifyError)?;
+
             println!(
                 "{} of {} songs from playlist '{}' are available in the local playlist folder.",
-                present_songs_count.to_string().green(),
+                expected_files.len().to_string().green(),
                 playlist.tracks.len(),
                 playlist.name.cyan()
             );
+            for file_name in &folder_sync.removed {
+                println!(
+                    "  Removed {}, no longer in the playlist",
+                    file_name.yellow()
+                );
+            }
+            for file_name in &folder_sync.kept {
+                println!(
+                    "  Kept {}, no longer in the playlist but it is the only copy",
+                    file_name.yellow()
+                );
+            }
         }
 
         // Phase 3: Actions and Reporting
@@ -1645,9 +1932,34 @@ impl SpotifyCommands {
         Ok(())
     }
 
-    fn sanitize_filename(name: &str) -> String {
-        name.chars()
-            .filter(|c| !matches!(*c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
-            .collect()
+    fn configure_link_mode() -> SpotifyResult<()> {
+        let mut user_config = User::new();
+        user_config
+            .read_config_file()
+            .change_context(SpotifyError)?;
+        let link_modes = LinkMode::iter().collect::<Vec<_>>();
+        let options = vec![
+            "Hardlink: no extra disk space, tag edits are shared (copies across drives)",
+            "Symlink: links to the downloaded file, break if the download folder moves",
+            "Copy: independent files, one per playlist",
+        ];
+        let current = link_modes
+            .iter()
+            .position(|link_mode| *link_mode == user_config.playlist_link_mode);
+        let selection = Dialoguer::select(
+            "How should the playlist folders reference the downloaded files?".to_string(),
+            options,
+            current,
+        )
+        .change_context(SpotifyError)?;
+        user_config.playlist_link_mode = link_modes[selection];
+        user_config
+            .save_config_file()
+            .change_context(SpotifyError)?;
+        println!(
+            "Playlist folders will use {} from the next organize",
+            user_config.playlist_link_mode.to_string().green()
+        );
+        Ok(())
     }
 }
@@ -0,0 +1,276 @@
+use error_stack::{IntoReport, Report, ResultExt};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::oauth::{OAuthProvider, TokenManager};
+use crate::spotify::{SpotifyError, SpotifyResult};
+use crate::user::User;
+use crate::Suggestion;
+
+/// Id under which the Liked Songs of the user are stored as a playlist
+pub const LIKED_SONGS_PLAYLIST_ID: &str = "liked-songs";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiArtist {
+    name: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiSavedAlbumItem {
+    album: ApiSimpleAlbum,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiSimpleAlbum {
+    id: String,
+    name: String,
+    artists: Vec<ApiArtist>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PaginatedSavedAlbums {
+    items: Vec<ApiSavedAlbumItem>,
+    next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct FollowedArtistsResponse {
+    artists: PaginatedArtists,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct PaginatedArtists {
+    items: Vec<ApiArtist>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedAlbum {
+    pub spotify_album_id: String,
+    pub name: String,
+    pub artists: String,
+}
+
+/// Sends an authenticated GET to the Spotify Web API
+pub async fn get_json<T: DeserializeOwned>(user_config: &mut User, url: &str) -> SpotifyResult<T> {
+    let response = TokenManager::new(OAuthProvider::Spotify, user_config)
+        .get(url)
+        .await
+        .change_context(SpotifyError)?;
+
+    if response.status() == reqwest::StatusCode::FORBIDDEN {
+        return Err(Report::new(SpotifyError)
+            .attach_printable(format!("Spotify denied access to {}", url))
+            .attach(Suggestion(
+                "Your Spotify login is missing permissions. Please log in again.".to_string(),
+            )));
+    }
+
+    if !response.status().is_success() {
+        let error_body = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Could not read error body".to_string());
+        return Err(Report::new(SpotifyError)
+            .attach_printable(format!("Spotify API returned an error: {}", error_body)));
+    }
+
+    response
+        .json::<T>()
+        .await
+        .into_report()
+        .change_context(SpotifyError)
+}
+
+/// Albums saved in the library of the user, most recently saved first.
+/// `api_url` is the Spotify Web API, `SPOTIFY_API_URL` outside of the tests
+pub async fn get_saved_albums(
+    user_config: &mut User,
+    api_url: &str,
+) -> SpotifyResult<Vec<SavedAlbum>> {
+    let mut albums = Vec::new();
+    let mut next_url = Some(format!("{}/me/albums?limit=50", api_url));
+    while let Some(url) = next_url {
+        let page: PaginatedSavedAlbums = get_json(user_config, &url).await?;
+        albums.extend(page.items.into_iter().map(|item| {
+            SavedAlbum {
+                spotify_album_id: item.album.id,
+                name: item.album.name,
+                artists: item
+                    .album
+                    .artists
+                    .into_iter()
+                    .map(|artist| artist.name)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            }
+        }));
+        next_url = page.next;
+    }
+    Ok(albums)
+}
+
+/// Names of the artists followed by the user
+pub async fn get_followed_artists(
+    user_config: &mut User,
+    api_url: &str,
+) -> SpotifyResult<Vec<String>> {
+    let mut artists = Vec::new();
+    let mut next_url = Some(format!("{}/me/following?type=artist&limit=50", api_url));
+    while let Some(url) = next_url {
+        let response: FollowedArtistsResponse = get_json(user_config, &url).await?;
+        artists.extend(response.artists.items.into_iter().map(|artist| artist.name));
+        next_url = response.artists.next;
+    }
+    Ok(artists)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spotify::playlist::SpotifyPlaylist;
+    use crate::stand_in::{self, user};
+
+    /// Local stand-in for the Spotify Web API, answering each path with its fixture
+    fn start_api(fixtures: Vec<(&'static str, &'static str)>) -> String {
+        let (api_url, _) = stand_in::start(move |request| {
+            match fixtures.iter().find(|(path, _)| request.url == *path) {
+                Some((_, body)) => (200, body.to_string()),
+                None => (404, "{}".to_string()),
+            }
+        });
+        api_url
+    }
+
+    #[tokio::test]
+    async fn test_get_liked_songs_of_every_page() {
+        let api_url = start_api(vec![
+            (
+                "/me/tracks?limit=50",
+                r#"{
+                    "items": [
+                        {"added_at": "2025-09-01T10:00:00Z", "track": {
+                            "id": "liquid", "name": "Mr Majestic", "duration_ms": 301000,
+                            "artists": [{"name": "Calibre"}],
+                            "external_ids": {"isrc": "GBCPZ0500001"},
+                            "album": {"name": "Shelflife", "release_date": "2005-03-01"}
+                        }},
+                        {"added_at": "2025-09-01T10:00:00Z", "track": {
+                            "id": null, "name": "Local file", "artists": []
+                        }}
+                    ],
+                    "next": "{api_url}/me/tracks?offset=50&limit=50"
+                }"#,
+            ),
+            (
+                "/me/tracks?offset=50&limit=50",
+                r#"{
+                    "items": [
+                        {"added_at": "2025-08-01T10:00:00Z", "track": {
+                            "id": "rollers", "name": "Sea Of Stars",
+                            "artists": [{"name": "Degs"}, {"name": "Pola & Bryson"}]
+                        }}
+                    ],
+                    "next": null
+                }"#,
+            ),
+        ]);
+        let mut user = user();
+        let mut playlist = SpotifyPlaylist::liked_songs();
+
+        playlist
+            .get_liked_songs_info(&mut user, &api_url, false)
+            .await
+            .unwrap();
+
+        assert_eq!(playlist.track_order, vec!["liquid", "rollers"]);
+        let liquid = &playlist.tracks["liquid"];
+        assert_eq!(liquid.title, "Mr Majestic");
+        assert_eq!(liquid.artists, "Calibre");
+        assert_eq!(liquid.isrc, Some("GBCPZ0500001".to_string()));
+        assert_eq!(liquid.album, Some("Shelflife".to_string()));
+        assert_eq!(playlist.tracks["rollers"].artists, "Degs, Pola & Bryson");
+    }
+
+    #[tokio::test]
+    async fn test_get_saved_albums_of_every_page() {
+        let api_url = start_api(vec![
+            (
+                "/me/albums?limit=50",
+                r#"{
+                    "items": [
+                        {"added_at": "2025-09-01T10:00:00Z", "album": {
+                            "id": "shelflife", "name": "Shelflife",
+                            "artists": [{"name": "Calibre"}], "total_tracks": 12
+                        }}
+                    ],
+                    "next": "{api_url}/me/albums?offset=50&limit=50"
+                }"#,
+            ),
+            (
+                "/me/albums?offset=50&limit=50",
+                r#"{
+                    "items": [
+                        {"added_at": "2025-08-01T10:00:00Z", "album": {
+                            "id": "tangents", "name": "Tangents",
+                            "artists": [{"name": "Lenzman"}, {"name": "Riya"}]
+                        }}
+                    ],
+                    "next": null
+                }"#,
+            ),
+        ]);
+        let mut user = user();
+
+        let albums = get_saved_albums(&mut user, &api_url).await.unwrap();
+
+        let albums: Vec<(&str, &str, &str)> = albums
+            .iter()
+            .map(|album| {
+                (
+                    album.spotify_album_id.as_str(),
+                    album.name.as_str(),
+                    album.artists.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            albums,
+            vec![
+                ("shelflife", "Shelflife", "Calibre"),
+                ("tangents", "Tangents", "Lenzman, Riya")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_followed_artists_of_every_page() {
+        let api_url = start_api(vec![
+            (
+                "/me/following?type=artist&limit=50",
+                r#"{
+                    "artists": {
+                        "items": [{"id": "1", "name": "Calibre", "genres": ["liquid funk"]}],
+                        "next": "{api_url}/me/following?type=artist&after=1&limit=50",
+                        "cursors": {"after": "1"}
+                    }
+                }"#,
+            ),
+            (
+                "/me/following?type=artist&after=1&limit=50",
+                r#"{
+                    "artists": {
+                        "items": [{"id": "2", "name": "Alix Perez"}],
+                        "next": null,
+                        "cursors": {"after": null}
+                    }
+                }"#,
+            ),
+        ]);
+        let mut user = user();
+
+        let artists = get_followed_artists(&mut user, &api_url).await.unwrap();
+
+        assert_eq!(artists, vec!["Calibre", "Alix Perez"]);
+    }
+}
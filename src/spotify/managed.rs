@@ -0,0 +1,460 @@
+use std::collections::HashSet;
+
+use error_stack::{IntoReport, Report, ResultExt};
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::oauth::{OAuthProvider, TokenManager};
+use crate::soundeo::Soundeo;
+use crate::spotify::playlist::SpotifyPlaylist;
+use crate::spotify::review::ReviewDecision;
+use crate::spotify::{Spotify, SpotifyError, SpotifyResult};
+use crate::user::User;
+
+pub const SPOTIFY_API_URL: &str = "https://api.spotify.com/v1";
+
+/// Tracks sent per request when replacing the tracks of a playlist, the Spotify maximum
+const TRACKS_PER_REQUEST: usize = 100;
+
+/// Playlists kept up to date by dj-wizard in the Spotify account of the user
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+)]
+pub enum ManagedPlaylist {
+    NotOnSoundeo,
+    ReadyToPlay,
+}
+
+impl ManagedPlaylist {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ManagedPlaylist::NotOnSoundeo => "DJ Wizard - Not on Soundeo",
+            ManagedPlaylist::ReadyToPlay => "DJ Wizard - Ready to play",
+        }
+    }
+
+    fn description(&self) -> &'static str {
+        match self {
+            ManagedPlaylist::NotOnSoundeo => {
+                "Tracks of your playlists that could not be found on Soundeo. Managed by dj-wizard."
+            }
+            ManagedPlaylist::ReadyToPlay => {
+                "Tracks of your playlists that are paired and downloaded. Managed by dj-wizard."
+            }
+        }
+    }
+
+    /// Spotify track ids the playlist should hold, following the synced playlists sorted by name.
+    /// Not on Soundeo holds the tracks rejected by the reviewer and the ones without candidates
+    pub fn track_ids(&self, spotify: &Spotify, soundeo: &Soundeo) -> Vec<String> {
+        let mut playlists: Vec<&SpotifyPlaylist> = spotify
+            .playlists
+            .values()
+            .filter(|playlist| {
+                !spotify
+                    .managed_playlists
+                    .values()
+                    .any(|id| id == &playlist.spotify_playlist_id)
+            })
+            .collect();
+        playlists.sort_by_key(|playlist| playlist.name.to_lowercase());
+
+        let mut seen = HashSet::new();
+        playlists
+            .into_iter()
+            .flat_map(|playlist| playlist.ordered_tracks())
+            .map(|track| track.spotify_track_id.clone())
+            .filter(|spotify_track_id| match self {
+                ManagedPlaylist::NotOnSoundeo => {
+                    spotify.review_decisions.get(spotify_track_id)
+                        == Some(&ReviewDecision::NotOnSoundeo)
+                        || spotify
+                            .pairing_reviews
+                            .get(spotify_track_id)
+                            .is_some_and(|review| review.candidates.is_empty())
+                }
+                ManagedPlaylist::ReadyToPlay => spotify
+                    .soundeo_track_ids
+                    .get(spotify_track_id)
+                    .cloned()
+                    .flatten()
+                    .and_then(|soundeo_id| soundeo.tracks_info.get(&soundeo_id))
+                    .is_some_and(|track| track.already_downloaded),
+            })
+            .filter(|spotify_track_id| seen.insert(spotify_track_id.clone()))
+            .collect()
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiUser {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ApiCreatedPlaylist {
+    id: String,
+}
+
+/// Writes the managed playlists through the Spotify Web API
+pub struct ManagedPlaylistsWriter<'a> {
+    api_url: String,
+    tokens: TokenManager<'a>,
+    user_id: Option<String>,
+}
+
+impl<'a> ManagedPlaylistsWriter<'a> {
+    pub fn new(user_config: &'a mut User) -> Self {
+        Self::with_api_url(user_config, SPOTIFY_API_URL.to_string())
+    }
+
+    /// Uses `api_url` instead of the Spotify Web API, for a local stand-in
+    pub fn with_api_url(user_config: &'a mut User, api_url: String) -> Self {
+        Self {
+            api_url,
+            tokens: TokenManager::new(OAuthProvider::Spotify, user_config),
+            user_id: None,
+        }
+    }
+
+    /// Replaces the tracks of the playlist, creating it again when there is no `stored_id` or it
+    /// was deleted from Spotify. Returns the id of the playlist
+    pub async fn update(
+        &mut self,
+        managed_playlist: ManagedPlaylist,
+        stored_id: Option<String>,
+        track_ids: &[String],
+    ) -> SpotifyResult<String> {
+        if let Some(playlist_id) = stored_id {
+            // Deleting a playlist on Spotify only unfollows it, it can still be written
+            if self.is_followed(&playlist_id).await?
+                && self.replace_tracks(&playlist_id, track_ids).await?
+            {
+                return Ok(playlist_id);
+            }
+        }
+        let playlist_id = self.create_playlist(managed_playlist).await?;
+        self.replace_tracks(&playlist_id, track_ids).await?;
+        Ok(playlist_id)
+    }
+
+    async fn user_id(&mut self) -> SpotifyResult<String> {
+        if let Some(user_id) = self.user_id.clone() {
+            return Ok(user_id);
+        }
+        let response = self.send(Method::GET, "/me", None).await?;
+        let response = Self::success(response).await?;
+        let api_user: ApiUser = response
+            .json()
+            .await
+            .into_report()
+            .change_context(SpotifyError)?;
+        self.user_id = Some(api_user.id.clone());
+        Ok(api_user.id)
+    }
+
+    /// Whether the user still follows the playlist, false when it does not exist
+    async fn is_followed(&mut self, playlist_id: &str) -> SpotifyResult<bool> {
+        let user_id = self.user_id().await?;
+        let path = format!(
+            "/playlists/{}/followers/contains?ids={}",
+            playlist_id, user_id
+        );
+        let response = self.send(Method::GET, &path, None).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        let response = Self::success(response).await?;
+        let follows: Vec<bool> = response
+            .json()
+            .await
+            .into_report()
+            .change_context(SpotifyError)?;
+        Ok(follows.first().copied().unwrap_or(false))
+    }
+
+    async fn create_playlist(
+        &mut self,
+        managed_playlist: ManagedPlaylist,
+    ) -> SpotifyResult<String> {
+        let user_id = self.user_id().await?;
+        let response = self
+            .send(
+                Method::POST,
+                &format!("/users/{}/playlists", user_id),
+                Some(json!({
+                    "name": managed_playlist.name(),
+                    "description": managed_playlist.description(),
+                    "public": false,
+                })),
+            )
+            .await?;
+        let response = Self::success(response).await?;
+        let created: ApiCreatedPlaylist = response
+            .json()
+            .await
+            .into_report()
+            .change_context(SpotifyError)?;
+        Ok(created.id)
+    }
+
+    /// Returns false when the playlist does not exist
+    async fn replace_tracks(
+        &mut self,
+        playlist_id: &str,
+        track_ids: &[String],
+    ) -> SpotifyResult<bool> {
+        let uris: Vec<String> = track_ids
+            .iter()
+            .map(|track_id| format!("spotify:track:{}", track_id))
+            .collect();
+        let path = format!("/playlists/{}/tracks", playlist_id);
+        let mut chunks = uris.chunks(TRACKS_PER_REQUEST);
+
+        // The first request replaces every track, the rest append
+        let first_chunk = chunks.next().unwrap_or(&[]);
+        let response = self
+            .send(Method::PUT, &path, Some(json!({ "uris": first_chunk })))
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        Self::success(response).await?;
+        for chunk in chunks {
+            let response = self
+                .send(Method::POST, &path, Some(json!({ "uris": chunk })))
+                .await?;
+            Self::success(response).await?;
+        }
+        Ok(true)
+    }
+
+    async fn send(
+        &mut self,
+        method: Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> SpotifyResult<reqwest::Response> {
+        let url = format!("{}{}", self.api_url, path);
+        self.tokens
+            .send(|client| {
+                let request = client.request(method.clone(), &url);
+                match &body {
+                    Some(body) => request.json(body),
+                    None => request,
+                }
+            })
+            .await
+            .change_context(SpotifyError)
+    }
+
+    async fn success(response: reqwest::Response) -> SpotifyResult<reqwest::Response> {
+        if !response.status().is_success() {
+            let error_body = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Could not read error body".to_string());
+            return Err(Report::new(SpotifyError)
+                .attach_printable(format!("Spotify API returned an error: {}", error_body)));
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::soundeo::track::SoundeoTrack;
+    use crate::spotify::review::PairingReview;
+    use crate::spotify::track::SpotifyTrack;
+    use crate::stand_in::{self, user, Requests};
+
+    /// Local stand-in for the Spotify Web API
+    fn start_api(deleted_playlist_id: &'static str) -> (String, Requests) {
+        stand_in::start(
+            move |request| match (request.method.as_str(), request.url.as_str()) {
+                ("GET", "/me") => (200, r#"{"id":"dj"}"#.to_string()),
+                ("POST", "/users/dj/playlists") => (200, r#"{"id":"created"}"#.to_string()),
+                (_, url) if url.contains(deleted_playlist_id) => (404, "{}".to_string()),
+                ("GET", url) if url.contains("/followers/contains") => {
+                    (200, format!("[{}]", !url.contains("unfollowed")))
+                }
+                _ => (200, r#"{"snapshot_id":"snapshot"}"#.to_string()),
+            },
+        )
+    }
+
+    fn track_ids(count: usize) -> Vec<String> {
+        (0..count).map(|i| format!("track{}", i)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_update_replaces_tracks_in_chunks() {
+        let (api_url, requests) = start_api("deleted");
+        let mut user = user();
+        let mut writer = ManagedPlaylistsWriter::with_api_url(&mut user, api_url);
+
+        let playlist_id = writer
+            .update(
+                ManagedPlaylist::ReadyToPlay,
+                Some("stored".to_string()),
+                &track_ids(150),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(playlist_id, "stored");
+        let requests = requests.lock().unwrap();
+        let routes: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|request| (request.method.as_str(), request.url.as_str()))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                ("GET", "/me"),
+                ("GET", "/playlists/stored/followers/contains?ids=dj"),
+                ("PUT", "/playlists/stored/tracks"),
+                ("POST", "/playlists/stored/tracks")
+            ]
+        );
+        let uris = |body: &str| {
+            serde_json::from_str::<serde_json::Value>(body).unwrap()["uris"]
+                .as_array()
+                .unwrap()
+                .len()
+        };
+        assert_eq!(uris(&requests[2].body), 100);
+        assert_eq!(uris(&requests[3].body), 50);
+        assert!(requests[2].body.contains("spotify:track:track0"));
+    }
+
+    #[tokio::test]
+    async fn test_update_creates_deleted_playlist() {
+        let (api_url, requests) = start_api("deleted");
+        let mut user = user();
+        let mut writer = ManagedPlaylistsWriter::with_api_url(&mut user, api_url);
+
+        let playlist_id = writer
+            .update(
+                ManagedPlaylist::NotOnSoundeo,
+                Some("deleted".to_string()),
+                &track_ids(1),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(playlist_id, "created");
+        let requests = requests.lock().unwrap();
+        let routes: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|request| (request.method.as_str(), request.url.as_str()))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                ("GET", "/me"),
+                ("GET", "/playlists/deleted/followers/contains?ids=dj"),
+                ("POST", "/users/dj/playlists"),
+                ("PUT", "/playlists/created/tracks")
+            ]
+        );
+        assert!(requests[2].body.contains("DJ Wizard - Not on Soundeo"));
+    }
+
+    #[tokio::test]
+    async fn test_update_creates_unfollowed_playlist() {
+        let (api_url, requests) = start_api("deleted");
+        let mut user = user();
+        let mut writer = ManagedPlaylistsWriter::with_api_url(&mut user, api_url);
+
+        let playlist_id = writer
+            .update(
+                ManagedPlaylist::ReadyToPlay,
+                Some("unfollowed".to_string()),
+                &track_ids(1),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(playlist_id, "created");
+        let requests = requests.lock().unwrap();
+        let routes: Vec<(&str, &str)> = requests
+            .iter()
+            .map(|request| (request.method.as_str(), request.url.as_str()))
+            .collect();
+        assert_eq!(
+            routes,
+            vec![
+                ("GET", "/me"),
+                ("GET", "/playlists/unfollowed/followers/contains?ids=dj"),
+                ("POST", "/users/dj/playlists"),
+                ("PUT", "/playlists/created/tracks")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_managed_playlists_track_ids() {
+        let mut spotify = Spotify::new();
+        let mut playlist =
+            SpotifyPlaylist::new("https://open.spotify.com/playlist/liquid".to_string()).unwrap();
+        for (id, artists) in [
+            ("downloaded", "Calibre"),
+            ("queued", "DRS"),
+            ("rejected", "Bou"),
+            ("no_candidates", "Zero T"),
+        ] {
+            let track = SpotifyTrack::new(
+                format!("Title {}", id),
+                artists.to_string(),
+                id.to_string(),
+                None,
+            );
+            playlist.track_order.push(id.to_string());
+            playlist.tracks.insert(id.to_string(), track);
+        }
+        spotify
+            .playlists
+            .insert(playlist.spotify_playlist_id.clone(), playlist.clone());
+        spotify
+            .soundeo_track_ids
+            .insert("downloaded".to_string(), Some("1".to_string()));
+        spotify
+            .soundeo_track_ids
+            .insert("queued".to_string(), Some("2".to_string()));
+        spotify
+            .review_decisions
+            .insert("rejected".to_string(), ReviewDecision::NotOnSoundeo);
+        spotify.pairing_reviews.insert(
+            "no_candidates".to_string(),
+            PairingReview::new(&playlist.tracks["no_candidates"], vec![]),
+        );
+        let mut soundeo = Soundeo::new();
+        let mut downloaded = SoundeoTrack::new("1".to_string());
+        downloaded.already_downloaded = true;
+        soundeo.tracks_info.insert("1".to_string(), downloaded);
+        soundeo
+            .tracks_info
+            .insert("2".to_string(), SoundeoTrack::new("2".to_string()));
+
+        assert_eq!(
+            ManagedPlaylist::ReadyToPlay.track_ids(&spotify, &soundeo),
+            vec!["downloaded".to_string()]
+        );
+        assert_eq!(
+            ManagedPlaylist::NotOnSoundeo.track_ids(&spotify, &soundeo),
+            vec!["rejected".to_string(), "no_candidates".to_string()]
+        );
+    }
+}
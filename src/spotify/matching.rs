@@ -0,0 +1,233 @@
+use chrono::NaiveDate;
+
+use crate::normalize::{similarity, TrackName};
+use crate::soundeo::track::SoundeoTrack;
+use crate::spotify::track::SpotifyTrack;
+use crate::spotify::Spotify;
+
+/// Candidates scoring at least this much are paired without asking
+pub const AUTO_PAIR_SCORE: u32 = 80;
+
+const TITLE_POINTS: u32 = 40;
+const VERSION_POINTS: u32 = 25;
+const ARTIST_POINTS: u32 = 25;
+const RELEASE_DATE_POINTS: u32 = 10;
+
+/// Versions, once normalized, Spotify uses for the main version of a track, which Soundeo
+/// sells as "Extended Mix" or "Original Mix"
+const MAIN_VERSIONS: [&str; 4] = ["original", "extended", "radio edit", "edit"];
+
+/// Scores from 0 to 100 how likely a Soundeo track is the same recording as a Spotify track
+pub fn score_candidate(spotify_track: &SpotifyTrack, soundeo_track: &SoundeoTrack) -> u32 {
+    let spotify_name = TrackName::parse(&spotify_track.artists, &spotify_track.title);
+    let soundeo_name = TrackName::from_soundeo_title(&soundeo_track.title);
+
+    title_score(&spotify_name.name, &soundeo_name.name)
+        + version_score(
+            spotify_name.normalized_version().as_deref(),
+            soundeo_name.normalized_version().as_deref(),
+        )
+        + artist_score(&spotify_name, &soundeo_name)
+        + release_date_score(spotify_track.release_date.as_deref(), &soundeo_track.date)
+}
+
+/// Position of the candidate to pair automatically: the best one reaching the threshold,
+/// as long as no other candidate with a different title scores the same
+pub fn best_candidate(scored: &[(u32, &SoundeoTrack)]) -> Option<usize> {
+    // The first one on ties, search results come sorted by relevance
+    let best_score = scored.iter().map(|(score, _)| *score).max()?;
+    let best_position = scored.iter().position(|(score, _)| *score == best_score)?;
+    let best_track = scored[best_position].1;
+    if best_score < AUTO_PAIR_SCORE {
+        return None;
+    }
+    let tied_with_other_title = scored.iter().any(|(score, track)| {
+        *score == best_score
+            && TrackName::from_soundeo_title(&track.title).key()
+                != TrackName::from_soundeo_title(&best_track.title).key()
+    });
+    (!tied_with_other_title).then_some(best_position)
+}
+
+/// Soundeo track paired to another Spotify track of the same recording, as the same track
+/// gets a different Spotify id on each release (single, album, compilation...)
+pub fn paired_by_isrc(spotify: &Spotify, spotify_track: &SpotifyTrack) -> Option<String> {
+    let isrc = spotify_track.isrc.as_ref()?;
+    spotify
+        .playlists
+        .values()
+        .flat_map(|playlist| playlist.tracks.values())
+        .filter(|other| {
+            other.isrc.as_ref() == Some(isrc)
+                && other.spotify_track_id != spotify_track.spotify_track_id
+        })
+        .find_map(|other| {
+            spotify
+                .soundeo_track_ids
+                .get(&other.spotify_track_id)
+                .cloned()
+                .flatten()
+        })
+}
+
+fn title_score(spotify_name: &str, soundeo_name: &str) -> u32 {
+    let similarity = similarity(spotify_name, soundeo_name);
+    if similarity < 0.5 {
+        return 0;
+    }
+    (TITLE_POINTS as f64 * similarity).round() as u32
+}
+
+/// A remix on Spotify has to be the same remix on Soundeo, otherwise the extended version
+/// of the track is preferred over the original one
+fn version_score(spotify_version: Option<&str>, soundeo_version: Option<&str>) -> u32 {
+    let is_main = |version: Option<&str>| version.map_or(true, |v| MAIN_VERSIONS.contains(&v));
+    match (is_main(spotify_version), is_main(soundeo_version)) {
+        (true, true) => match soundeo_version {
+            Some("extended") => VERSION_POINTS,
+            _ => VERSION_POINTS * 4 / 5,
+        },
+        (false, false) if spotify_version == soundeo_version => VERSION_POINTS,
+        _ => 0,
+    }
+}
+
+/// Share of the Spotify artists credited on Soundeo
+fn artist_score(spotify_name: &TrackName, soundeo_name: &TrackName) -> u32 {
+    let spotify_artists = spotify_name.artist_words();
+    if spotify_artists.is_empty() {
+        return 0;
+    }
+    let credited = spotify_artists
+        .intersection(&soundeo_name.artist_words())
+        .count();
+    ARTIST_POINTS * credited as u32 / spotify_artists.len() as u32
+}
+
+/// Spotify release dates can be just the year or the year and month
+fn release_date_score(spotify_release_date: Option<&str>, soundeo_date: &str) -> u32 {
+    let (Some(spotify_date), Some(soundeo_date)) = (
+        spotify_release_date.and_then(parse_release_date),
+        parse_release_date(soundeo_date),
+    ) else {
+        return 0;
+    };
+    let days = (spotify_date - soundeo_date).num_days().abs();
+    if days <= 31 {
+        RELEASE_DATE_POINTS
+    } else if days <= 366 {
+        RELEASE_DATE_POINTS / 2
+    } else {
+        0
+    }
+}
+
+fn parse_release_date(date: &str) -> Option<NaiveDate> {
+    let date = date.trim();
+    match date.len() {
+        4 => NaiveDate::parse_from_str(&format!("{}-01-01", date), "%Y-%m-%d").ok(),
+        7 => NaiveDate::parse_from_str(&format!("{}-01", date), "%Y-%m-%d").ok(),
+        _ => NaiveDate::parse_from_str(date.get(..10)?, "%Y-%m-%d").ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spotify::playlist::SpotifyPlaylist;
+
+    fn soundeo_track(id: &str, title: &str, date: &str) -> SoundeoTrack {
+        let mut track = SoundeoTrack::new(id.to_string());
+        track.title = title.to_string();
+        track.date = date.to_string();
+        track
+    }
+
+    #[test]
+    fn test_scores_prefer_the_same_version_and_artists() {
+        let mut spotify_track = SpotifyTrack::new(
+            "Mr Majestic".to_string(),
+            "Calibre, DRS".to_string(),
+            "spotify_id".to_string(),
+            None,
+        );
+        spotify_track.release_date = Some("2023-05".to_string());
+        let extended = soundeo_track(
+            "1",
+            "Calibre & DRS - Mr Majestic (Extended Mix)",
+            "2023-05-12",
+        );
+        let original = soundeo_track(
+            "2",
+            "Calibre & DRS - Mr Majestic (Original Mix)",
+            "2023-05-12",
+        );
+        let remix = soundeo_track(
+            "3",
+            "Calibre & DRS - Mr Majestic (Alix Perez Remix)",
+            "2023-05-12",
+        );
+        let other_artist = soundeo_track("4", "Calibre - Mr Majestic (Extended Mix)", "2019-01-01");
+
+        assert_eq!(score_candidate(&spotify_track, &extended), 100);
+        assert_eq!(score_candidate(&spotify_track, &original), 95);
+        assert_eq!(score_candidate(&spotify_track, &remix), 75);
+        assert_eq!(score_candidate(&spotify_track, &other_artist), 77);
+
+        let scored: Vec<(u32, &SoundeoTrack)> = [&remix, &original, &extended]
+            .into_iter()
+            .map(|track| (score_candidate(&spotify_track, track), track))
+            .collect();
+        assert_eq!(best_candidate(&scored), Some(2));
+
+        spotify_track.title = "Mr Majestic - Alix Perez Remix".to_string();
+        assert_eq!(score_candidate(&spotify_track, &remix), 100);
+        assert_eq!(score_candidate(&spotify_track, &extended), 75);
+    }
+
+    #[test]
+    fn test_best_candidate_needs_a_single_winner_above_the_threshold() {
+        let first = soundeo_track("1", "Calibre - Mr Majestic (Extended Mix)", "");
+        let second = soundeo_track("2", "Calibre - Mr Majestic (Extended Mix)", "");
+        let other = soundeo_track("3", "Calibre - Mr Majestic (VIP)", "");
+
+        assert_eq!(best_candidate(&[(90, &first), (90, &second)]), Some(0));
+        assert_eq!(best_candidate(&[(90, &first), (90, &other)]), None);
+        assert_eq!(best_candidate(&[(60, &first)]), None);
+        assert_eq!(best_candidate(&[]), None);
+    }
+
+    #[test]
+    fn test_paired_by_isrc_reuses_the_pairing_of_the_same_recording() {
+        let mut single = SpotifyTrack::new(
+            "Mr Majestic".to_string(),
+            "Calibre".to_string(),
+            "single".to_string(),
+            None,
+        );
+        single.isrc = Some("GBAAA2300001".to_string());
+        let mut album_track = single.clone();
+        album_track.spotify_track_id = "album".to_string();
+        let mut playlist =
+            SpotifyPlaylist::new("https://open.spotify.com/playlist/liquid".to_string()).unwrap();
+        playlist
+            .tracks
+            .insert(single.spotify_track_id.clone(), single.clone());
+        let mut spotify = Spotify::new();
+        spotify
+            .playlists
+            .insert(playlist.spotify_playlist_id.clone(), playlist);
+
+        assert_eq!(paired_by_isrc(&spotify, &album_track), None);
+
+        spotify
+            .soundeo_track_ids
+            .insert("single".to_string(), Some("1".to_string()));
+        assert_eq!(
+            paired_by_isrc(&spotify, &album_track),
+            Some("1".to_string())
+        );
+        album_track.isrc = None;
+        assert_eq!(paired_by_isrc(&spotify, &album_track), None);
+    }
+}
@@ -8,11 +8,18 @@ use crate::dialoguer::Dialoguer;
 use crate::log::DjWizardLog;
 use crate::log::DjWizardLogResult;
 use crate::soundeo::search_bar::SoundeoSearchBarResult;
+use crate::spotify::managed::ManagedPlaylist;
 use crate::spotify::playlist::SpotifyPlaylist;
+use crate::spotify::review::{PairingReview, ReviewDecision};
 
 pub mod api;
 pub mod commands;
+pub mod library;
+pub mod managed;
+pub mod matching;
+pub mod organizer;
 pub mod playlist;
+pub mod review;
 pub mod track;
 
 #[derive(Debug)]
@@ -32,8 +39,22 @@ pub type SpotifyResult<T> = error_stack::Result<T, SpotifyError>;
 pub struct Spotify {
     pub playlists: HashMap<String, SpotifyPlaylist>,
     pub soundeo_track_ids: HashMap<String, Option<String>>,
+    /// Spotify tracks waiting for the reviewer, by Spotify track id
     #[serde(default)]
+    pub pairing_reviews: HashMap<String, PairingReview>,
+    /// Decisions of the reviewer, so the same track is never reviewed again
+    #[serde(default)]
+    pub review_decisions: HashMap<String, ReviewDecision>,
+    /// Search results of the tracks with several matches, stored before the review queue
+    /// existed. Moved to `pairing_reviews` when the log is read
+    #[serde(default, skip_serializing)]
     pub multiple_matches_cache: HashMap<String, Vec<SoundeoSearchBarResult>>,
+    /// Folder name each playlist was last organized into, to follow playlist renames
+    #[serde(default)]
+    pub playlist_folders: HashMap<String, String>,
+    /// Ids of the playlists dj-wizard maintains in the Spotify account
+    #[serde(default)]
+    pub managed_playlists: HashMap<ManagedPlaylist, String>,
 }
 
 impl Spotify {
@@ -41,7 +62,11 @@ impl Spotify {
         Self {
             playlists: HashMap::new(),
             soundeo_track_ids: HashMap::new(),
+            pairing_reviews: HashMap::new(),
+            review_decisions: HashMap::new(),
             multiple_matches_cache: HashMap::new(),
+            playlist_folders: HashMap::new(),
+            managed_playlists: HashMap::new(),
         }
     }
 
@@ -66,8 +91,25 @@ pub trait SpotifyCRUD {
 
     fn delete_spotify_playlists(playlist_ids: &[String]) -> DjWizardLogResult<()>;
 
-    fn add_to_multiple_matches_cache(
-        spotify_id: String,
-        results: Vec<SoundeoSearchBarResult>,
+    /// Adds a track to the review queue, or merges the candidates of the one already there.
+    /// Tracks the reviewer already decided on are left alone
+    fn add_pairing_review(review: PairingReview) -> DjWizardLogResult<()>;
+
+    fn reject_review_candidates(
+        spotify_track_id: String,
+        soundeo_track_ids: Vec<String>,
+    ) -> DjWizardLogResult<()>;
+
+    /// Stores the decision and pairs the track, removing it from the review queue
+    fn resolve_pairing_review(
+        spotify_track_id: String,
+        decision: ReviewDecision,
+    ) -> DjWizardLogResult<()>;
+
+    fn set_playlist_folder(playlist_id: String, folder_name: String) -> DjWizardLogResult<()>;
+
+    fn set_managed_playlist(
+        managed_playlist: ManagedPlaylist,
+        spotify_playlist_id: String,
     ) -> DjWizardLogResult<()>;
 }
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::soundeo::track::TrackFormat;
+
+/// How a playlist folder references the downloaded files
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Default,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+)]
+pub enum LinkMode {
+    /// Same file under another name, no extra space and tag edits are shared.
+    /// Falls back to a copy when the playlist folder is on another drive
+    #[default]
+    Hardlink,
+    /// Link to the downloaded file, breaks if the download folder moves
+    Symlink,
+    Copy,
+}
+
+/// What happened to a playlist folder while syncing it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FolderSync {
+    pub linked: usize,
+    pub removed: Vec<String>,
+    /// Files no longer in the playlist that were kept because they are the only copy
+    pub kept: Vec<String>,
+}
+
+/// Name of a file or folder made from a playlist name, `fallback` when nothing usable is left,
+/// e.g. for "???" or "..", so the name never points at the download directory or its parent
+pub fn sanitize_name(name: &str, fallback: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .filter(|c| !matches!(*c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|'))
+        .collect();
+    let sanitized = sanitized.trim();
+    if sanitized.chars().all(|c| c == '.') {
+        fallback.to_string()
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Keeps a playlist folder in sync with the files of its tracks.
+/// `expected_files` maps the file names the folder should hold to the downloaded file.
+/// The folder has to be inside the download directory, as the cleanup removes the files
+/// that are also in the download directory
+pub fn sync_folder(
+    download_dir: &Path,
+    folder: &Path,
+    expected_files: &HashMap<String, PathBuf>,
+    link_mode: LinkMode,
+) -> io::Result<FolderSync> {
+    fs::create_dir_all(folder)?;
+    let canonical_download_dir = download_dir.canonicalize()?;
+    let canonical_folder = folder.canonicalize()?;
+    if canonical_folder == canonical_download_dir
+        || !canonical_folder.starts_with(&canonical_download_dir)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "The playlist folder {} is not inside the download directory {}",
+                folder.display(),
+                download_dir.display()
+            ),
+        ));
+    }
+    let mut folder_sync = FolderSync::default();
+    for (file_name, source) in expected_files {
+        let destination = folder.join(file_name);
+        if destination == *source {
+            continue;
+        }
+        if is_linked(source, &destination, link_mode) {
+            continue;
+        }
+        if fs::symlink_metadata(&destination).is_ok() {
+            fs::remove_file(&destination)?;
+        }
+        link_file(source, &destination, link_mode)?;
+        folder_sync.linked += 1;
+    }
+
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !is_track_file(&path) || expected_files.contains_key(file_name) {
+            continue;
+        }
+        let is_symlink = fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_symlink());
+        if is_symlink || download_dir.join(file_name).is_file() {
+            fs::remove_file(&path)?;
+            folder_sync.removed.push(file_name.to_string());
+        } else {
+            folder_sync.kept.push(file_name.to_string());
+        }
+    }
+    folder_sync.removed.sort();
+    folder_sync.kept.sort();
+    Ok(folder_sync)
+}
+
+/// Whether the file has the extension of one of the downloaded formats
+fn is_track_file(path: &Path) -> bool {
+    TrackFormat::from_path(path).is_some()
+}
+
+/// Renames the folder of a renamed playlist, unless a folder with the new name already exists.
+/// Returns whether the folder was renamed
+pub fn rename_folder(old_folder: &Path, new_folder: &Path) -> io::Result<bool> {
+    if old_folder == new_folder || !old_folder.is_dir() || new_folder.exists() {
+        return Ok(false);
+    }
+    fs::rename(old_folder, new_folder)?;
+    Ok(true)
+}
+
+fn link_file(source: &Path, destination: &Path, link_mode: LinkMode) -> io::Result<()> {
+    match link_mode {
+        LinkMode::Hardlink => {
+            if let Err(error) = fs::hard_link(source, destination) {
+                log::warn!(
+                    "Failed to hard link {}, copying it instead: {}",
+                    source.display(),
+                    error
+                );
+                fs::copy(source, destination)?;
+            }
+            Ok(())
+        }
+        LinkMode::Symlink => symlink_file(source, destination),
+        LinkMode::Copy => fs::copy(source, destination).map(|_| ()),
+    }
+}
+
+/// Whether `destination` already references `source` the way the link mode asks for
+fn is_linked(source: &Path, destination: &Path, link_mode: LinkMode) -> bool {
+    let Ok(metadata) = fs::symlink_metadata(destination) else {
+        return false;
+    };
+    match link_mode {
+        LinkMode::Symlink => fs::read_link(destination).is_ok_and(|target| target == source),
+        LinkMode::Copy => metadata.is_file(),
+        LinkMode::Hardlink => metadata.is_file() && is_same_file(source, destination),
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Without inode numbers, a file of the same size is taken as the hard link
+#[cfg(not(unix))]
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.len() == b.len(),
+        _ => false,
+    }
+}
+
+#[cfg(unix)]
+fn symlink_file(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, destination)
+}
+
+#[cfg(windows)]
+fn symlink_file(source: &Path, destination: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(source, destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_links_new_tracks_and_removes_old_ones() {
+        let download_dir =
+            std::env::temp_dir().join(format!("dj-wizard-organizer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&download_dir);
+        fs::create_dir_all(&download_dir).unwrap();
+        let track = download_dir.join("A - B.AIFF");
+        fs::write(&track, "track").unwrap();
+        fs::write(download_dir.join("C - D.AIFF"), "removed").unwrap();
+        fs::write(download_dir.join("E - F.MP3"), "removed").unwrap();
+        let folder = download_dir.join("Liquid");
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("C - D.AIFF"), "removed").unwrap();
+        fs::write(folder.join("E - F.MP3"), "removed").unwrap();
+        fs::write(folder.join("Only Copy.wav"), "only copy").unwrap();
+        fs::write(folder.join("cover.jpg"), "not a track").unwrap();
+        let expected_files = HashMap::from([("A - B.AIFF".to_string(), track.clone())]);
+
+        let folder_sync =
+            sync_folder(&download_dir, &folder, &expected_files, LinkMode::Hardlink).unwrap();
+
+        assert_eq!(
+            folder_sync,
+            FolderSync {
+                linked: 1,
+                removed: vec!["C - D.AIFF".to_string(), "E - F.MP3".to_string()],
+                kept: vec!["Only Copy.wav".to_string()],
+            }
+        );
+        assert!(folder.join("cover.jpg").is_file());
+        assert!(is_linked(
+            &track,
+            &folder.join("A - B.AIFF"),
+            LinkMode::Hardlink
+        ));
+        let resync =
+            sync_folder(&download_dir, &folder, &expected_files, LinkMode::Hardlink).unwrap();
+        assert_eq!(resync.linked, 0);
+
+        let renamed = download_dir.join("Liquid Rollers");
+        assert!(rename_folder(&folder, &renamed).unwrap());
+        assert!(renamed.join("A - B.AIFF").is_file());
+        fs::remove_dir_all(&download_dir).unwrap();
+    }
+
+    #[test]
+    fn test_sync_never_cleans_the_download_dir() {
+        let download_dir =
+            std::env::temp_dir().join(format!("dj-wizard-organizer-unsafe-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&download_dir);
+        fs::create_dir_all(&download_dir).unwrap();
+        fs::write(download_dir.join("A - B.AIFF"), "track").unwrap();
+        let expected_files = HashMap::new();
+
+        let folder_name = sanitize_name("???", "37i9dQZF1DX8Ue");
+        assert_eq!(folder_name, "37i9dQZF1DX8Ue");
+        assert_eq!(sanitize_name("..", "37i9dQZF1DX8Ue"), "37i9dQZF1DX8Ue");
+        assert_eq!(sanitize_name(" // ", "37i9dQZF1DX8Ue"), "37i9dQZF1DX8Ue");
+        let folder_sync = sync_folder(
+            &download_dir,
+            &download_dir.join(&folder_name),
+            &expected_files,
+            LinkMode::Copy,
+        )
+        .unwrap();
+        assert!(folder_sync.removed.is_empty());
+
+        for unsafe_folder in [
+            download_dir.join(""),
+            download_dir.join(".."),
+            download_dir.join("."),
+        ] {
+            assert!(sync_folder(
+                &download_dir,
+                &unsafe_folder,
+                &expected_files,
+                LinkMode::Copy
+            )
+            .is_err());
+        }
+        assert!(download_dir.join("A - B.AIFF").is_file());
+        fs::remove_dir_all(&download_dir).unwrap();
+    }
+}
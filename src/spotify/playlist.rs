@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::dialoguer::Dialoguer;
 use crate::log::DjWizardLog;
@@ -9,6 +9,9 @@ use error_stack::{IntoReport, Report, ResultExt};
 use serde::{Deserialize, Serialize};
 use url::Url;
 
+use crate::spotify::library::{self, LIKED_SONGS_PLAYLIST_ID};
+use crate::spotify::managed::SPOTIFY_API_URL;
+use crate::spotify::review::{review_pairings, PairingReview};
 use crate::spotify::track::AutoPairResult;
 use crate::spotify::track::SpotifyTrack;
 use crate::spotify::{SpotifyCRUD, SpotifyError, SpotifyResult};
@@ -30,6 +33,56 @@ pub struct SpotifyPlaylist {
     pub spotify_playlist_id: String,
     pub url: String,
     pub tracks: HashMap<String, SpotifyTrack>,
+    /// Spotify track ids in playlist order, empty for playlists synced before it was stored
+    #[serde(default)]
+    pub track_order: Vec<String>,
+    /// Version of the playlist on Spotify, it changes whenever the playlist does
+    #[serde(default)]
+    pub snapshot_id: Option<String>,
+    /// Tracks added and removed between syncs, oldest first
+    #[serde(default)]
+    pub changes: Vec<PlaylistChange>,
+    /// Where the tracks come from, Liked Songs and saved albums are stored as playlists
+    #[serde(default)]
+    pub source: PlaylistSource,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum PlaylistSource {
+    #[default]
+    Playlist,
+    LikedSongs,
+    Album,
+}
+
+/// Changes kept per playlist, the oldest are dropped first
+pub const MAX_PLAYLIST_CHANGES: usize = 1000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PlaylistChangeKind {
+    Added,
+    Removed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PlaylistChange {
+    pub kind: PlaylistChangeKind,
+    pub spotify_track_id: String,
+    pub title: String,
+    pub artists: String,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiExternalIds {
+    isrc: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiAlbum {
+    name: String,
+    release_date: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -37,6 +90,12 @@ struct ApiTrack {
     id: Option<String>,
     name: String,
     artists: Vec<ApiArtist>,
+    #[serde(default)]
+    duration_ms: Option<u64>,
+    #[serde(default)]
+    external_ids: Option<ApiExternalIds>,
+    #[serde(default)]
+    album: Option<ApiAlbum>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -54,6 +113,26 @@ struct PlaylistTracks {
 struct ApiPlaylist {
     name: String,
     tracks: PlaylistTracks,
+    #[serde(default)]
+    snapshot_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct AlbumTracks {
+    items: Vec<ApiTrack>,
+    next: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiFullAlbum {
+    name: String,
+    release_date: Option<String>,
+    tracks: AlbumTracks,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct ApiPlaylistSnapshot {
+    snapshot_id: String,
 }
 
 impl SpotifyPlaylist {
@@ -74,14 +153,43 @@ impl SpotifyPlaylist {
             spotify_playlist_id: sections.next().unwrap().to_string(),
             url,
             tracks: HashMap::new(),
+            track_order: vec![],
+            snapshot_id: None,
+            changes: vec![],
+            source: PlaylistSource::Playlist,
         })
     }
 
-    /// Fetches playlist information (name and tracks) from the Spotify API.
-    ///
-    /// This function requires `SPOTIFY_CLIENT_ID` and `SPOTIFY_CLIENT_SECRET` to be set
-    /// in a `.env` file in the project root. It uses the Client Credentials Flow to
-    /// authenticate with the Spotify API.
+    /// The Liked Songs of the user, stored as a playlist
+    pub fn liked_songs() -> Self {
+        Self {
+            name: "Liked Songs".to_string(),
+            spotify_playlist_id: LIKED_SONGS_PLAYLIST_ID.to_string(),
+            url: "https://open.spotify.com/collection/tracks".to_string(),
+            tracks: HashMap::new(),
+            track_order: vec![],
+            snapshot_id: None,
+            changes: vec![],
+            source: PlaylistSource::LikedSongs,
+        }
+    }
+
+    /// An album saved in the library of the user, stored as a playlist
+    pub fn saved_album(spotify_album_id: String, name: String) -> Self {
+        Self {
+            name,
+            url: format!("https://open.spotify.com/album/{}", spotify_album_id),
+            spotify_playlist_id: spotify_album_id,
+            tracks: HashMap::new(),
+            track_order: vec![],
+            snapshot_id: None,
+            changes: vec![],
+            source: PlaylistSource::Album,
+        }
+    }
+
+    /// Fetches playlist information (name and tracks) from the Spotify API,
+    /// authenticated with the token of the Spotify login.
     pub async fn get_playlist_info(
         &mut self,
         user_config: &mut User,
@@ -91,104 +199,98 @@ impl SpotifyPlaylist {
             println!("Getting playlist info from Spotify API...");
         }
 
-        let client = reqwest::Client::new();
+        match self.source {
+            PlaylistSource::Playlist => {}
+            PlaylistSource::LikedSongs => {
+                return self
+                    .get_liked_songs_info(user_config, SPOTIFY_API_URL, verbose)
+                    .await
+            }
+            PlaylistSource::Album => return self.get_album_info(user_config, verbose).await,
+        }
 
         // --- Get Playlist Info (first page) ---
         let playlist_url = format!(
             "https://api.spotify.com/v1/playlists/{}",
             self.spotify_playlist_id
         );
-
-        let mut response = client
-            .get(&playlist_url)
-            .bearer_auth(&user_config.spotify_access_token)
-            .send()
-            .await
-            .into_report()
-            .change_context(SpotifyError)?;
-
-        if response.status() == reqwest::StatusCode::UNAUTHORIZED {
-            user_config
-                .refresh_spotify_token()
-                .await
-                .change_context(SpotifyError)?;
-            response = client
-                .get(&playlist_url)
-                .bearer_auth(&user_config.spotify_access_token)
-                .send()
-                .await
-                .into_report()
-                .change_context(SpotifyError)?;
-        }
-
-        if !response.status().is_success() {
-            let error_body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Could not read error body".to_string());
-            return Err(Report::new(SpotifyError)
-                .attach_printable(format!("Spotify API returned an error: {}", error_body)));
-        }
-
-        let mut api_playlist: ApiPlaylist = response
-            .json::<ApiPlaylist>()
-            .await
-            .into_report()
-            .change_context(SpotifyError)?;
+        let mut api_playlist: ApiPlaylist = library::get_json(user_config, &playlist_url).await?;
 
         self.name = api_playlist.name;
+        self.snapshot_id = api_playlist.snapshot_id;
         if verbose {
             println!("The playlist name is {}", self.name.clone().green());
         }
 
         // --- Process tracks and handle pagination ---
         self.tracks.clear();
+        self.track_order.clear();
         let mut next_url = api_playlist.tracks.next.take();
 
         self.process_track_items(api_playlist.tracks.items, verbose);
 
         while let Some(url) = next_url {
-            let mut paginated_response_raw = client
-                .get(&url)
-                .bearer_auth(&user_config.spotify_access_token)
-                .send()
-                .await
-                .into_report()
-                .change_context(SpotifyError)?;
-
-            if paginated_response_raw.status() == reqwest::StatusCode::UNAUTHORIZED {
-                user_config
-                    .refresh_spotify_token()
-                    .await
-                    .change_context(SpotifyError)?;
-                paginated_response_raw = client
-                    .get(&url)
-                    .bearer_auth(&user_config.spotify_access_token)
-                    .send()
-                    .await
-                    .into_report()
-                    .change_context(SpotifyError)?;
-            }
+            let paginated_response: PlaylistTracks = library::get_json(user_config, &url).await?;
+            self.process_track_items(paginated_response.items, verbose);
+            next_url = paginated_response.next;
+        }
 
-            if !paginated_response_raw.status().is_success() {
-                let error_body = paginated_response_raw
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Could not read error body".to_string());
-                return Err(Report::new(SpotifyError)
-                    .attach_printable(format!("Spotify API returned an error: {}", error_body)));
-            }
+        Ok(())
+    }
 
-            let paginated_response: PlaylistTracks = paginated_response_raw
-                .json::<PlaylistTracks>()
-                .await
-                .into_report()
-                .change_context(SpotifyError)?;
+    /// Fetches the Liked Songs from the Spotify Web API at `api_url`
+    pub async fn get_liked_songs_info(
+        &mut self,
+        user_config: &mut User,
+        api_url: &str,
+        verbose: bool,
+    ) -> SpotifyResult<()> {
+        self.tracks.clear();
+        self.track_order.clear();
+        let mut next_url = Some(format!("{}/me/tracks?limit=50", api_url));
+        while let Some(url) = next_url {
+            let page: PlaylistTracks = library::get_json(user_config, &url).await?;
+            self.process_track_items(page.items, verbose);
+            next_url = page.next;
+        }
+        Ok(())
+    }
 
-            self.process_track_items(paginated_response.items, verbose);
-            next_url = paginated_response.next;
+    async fn get_album_info(&mut self, user_config: &mut User, verbose: bool) -> SpotifyResult<()> {
+        let album_url = format!(
+            "https://api.spotify.com/v1/albums/{}",
+            self.spotify_playlist_id
+        );
+        let api_album: ApiFullAlbum = library::get_json(user_config, &album_url).await?;
+        self.name = api_album.name.clone();
+        if verbose {
+            println!("The album name is {}", self.name.clone().green());
         }
 
+        // Album tracks come without the album, it is added so they look like playlist tracks
+        let album = ApiAlbum {
+            name: api_album.name,
+            release_date: api_album.release_date,
+        };
+        let into_items = |tracks: Vec<ApiTrack>| -> Vec<PlaylistItem> {
+            tracks
+                .into_iter()
+                .map(|mut track| {
+                    track.album = Some(album.clone());
+                    PlaylistItem { track: Some(track) }
+                })
+                .collect()
+        };
+
+        self.tracks.clear();
+        self.track_order.clear();
+        self.process_track_items(into_items(api_album.tracks.items), verbose);
+        let mut next_url = api_album.tracks.next;
+        while let Some(url) = next_url {
+            let page: AlbumTracks = library::get_json(user_config, &url).await?;
+            self.process_track_items(into_items(page.items), verbose);
+            next_url = page.next;
+        }
         Ok(())
     }
 
@@ -200,13 +302,25 @@ impl SpotifyPlaylist {
                         track.artists.iter().map(|a| a.name.clone()).collect();
                     let artists_string = artists.join(", ");
 
-                    let spotify_track = SpotifyTrack::new(
+                    let mut spotify_track = SpotifyTrack::new(
                         track.name.clone(),
                         artists_string.clone(),
                         track_id.clone(),
+                        track.duration_ms,
                     );
+                    spotify_track.isrc = track.external_ids.and_then(|ids| ids.isrc);
+                    if let Some(album) = track.album {
+                        spotify_track.album = Some(album.name);
+                        spotify_track.release_date = album.release_date;
+                    }
 
-                    self.tracks.insert(track_id, spotify_track);
+                    if self
+                        .tracks
+                        .insert(track_id.clone(), spotify_track)
+                        .is_none()
+                    {
+                        self.track_order.push(track_id);
+                    }
                     if verbose {
                         println!(
                             "Adding {} by {} to the playlist data",
@@ -224,21 +338,74 @@ impl SpotifyPlaylist {
         }
     }
 
+    /// Fetches only the current snapshot id of the playlist, to know if it changed
+    pub async fn get_snapshot_id(&self, user_config: &mut User) -> SpotifyResult<String> {
+        let snapshot_url = format!(
+            "https://api.spotify.com/v1/playlists/{}?fields=snapshot_id",
+            self.spotify_playlist_id
+        );
+        let snapshot: ApiPlaylistSnapshot = library::get_json(user_config, &snapshot_url).await?;
+        Ok(snapshot.snapshot_id)
+    }
+
+    /// Records the tracks added and removed since `previous_tracks`, returning the new changes.
+    /// Added tracks follow the playlist order, removed ones are sorted by artist
+    pub fn record_changes(
+        &mut self,
+        previous_tracks: &HashMap<String, SpotifyTrack>,
+        timestamp: u64,
+    ) -> Vec<PlaylistChange> {
+        let change = |kind: PlaylistChangeKind, track: &SpotifyTrack| PlaylistChange {
+            kind,
+            spotify_track_id: track.spotify_track_id.clone(),
+            title: track.title.clone(),
+            artists: track.artists.clone(),
+            timestamp,
+        };
+        let mut new_changes: Vec<PlaylistChange> = self
+            .ordered_tracks()
+            .into_iter()
+            .filter(|track| !previous_tracks.contains_key(&track.spotify_track_id))
+            .map(|track| change(PlaylistChangeKind::Added, track))
+            .collect();
+        let mut removed: Vec<&SpotifyTrack> = previous_tracks
+            .values()
+            .filter(|track| !self.tracks.contains_key(&track.spotify_track_id))
+            .collect();
+        removed.sort_by(|a, b| (&a.artists, &a.title).cmp(&(&b.artists, &b.title)));
+        new_changes.extend(
+            removed
+                .into_iter()
+                .map(|track| change(PlaylistChangeKind::Removed, track)),
+        );
+
+        self.changes.extend(new_changes.iter().cloned());
+        if self.changes.len() > MAX_PLAYLIST_CHANGES {
+            let excess = self.changes.len() - MAX_PLAYLIST_CHANGES;
+            self.changes.drain(..excess);
+        }
+        new_changes
+    }
+
     pub async fn pair_unpaired_tracks(
         &mut self,
         soundeo_user: &mut SoundeoUser,
     ) -> SpotifyResult<()> {
         let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
 
+        // Tracks already waiting for review are not searched again
         let unpaired_tracks: Vec<_> = self
             .tracks
             .iter()
-            .filter(|(id, _)| !spotify_log.soundeo_track_ids.contains_key(*id))
+            .filter(|(id, _)| {
+                !spotify_log.soundeo_track_ids.contains_key(*id)
+                    && !spotify_log.pairing_reviews.contains_key(*id)
+            })
             .map(|(id, track)| (id.clone(), track.clone()))
             .collect();
 
         if unpaired_tracks.is_empty() {
-            return Ok(());
+            return self.review_pending_pairings(soundeo_user).await;
         }
 
         println!(
@@ -250,7 +417,6 @@ impl SpotifyPlaylist {
         // --- Phase 1: Automatic Pairing Pass ---
         println!("\n--- Phase 1: Automatic Pairing Pass ---");
         let mut paired_count = 0;
-        let mut needs_manual_review: Vec<(String, SpotifyTrack)> = Vec::new();
 
         let unpaired_len = unpaired_tracks.len();
         for (i, (spotify_track_id, mut spotify_track)) in unpaired_tracks.into_iter().enumerate() {
@@ -274,8 +440,12 @@ impl SpotifyPlaylist {
                         )
                         .change_context(SpotifyError)?;
 
-                        if DjWizardLog::add_queued_track(soundeo_id, Priority::High)
-                            .change_context(SpotifyError)?
+                        if DjWizardLog::add_queued_track(
+                            soundeo_id,
+                            Priority::High,
+                            Some("spotify".to_string()),
+                        )
+                        .change_context(SpotifyError)?
                         {
                             println!(
                                 "    └─ Added to download queue with {} priority.",
@@ -285,17 +455,26 @@ impl SpotifyPlaylist {
                             println!("    └─ Already in download queue.");
                         }
                     }
-                    AutoPairResult::NoMatch | AutoPairResult::MultipleMatches(_) => {
+                    unpaired_result => {
                         println!("  └─ {} Needs manual review.", "…".yellow());
-                        needs_manual_review.push((spotify_track_id, spotify_track));
+                        let candidates = match unpaired_result {
+                            AutoPairResult::MultipleMatches(candidates) => candidates,
+                            _ => vec![],
+                        };
+                        DjWizardLog::update_spotify_to_soundeo_track(spotify_track_id, None)
+                            .change_context(SpotifyError)?;
+                        DjWizardLog::add_pairing_review(PairingReview::new(
+                            &spotify_track,
+                            candidates,
+                        ))
+                        .change_context(SpotifyError)?;
                     }
                 },
                 Err(_) => {
                     println!(
-                        "  └─ {} Error during search. Needs manual review.",
+                        "  └─ {} Error during search. It will be searched again next time.",
                         "✖".red(),
                     );
-                    needs_manual_review.push((spotify_track_id, spotify_track));
                 }
             }
         }
@@ -305,59 +484,57 @@ impl SpotifyPlaylist {
             paired_count.to_string().green()
         );
 
-        // --- Phase 2: Manual Pairing Pass ---
-        if needs_manual_review.is_empty() {
+        self.review_pending_pairings(soundeo_user).await
+    }
+
+    /// Reviews the tracks of the playlist waiting in the review queue, after asking
+    async fn review_pending_pairings(&self, soundeo_user: &SoundeoUser) -> SpotifyResult<()> {
+        let spotify_log = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        let pending: HashSet<String> = self
+            .tracks
+            .keys()
+            .filter(|id| spotify_log.pairing_reviews.contains_key(*id))
+            .cloned()
+            .collect();
+        if pending.is_empty() {
             println!("{}", "No tracks require manual review.".green());
             return Ok(());
         }
 
-        println!("\n--- Phase 2: Manual Pairing Pass ---");
+        println!("\n--- Phase 2: Manual Review ---");
         let confirm_manual = Dialoguer::confirm(
             format!(
                 "There are {} tracks that need manual review. Do you want to review them now?",
-                needs_manual_review.len().to_string().yellow()
+                pending.len().to_string().yellow()
             ),
             Some(true),
         )
         .change_context(SpotifyError)?;
 
         if !confirm_manual {
-            println!("Manual review skipped.");
+            println!("Manual review skipped, the tracks stay in the review queue.");
             return Ok(());
         }
 
-        for (spotify_track_id, spotify_track) in needs_manual_review {
-            println!(
-                "\nReviewing: {} by {}",
-                spotify_track.title.yellow(),
-                spotify_track.artists.yellow()
-            );
-            let soundeo_id_result = spotify_track.get_soundeo_track_id(soundeo_user).await;
-
-            match soundeo_id_result {
-                Ok(soundeo_id_option) => {
-                    DjWizardLog::update_spotify_to_soundeo_track(
-                        spotify_track_id,
-                        soundeo_id_option.clone(),
-                    )
-                    .change_context(SpotifyError)?;
-                    if let Some(id) = soundeo_id_option {
-                        DjWizardLog::add_queued_track(id, Priority::High)
-                            .change_context(SpotifyError)?;
-                    }
-                }
-                Err(_) => {
-                    println!(
-                        "  └─ {} Error reviewing track: {}",
-                        "✖".red(),
-                        spotify_track.title.cyan()
-                    );
-                }
-            }
-        }
+        review_pairings(Some(&pending), soundeo_user).await
+    }
 
-        println!("\n{}", "Manual pairing session complete.".green());
-        Ok(())
+    /// Tracks in Spotify order, the ones missing from `track_order` go last sorted by artist
+    pub fn ordered_tracks(&self) -> Vec<&SpotifyTrack> {
+        let mut ordered: Vec<&SpotifyTrack> = self
+            .track_order
+            .iter()
+            .filter_map(|track_id| self.tracks.get(track_id))
+            .collect();
+        let in_order: HashSet<&String> = self.track_order.iter().collect();
+        let mut unordered: Vec<&SpotifyTrack> = self
+            .tracks
+            .values()
+            .filter(|track| !in_order.contains(&track.spotify_track_id))
+            .collect();
+        unordered.sort_by(|a, b| (&a.artists, &a.title).cmp(&(&b.artists, &b.title)));
+        ordered.extend(unordered);
+        ordered
     }
 
     pub fn prompt_select_playlist(prompt_text: &str) -> SpotifyResult<Self> {
@@ -400,4 +577,57 @@ mod tests {
     //     assert!(!playlist.tracks.is_empty());
     //     assert!(!playlist.name.is_empty());
     // }
+
+    fn track(id: &str, artists: &str) -> SpotifyTrack {
+        SpotifyTrack::new(
+            format!("Title {}", id),
+            artists.to_string(),
+            id.to_string(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_record_changes_between_syncs() {
+        let mut playlist =
+            SpotifyPlaylist::new("https://open.spotify.com/playlist/liquid".to_string()).unwrap();
+        let previous_tracks: HashMap<String, SpotifyTrack> = [
+            track("kept", "Calibre"),
+            track("removed_b", "DRS"),
+            track("removed_a", "Bou"),
+        ]
+        .into_iter()
+        .map(|track| (track.spotify_track_id.clone(), track))
+        .collect();
+        for track in [
+            track("added_2", "Alix Perez"),
+            track("kept", "Calibre"),
+            track("added_1", "Zero T"),
+        ] {
+            playlist.track_order.push(track.spotify_track_id.clone());
+            playlist
+                .tracks
+                .insert(track.spotify_track_id.clone(), track);
+        }
+
+        let changes = playlist.record_changes(&previous_tracks, 100);
+
+        let summary: Vec<(PlaylistChangeKind, &str)> = changes
+            .iter()
+            .map(|change| (change.kind.clone(), change.spotify_track_id.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (PlaylistChangeKind::Added, "added_2"),
+                (PlaylistChangeKind::Added, "added_1"),
+                (PlaylistChangeKind::Removed, "removed_a"),
+                (PlaylistChangeKind::Removed, "removed_b"),
+            ]
+        );
+        assert_eq!(playlist.changes, changes);
+        assert!(playlist
+            .record_changes(&playlist.tracks.clone(), 200)
+            .is_empty());
+    }
 }
@@ -0,0 +1,512 @@
+use std::collections::{HashMap, HashSet};
+
+use colored::Colorize;
+use error_stack::ResultExt;
+use inflector::Inflector;
+use serde::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
+
+use crate::dialoguer::Dialoguer;
+use crate::log::{DjWizardLog, Priority};
+use crate::soundeo::track::SoundeoTrack;
+use crate::spotify::matching::score_candidate;
+use crate::spotify::track::SpotifyTrack;
+use crate::spotify::{Spotify, SpotifyCRUD, SpotifyError, SpotifyResult};
+use crate::user::SoundeoUser;
+
+/// A Soundeo track proposed for a Spotify track, with its matching score from 0 to 100
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ReviewCandidate {
+    pub soundeo_track_id: String,
+    pub title: String,
+    pub track_url: String,
+    pub score: u32,
+}
+
+impl ReviewCandidate {
+    /// Candidates of the scored Soundeo tracks, best first
+    pub fn from_scored(scored: &[(u32, &SoundeoTrack)]) -> Vec<Self> {
+        let mut candidates: Vec<Self> = scored
+            .iter()
+            .map(|(score, track)| Self {
+                soundeo_track_id: track.id.clone(),
+                title: track.title.clone(),
+                track_url: track.get_track_url(),
+                score: *score,
+            })
+            .collect();
+        candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.score));
+        candidates
+    }
+}
+
+/// A Spotify track that could not be paired automatically, waiting for the reviewer
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PairingReview {
+    pub spotify_track_id: String,
+    pub title: String,
+    pub artists: String,
+    /// Best scored first
+    pub candidates: Vec<ReviewCandidate>,
+    /// Soundeo tracks the reviewer rejected, never proposed again
+    #[serde(default)]
+    pub rejected: HashSet<String>,
+}
+
+/// What the reviewer decided for a Spotify track, remembered across syncs
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ReviewDecision {
+    Accepted(String),
+    NotOnSoundeo,
+}
+
+#[derive(Debug, Clone, strum_macros::Display, strum_macros::EnumIter)]
+enum ReviewAction {
+    RejectCandidates,
+    SearchSoundeoManually,
+    NotOnSoundeo,
+    SkipForNow,
+    StopReviewing,
+}
+
+enum ReviewStep {
+    Decided(ReviewDecision),
+    Skipped,
+    Stopped,
+}
+
+impl PairingReview {
+    pub fn new(spotify_track: &SpotifyTrack, candidates: Vec<ReviewCandidate>) -> Self {
+        Self {
+            spotify_track_id: spotify_track.spotify_track_id.clone(),
+            title: spotify_track.title.clone(),
+            artists: spotify_track.artists.clone(),
+            candidates,
+            rejected: HashSet::new(),
+        }
+    }
+
+    /// Candidates the reviewer did not reject
+    pub fn pending_candidates(&self) -> Vec<&ReviewCandidate> {
+        self.candidates
+            .iter()
+            .filter(|candidate| !self.rejected.contains(&candidate.soundeo_track_id))
+            .collect()
+    }
+
+    /// Adds the candidates of a new search, replacing the ones found again
+    pub fn merge_candidates(&mut self, candidates: Vec<ReviewCandidate>) {
+        for candidate in candidates {
+            self.candidates
+                .retain(|current| current.soundeo_track_id != candidate.soundeo_track_id);
+            self.candidates.push(candidate);
+        }
+        self.candidates
+            .sort_by_key(|candidate| std::cmp::Reverse(candidate.score));
+    }
+
+    /// The stored Spotify track, with its release date for the scores
+    fn spotify_track(&self, spotify: &Spotify) -> SpotifyTrack {
+        spotify
+            .playlists
+            .values()
+            .find_map(|playlist| playlist.tracks.get(&self.spotify_track_id))
+            .cloned()
+            .unwrap_or_else(|| {
+                SpotifyTrack::new(
+                    self.title.clone(),
+                    self.artists.clone(),
+                    self.spotify_track_id.clone(),
+                    None,
+                )
+            })
+    }
+}
+
+/// Reviews of the Spotify tracks stored without a match before the review queue existed
+pub fn untracked_reviews(spotify: &Spotify) -> Vec<PairingReview> {
+    let mut seen = HashSet::new();
+    spotify
+        .playlists
+        .values()
+        .flat_map(|playlist| playlist.tracks.values())
+        .filter(|track| {
+            matches!(
+                spotify.soundeo_track_ids.get(&track.spotify_track_id),
+                Some(None)
+            ) && !spotify
+                .review_decisions
+                .contains_key(&track.spotify_track_id)
+                && !spotify
+                    .pairing_reviews
+                    .contains_key(&track.spotify_track_id)
+                && seen.insert(track.spotify_track_id.clone())
+        })
+        .map(|track| PairingReview::new(track, vec![]))
+        .collect()
+}
+
+/// Moves the search results of the old multiple matches cache to the review queue, scored
+/// against the Spotify track. `tracks_info` gives the full info of the results already stored.
+/// Tracks already paired or decided on, and the ones no longer in a playlist, are dropped
+pub fn migrate_multiple_matches_cache(
+    spotify: &mut Spotify,
+    tracks_info: &HashMap<String, SoundeoTrack>,
+) {
+    let cache = std::mem::take(&mut spotify.multiple_matches_cache);
+    for (spotify_track_id, results) in cache {
+        let is_paired = matches!(
+            spotify.soundeo_track_ids.get(&spotify_track_id),
+            Some(Some(_))
+        );
+        if is_paired || spotify.review_decisions.contains_key(&spotify_track_id) {
+            continue;
+        }
+        let Some(spotify_track) = spotify
+            .playlists
+            .values()
+            .find_map(|playlist| playlist.tracks.get(&spotify_track_id))
+            .cloned()
+        else {
+            log::warn!(
+                "Dropping the cached matches of {}, it is in no playlist",
+                spotify_track_id
+            );
+            continue;
+        };
+        let soundeo_tracks: Vec<SoundeoTrack> = results
+            .into_iter()
+            .map(|result| {
+                tracks_info.get(&result.value).cloned().unwrap_or_else(|| {
+                    let mut soundeo_track = SoundeoTrack::new(result.value);
+                    soundeo_track.title = result.label;
+                    soundeo_track
+                })
+            })
+            .collect();
+        let scored: Vec<(u32, &SoundeoTrack)> = soundeo_tracks
+            .iter()
+            .map(|soundeo_track| {
+                (
+                    score_candidate(&spotify_track, soundeo_track),
+                    soundeo_track,
+                )
+            })
+            .collect();
+        let candidates = ReviewCandidate::from_scored(&scored);
+        match spotify.pairing_reviews.get_mut(&spotify_track_id) {
+            Some(review) => review.merge_candidates(candidates),
+            None => {
+                spotify.pairing_reviews.insert(
+                    spotify_track_id,
+                    PairingReview::new(&spotify_track, candidates),
+                );
+            }
+        }
+    }
+}
+
+/// Goes through the pending reviews, only the ones of `spotify_track_ids` when given.
+/// Accepted tracks are paired and queued with High priority
+pub async fn review_pairings(
+    spotify_track_ids: Option<&HashSet<String>>,
+    soundeo_user: &SoundeoUser,
+) -> SpotifyResult<()> {
+    if spotify_track_ids.is_none() {
+        let spotify = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        for review in untracked_reviews(&spotify) {
+            DjWizardLog::add_pairing_review(review).change_context(SpotifyError)?;
+        }
+    }
+    let spotify = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+    let mut reviews: Vec<PairingReview> = spotify
+        .pairing_reviews
+        .values()
+        .filter(|review| {
+            spotify_track_ids.map_or(true, |ids| ids.contains(&review.spotify_track_id))
+        })
+        .cloned()
+        .collect();
+    if reviews.is_empty() {
+        println!("{}", "No tracks are waiting for review.".green());
+        return Ok(());
+    }
+    reviews.sort_by(|a, b| (&a.artists, &a.title).cmp(&(&b.artists, &b.title)));
+
+    let total = reviews.len();
+    let mut accepted = 0;
+    let mut not_on_soundeo = 0;
+    for (index, mut review) in reviews.into_iter().enumerate() {
+        println!(
+            "\nReviewing {}/{}: {} by {} - {}",
+            (index + 1).to_string().cyan(),
+            total.to_string().cyan(),
+            review.title.yellow(),
+            review.artists.yellow(),
+            review.spotify_track(&spotify).get_track_url()
+        );
+        match review_track(&mut review, &spotify, soundeo_user).await? {
+            ReviewStep::Decided(decision) => {
+                DjWizardLog::resolve_pairing_review(
+                    review.spotify_track_id.clone(),
+                    decision.clone(),
+                )
+                .change_context(SpotifyError)?;
+                match decision {
+                    ReviewDecision::Accepted(soundeo_id) => {
+                        accepted += 1;
+                        if DjWizardLog::add_queued_track(
+                            soundeo_id,
+                            Priority::High,
+                            Some("spotify".to_string()),
+                        )
+                        .change_context(SpotifyError)?
+                        {
+                            println!(
+                                "  └─ {} Paired and added to the queue with High priority.",
+                                "✔".green()
+                            );
+                        } else {
+                            println!("  └─ {} Paired, already in the queue.", "✔".green());
+                        }
+                    }
+                    ReviewDecision::NotOnSoundeo => {
+                        not_on_soundeo += 1;
+                        println!("  └─ Marked as not on Soundeo.");
+                    }
+                }
+            }
+            ReviewStep::Skipped => {}
+            ReviewStep::Stopped => break,
+        }
+    }
+
+    println!(
+        "\nReview complete: {} paired, {} not on Soundeo, {} still waiting.",
+        accepted.to_string().green(),
+        not_on_soundeo.to_string().yellow(),
+        (total - accepted - not_on_soundeo).to_string().cyan()
+    );
+    Ok(())
+}
+
+async fn review_track(
+    review: &mut PairingReview,
+    spotify: &Spotify,
+    soundeo_user: &SoundeoUser,
+) -> SpotifyResult<ReviewStep> {
+    loop {
+        let candidates: Vec<ReviewCandidate> =
+            review.pending_candidates().into_iter().cloned().collect();
+        let actions: Vec<ReviewAction> = ReviewAction::iter()
+            .filter(|action| {
+                !matches!(action, ReviewAction::RejectCandidates) || !candidates.is_empty()
+            })
+            .collect();
+        let mut options: Vec<String> = candidates
+            .iter()
+            .map(|candidate| {
+                format!(
+                    "Accept {} (score {}) - {}",
+                    candidate.title, candidate.score, candidate.track_url
+                )
+            })
+            .collect();
+        options.extend(
+            actions
+                .iter()
+                .map(|action| action.to_string().to_sentence_case()),
+        );
+        let selection = Dialoguer::select(
+            "What do you want to do with this track?".to_string(),
+            options,
+            Some(0),
+        )
+        .change_context(SpotifyError)?;
+        if selection < candidates.len() {
+            return Ok(ReviewStep::Decided(ReviewDecision::Accepted(
+                candidates[selection].soundeo_track_id.clone(),
+            )));
+        }
+
+        match actions[selection - candidates.len()] {
+            ReviewAction::RejectCandidates => {
+                let titles: Vec<String> = candidates
+                    .iter()
+                    .map(|candidate| format!("{} (score {})", candidate.title, candidate.score))
+                    .collect();
+                let selections = Dialoguer::multiselect(
+                    "Select the candidates to reject (spacebar to toggle, enter to confirm):"
+                        .to_string(),
+                    titles,
+                    None,
+                    false,
+                )
+                .change_context(SpotifyError)?;
+                let rejected: Vec<String> = selections
+                    .into_iter()
+                    .map(|index| candidates[index].soundeo_track_id.clone())
+                    .collect();
+                review.rejected.extend(rejected.iter().cloned());
+                DjWizardLog::reject_review_candidates(review.spotify_track_id.clone(), rejected)
+                    .change_context(SpotifyError)?;
+            }
+            ReviewAction::SearchSoundeoManually => {
+                let term = Dialoguer::input("Enter the search term:".to_string())
+                    .change_context(SpotifyError)?;
+                if term.trim().is_empty() {
+                    continue;
+                }
+                let found = review
+                    .spotify_track(spotify)
+                    .search_review_candidates(term, soundeo_user)
+                    .await?;
+                if found.is_empty() {
+                    println!("{}", "No downloadable tracks found.".yellow());
+                    continue;
+                }
+                review.merge_candidates(found);
+                DjWizardLog::add_pairing_review(review.clone()).change_context(SpotifyError)?;
+            }
+            ReviewAction::NotOnSoundeo => {
+                return Ok(ReviewStep::Decided(ReviewDecision::NotOnSoundeo))
+            }
+            ReviewAction::SkipForNow => return Ok(ReviewStep::Skipped),
+            ReviewAction::StopReviewing => return Ok(ReviewStep::Stopped),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::soundeo::search_bar::SoundeoSearchBarResult;
+
+    fn candidate(id: &str, score: u32) -> ReviewCandidate {
+        ReviewCandidate {
+            soundeo_track_id: id.to_string(),
+            title: format!("Calibre - Mr Majestic ({})", id),
+            track_url: format!("https://www.soundeo.com/track/{}", id),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_rejected_candidates_are_not_proposed_again() {
+        let spotify_track = SpotifyTrack::new(
+            "Mr Majestic".to_string(),
+            "Calibre".to_string(),
+            "spotify_id".to_string(),
+            None,
+        );
+        let mut review =
+            PairingReview::new(&spotify_track, vec![candidate("1", 70), candidate("2", 60)]);
+        review.rejected.insert("1".to_string());
+
+        review.merge_candidates(vec![candidate("1", 75), candidate("3", 65)]);
+
+        let pending: Vec<&str> = review
+            .pending_candidates()
+            .iter()
+            .map(|candidate| candidate.soundeo_track_id.as_str())
+            .collect();
+        assert_eq!(pending, vec!["3", "2"]);
+        assert_eq!(review.candidates.len(), 3);
+
+        let mut spotify = Spotify::new();
+        let mut playlist = crate::spotify::playlist::SpotifyPlaylist::new(
+            "https://open.spotify.com/playlist/liquid".to_string(),
+        )
+        .unwrap();
+        for id in ["no_match", "decided", "in_review", "unpaired"] {
+            let mut track = spotify_track.clone();
+            track.spotify_track_id = id.to_string();
+            playlist.tracks.insert(id.to_string(), track);
+        }
+        spotify
+            .playlists
+            .insert(playlist.spotify_playlist_id.clone(), playlist);
+        for id in ["no_match", "decided", "in_review"] {
+            spotify.soundeo_track_ids.insert(id.to_string(), None);
+        }
+        spotify
+            .review_decisions
+            .insert("decided".to_string(), ReviewDecision::NotOnSoundeo);
+        spotify
+            .pairing_reviews
+            .insert("in_review".to_string(), review);
+
+        let untracked: Vec<String> = untracked_reviews(&spotify)
+            .into_iter()
+            .map(|review| review.spotify_track_id)
+            .collect();
+        assert_eq!(untracked, vec!["no_match".to_string()]);
+    }
+
+    #[test]
+    fn test_multiple_matches_cache_becomes_reviews() {
+        let mut spotify = Spotify::new();
+        let mut playlist = crate::spotify::playlist::SpotifyPlaylist::new(
+            "https://open.spotify.com/playlist/liquid".to_string(),
+        )
+        .unwrap();
+        for id in ["cached", "paired", "decided"] {
+            let track = SpotifyTrack::new(
+                "Mr Majestic".to_string(),
+                "Calibre".to_string(),
+                id.to_string(),
+                None,
+            );
+            playlist.tracks.insert(id.to_string(), track);
+        }
+        spotify
+            .playlists
+            .insert(playlist.spotify_playlist_id.clone(), playlist);
+        spotify
+            .soundeo_track_ids
+            .insert("paired".to_string(), Some("1".to_string()));
+        spotify
+            .review_decisions
+            .insert("decided".to_string(), ReviewDecision::NotOnSoundeo);
+        let results = vec![
+            SoundeoSearchBarResult {
+                label: "Calibre - Mr Majestic (Original Mix)".to_string(),
+                category: "Tracks".to_string(),
+                value: "1".to_string(),
+            },
+            SoundeoSearchBarResult {
+                label: "Calibre - Drop It Down (Original Mix)".to_string(),
+                category: "Tracks".to_string(),
+                value: "2".to_string(),
+            },
+        ];
+        for id in ["cached", "paired", "decided", "removed"] {
+            spotify
+                .multiple_matches_cache
+                .insert(id.to_string(), results.clone());
+        }
+        let mut stored_track = SoundeoTrack::new("2".to_string());
+        stored_track.title = "Calibre - Drop It Down (Original Mix)".to_string();
+        stored_track.track_url = "track/calibre-drop-it-down".to_string();
+        let tracks_info = HashMap::from([("2".to_string(), stored_track)]);
+
+        migrate_multiple_matches_cache(&mut spotify, &tracks_info);
+
+        assert!(spotify.multiple_matches_cache.is_empty());
+        assert_eq!(
+            spotify.pairing_reviews.keys().collect::<Vec<_>>(),
+            vec!["cached"]
+        );
+        let candidates = &spotify.pairing_reviews["cached"].candidates;
+        let candidate_ids: Vec<&str> = candidates
+            .iter()
+            .map(|candidate| candidate.soundeo_track_id.as_str())
+            .collect();
+        assert_eq!(candidate_ids, vec!["1", "2"]);
+        assert!(candidates[0].score > candidates[1].score);
+        assert_eq!(
+            candidates[1].track_url,
+            "https://www.soundeo.com/track/calibre-drop-it-down"
+        );
+    }
+}
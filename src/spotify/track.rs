@@ -1,10 +1,12 @@
-use colored::Colorize;
 use error_stack::ResultExt;
 use serde::{Deserialize, Serialize};
 
-use crate::dialoguer::Dialoguer;
+use crate::log::DjWizardLog;
+use crate::normalize::search_terms;
 use crate::soundeo::search_bar::{SoundeoSearchBar, SoundeoSearchBarResult};
 use crate::soundeo::track::SoundeoTrack;
+use crate::spotify::matching::{best_candidate, paired_by_isrc, score_candidate};
+use crate::spotify::review::{ReviewCandidate, ReviewDecision};
 use crate::spotify::{SpotifyError, SpotifyResult};
 use crate::user::SoundeoUser;
 
@@ -12,7 +14,8 @@ use crate::user::SoundeoUser;
 pub enum AutoPairResult {
     Paired(String), // Contains the Soundeo track ID
     NoMatch,
-    MultipleMatches(Vec<SoundeoSearchBarResult>),
+    /// Candidates for the review queue, best scored first
+    MultipleMatches(Vec<ReviewCandidate>),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,137 +23,103 @@ pub struct SpotifyTrack {
     pub title: String,
     pub artists: String,
     pub spotify_track_id: String,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub isrc: Option<String>,
+    #[serde(default)]
+    pub album: Option<String>,
+    /// As returned by Spotify: "2023-05-12", "2023-05" or "2023"
+    #[serde(default)]
+    pub release_date: Option<String>,
 }
 
 impl SpotifyTrack {
-    pub fn new(title: String, artists: String, spotify_track_id: String) -> Self {
+    pub fn new(
+        title: String,
+        artists: String,
+        spotify_track_id: String,
+        duration_ms: Option<u64>,
+    ) -> Self {
         Self {
             title,
             artists,
             spotify_track_id,
+            duration_ms,
+            isrc: None,
+            album: None,
+            release_date: None,
         }
     }
 
+    /// Uses the decision of the reviewer when there is one. Otherwise pairs the track when
+    /// another Spotify track of the same recording (same ISRC) is paired, or when the best
+    /// scored Soundeo candidate reaches the auto pairing score. Rejected candidates are ignored
     pub async fn find_single_soundeo_match(
         &mut self,
         soundeo_user: &SoundeoUser,
     ) -> SpotifyResult<AutoPairResult> {
-        let (downloadable_tracks, all_search_results) =
-            find_downloadable_soundeo_tracks(self, soundeo_user).await?;
-
-        if downloadable_tracks.len() == 1 {
-            // Exactly one match, perfect for auto-pairing.
-            let (search_result, _track_info) = &downloadable_tracks[0];
-            Ok(AutoPairResult::Paired(search_result.value.clone()))
-        } else if downloadable_tracks.is_empty() {
-            Ok(AutoPairResult::NoMatch)
-        } else {
-            // Zero or more than one match, requires manual intervention.
-            Ok(AutoPairResult::MultipleMatches(all_search_results))
-        }
-    }
-
-    pub async fn get_soundeo_track_id(
-        &self,
-        soundeo_user: &SoundeoUser,
-    ) -> SpotifyResult<Option<String>> {
-        let (downloadable_tracks, _) = find_downloadable_soundeo_tracks(self, soundeo_user).await?;
-
-        if downloadable_tracks.is_empty() {
-            println!(
-                "Tracks not found for song {} by {}: {}",
-                self.title.clone().red(),
-                self.artists.clone().red(),
-                self.get_track_url()
-            );
-            return Ok(None); // No downloadable tracks found at all.
-        }
-
-        // --- Automatic selection logic ---
-
-        // Priority 1: Auto-select "(Extended Mix)" if available.
-        if let Some((result, track_info)) = downloadable_tracks
-            .iter()
-            .find(|(r, _)| r.label.contains("(Extended Mix)"))
-        {
-            println!(
-                "  └─ {} Automatically selected 'Extended Mix' version: {}",
-                "✔".green(),
-                track_info.title.cyan()
-            );
-            return Ok(Some(result.value.clone()));
+        let spotify = DjWizardLog::get_spotify().change_context(SpotifyError)?;
+        match spotify.review_decisions.get(&self.spotify_track_id) {
+            Some(ReviewDecision::Accepted(soundeo_id)) => {
+                return Ok(AutoPairResult::Paired(soundeo_id.clone()))
+            }
+            Some(ReviewDecision::NotOnSoundeo) => return Ok(AutoPairResult::NoMatch),
+            None => {}
         }
-
-        // Priority 2: Auto-select "(Original Mix)" if available.
-        if let Some((result, track_info)) = downloadable_tracks
-            .iter()
-            .find(|(r, _)| r.label.contains("(Original Mix)"))
-        {
-            println!(
-                "  └─ {} Automatically selected 'Original Mix' version: {}",
-                "✔".green(),
-                track_info.title.cyan()
-            );
-            return Ok(Some(result.value.clone()));
+        if let Some(soundeo_id) = paired_by_isrc(&spotify, self) {
+            return Ok(AutoPairResult::Paired(soundeo_id));
         }
 
-        // Check for multiple matches with identical names to auto-select.
-        if downloadable_tracks.len() > 1 {
-            let first_track_title = &downloadable_tracks[0].1.title;
-            let all_same_name = downloadable_tracks
-                .iter()
-                .all(|(_, track_info)| &track_info.title == first_track_title);
+        let rejected = spotify
+            .pairing_reviews
+            .get(&self.spotify_track_id)
+            .map(|review| review.rejected.clone())
+            .unwrap_or_default();
+        let downloadable_tracks: Vec<SoundeoTrack> =
+            find_downloadable_soundeo_tracks(self, soundeo_user)
+                .await?
+                .into_iter()
+                .filter(|track| !rejected.contains(&track.id))
+                .collect();
 
-            if all_same_name {
-                let chosen_track_info = &downloadable_tracks[0].1;
-                println!(
-                    "  └─ {} Automatically selected a match as all options had the same name: {} - {}",
-                    "✔".green(),
-                    chosen_track_info.title.cyan(),
-                    chosen_track_info.get_track_url().cyan()
-                );
-                // Since all are the same, we can just return the ID of the first one.
-                return Ok(Some(chosen_track_info.id.clone()));
-            }
+        if downloadable_tracks.is_empty() {
+            return Ok(AutoPairResult::NoMatch);
         }
-
-        let mut titles: Vec<String> = downloadable_tracks
-            .iter()
-            .map(|(_, info)| format!("{} - {}", info.title, info.get_track_url()))
-            .collect();
-
-        if titles.len() == 1 {
-            let track_data = format!(
-                "{} by {}: {}",
-                self.title.clone().cyan(),
-                self.artists.clone().cyan(),
-                self.get_track_url()
-            );
-            println!("Track found for {} \n {}", track_data, titles[0]);
-            return Ok(Some(downloadable_tracks[0].0.value.clone()));
+        let scored = self.score_candidates(&downloadable_tracks);
+        match best_candidate(&scored) {
+            Some(position) => Ok(AutoPairResult::Paired(scored[position].1.id.clone())),
+            // No candidate is good enough on its own, requires manual review.
+            None => Ok(AutoPairResult::MultipleMatches(
+                ReviewCandidate::from_scored(&scored),
+            )),
         }
+    }
 
-        titles.push("Skip this track".purple().to_string());
-
-        let prompt_text = format!(
-            "Select the correct option for {} by {}: {}",
-            self.title.clone().cyan(),
-            self.artists.clone().cyan(),
-            self.get_track_url()
-        );
-
-        let selection =
-            Dialoguer::select(prompt_text, titles.clone(), None).change_context(SpotifyError)?;
-
-        if selection == titles.len() - 1 {
-            return Ok(None);
-        }
-        let search_result = downloadable_tracks[selection].0.clone();
-        Ok(Some(search_result.value))
+    /// Downloadable Soundeo tracks found with a search term, scored against this track
+    pub async fn search_review_candidates(
+        &self,
+        term: String,
+        soundeo_user: &SoundeoUser,
+    ) -> SpotifyResult<Vec<ReviewCandidate>> {
+        let search_results = SoundeoSearchBar::Tracks
+            .search_term(term, soundeo_user)
+            .await
+            .change_context(SpotifyError)?;
+        let downloadable_tracks = downloadable_tracks(&search_results, soundeo_user).await?;
+        Ok(ReviewCandidate::from_scored(
+            &self.score_candidates(&downloadable_tracks),
+        ))
     }
 
-    pub fn get_track_search_term(&self) -> String {
-        format!("{} - {}", self.artists, self.title)
+    fn score_candidates<'a>(
+        &self,
+        downloadable_tracks: &'a [SoundeoTrack],
+    ) -> Vec<(u32, &'a SoundeoTrack)> {
+        downloadable_tracks
+            .iter()
+            .map(|track_info| (score_candidate(self, track_info), track_info))
+            .collect()
     }
 
     pub fn get_track_url(&self) -> String {
@@ -159,25 +128,31 @@ impl SpotifyTrack {
 }
 
 /// Helper function to search Soundeo and filter for downloadable tracks.
+/// Tries the search terms of [`search_terms`] until one gets results.
 async fn find_downloadable_soundeo_tracks(
     spotify_track: &SpotifyTrack,
     soundeo_user: &SoundeoUser,
-) -> SpotifyResult<(
-    Vec<(SoundeoSearchBarResult, SoundeoTrack)>,
-    Vec<SoundeoSearchBarResult>,
-)> {
-    let term = spotify_track.get_track_search_term();
-    let search_results = SoundeoSearchBar::Tracks
-        .search_term(term, soundeo_user)
-        .await
-        .change_context(SpotifyError)?;
-
-    if search_results.is_empty() {
-        return Ok((vec![], vec![]));
+) -> SpotifyResult<Vec<SoundeoTrack>> {
+    // The fallback terms are only searched when the previous one had no results
+    let mut search_results = vec![];
+    for term in search_terms(&spotify_track.artists, &spotify_track.title) {
+        search_results = SoundeoSearchBar::Tracks
+            .search_term(term, soundeo_user)
+            .await
+            .change_context(SpotifyError)?;
+        if !search_results.is_empty() {
+            break;
+        }
     }
+    downloadable_tracks(&search_results, soundeo_user).await
+}
 
+async fn downloadable_tracks(
+    search_results: &[SoundeoSearchBarResult],
+    soundeo_user: &SoundeoUser,
+) -> SpotifyResult<Vec<SoundeoTrack>> {
     let mut downloadable_results = vec![];
-    for result in &search_results {
+    for result in search_results {
         let id = result.value.clone();
         let mut full_info = SoundeoTrack::new(id);
         full_info
@@ -185,9 +160,8 @@ async fn find_downloadable_soundeo_tracks(
             .await
             .change_context(SpotifyError)?;
         if full_info.downloadable {
-            downloadable_results.push((result.clone(), full_info));
+            downloadable_results.push(full_info);
         }
     }
-
-    Ok((downloadable_results, search_results))
+    Ok(downloadable_results)
 }
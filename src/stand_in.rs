@@ -0,0 +1,78 @@
+//! Local stand-in for the HTTP APIs the tests talk to, and the users logged in to them
+
+use std::sync::{Arc, Mutex};
+
+use tiny_http::{Header, Response, Server};
+
+use crate::oauth::{OAuthProvider, OAuthToken};
+use crate::user::User;
+
+/// A request received by the stand-in
+#[derive(Debug, Clone)]
+pub struct StandInRequest {
+    pub method: String,
+    pub url: String,
+    /// Value of the Authorization header, empty without one
+    pub authorization: String,
+    pub body: String,
+}
+
+/// Every request received, in order
+pub type Requests = Arc<Mutex<Vec<StandInRequest>>>;
+
+/// Serves each request with the status and JSON body `route` returns for it, and records it.
+/// `{api_url}` in a body is replaced by the url of the stand-in, e.g. for the next pages.
+/// Returns the url of the stand-in and the recorded requests
+pub fn start<F>(route: F) -> (String, Requests)
+where
+    F: Fn(&StandInRequest) -> (u16, String) + Send + 'static,
+{
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let api_url = format!(
+        "http://127.0.0.1:{}",
+        server.server_addr().to_ip().unwrap().port()
+    );
+    let requests: Requests = Arc::new(Mutex::new(vec![]));
+    let recorded = requests.clone();
+    let served_api_url = api_url.clone();
+    std::thread::spawn(move || {
+        for mut request in server.incoming_requests() {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).unwrap();
+            let stand_in_request = StandInRequest {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+                authorization: request
+                    .headers()
+                    .iter()
+                    .find(|header| header.field.equiv("Authorization"))
+                    .map(|header| header.value.to_string())
+                    .unwrap_or_default(),
+                body,
+            };
+            let (status, response_body) = route(&stand_in_request);
+            recorded.lock().unwrap().push(stand_in_request);
+            let response =
+                Response::from_string(response_body.replace("{api_url}", &served_api_url))
+                    .with_status_code(status)
+                    .with_header(Header::from_bytes("Content-Type", "application/json").unwrap());
+            request.respond(response).unwrap();
+        }
+    });
+    (api_url, requests)
+}
+
+/// A user logged in to Spotify with `token`
+pub fn user_with_token(token: OAuthToken) -> User {
+    let mut user = User::new();
+    user.oauth_tokens.insert(OAuthProvider::Spotify, token);
+    user
+}
+
+/// A user logged in to Spotify with a token that never expires
+pub fn user() -> User {
+    user_with_token(OAuthToken {
+        access_token: "token".to_string(),
+        ..OAuthToken::default()
+    })
+}
@@ -1,5 +1,6 @@
+use clap::Subcommand;
 use colored::Colorize;
-use error_stack::{FutureExt, IntoReport, Report, ResultExt};
+use error_stack::{IntoReport, Report, ResultExt};
 use inflector::Inflector;
 use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
@@ -7,26 +8,58 @@ use url::Url;
 
 use crate::dialoguer::Dialoguer;
 use crate::log::DjWizardLog;
+use crate::queue::commands::QueueCommands;
+use crate::queue::track_processor::TrackProcessor;
 use crate::soundeo::track::SoundeoTrack;
 use crate::soundeo::track_list::SoundeoTracksList;
 use crate::user::SoundeoUser;
 
-use super::{UrlListCRUD, UrlListError, UrlListResult};
+use super::{TrackedSource, TrackedSourceType, UrlListCRUD, UrlListError, UrlListResult};
 
-#[derive(Debug, Deserialize, Serialize, Clone, strum_macros::Display, strum_macros::EnumIter)]
+#[derive(
+    Debug,
+    Deserialize,
+    Serialize,
+    Clone,
+    PartialEq,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+    Subcommand,
+)]
 pub enum UrlListCommands {
+    /// Save a Soundeo url, e.g. a label or a search, to queue its new tracks on every sync.
     AddToUrlList,
+    /// Show the saved urls.
+    ViewUrlList,
+    /// Stop tracking a saved url.
+    RemoveFromUrlList,
+    /// Change the priority, format and folder of the tracks queued from a saved url.
+    EditUrlDefaults,
+    /// Download all the tracks of a Soundeo url.
     DownloadFromUrl,
+    /// Queue the new tracks of every saved url.
+    Sync,
 }
 
 impl UrlListCommands {
-    pub async fn execute() -> UrlListResult<()> {
-        let options = Self::get_options();
-        let selection = Dialoguer::select("What you want to do?".to_string(), options, None)
-            .change_context(UrlListError)?;
-        return match Self::get_selection(selection) {
+    pub async fn execute(command: Option<UrlListCommands>) -> UrlListResult<()> {
+        let command_to_run = match command {
+            Some(command) => command,
+            None => {
+                let options = Self::get_options();
+                let selection =
+                    Dialoguer::select("What you want to do?".to_string(), options, None)
+                        .change_context(UrlListError)?;
+                Self::get_selection(selection)
+            }
+        };
+        return match command_to_run {
             UrlListCommands::AddToUrlList => Self::add_to_url_list().await,
+            UrlListCommands::ViewUrlList => Self::view_url_list(),
+            UrlListCommands::RemoveFromUrlList => Self::remove_from_url_list(),
+            UrlListCommands::EditUrlDefaults => Self::edit_url_defaults(),
             UrlListCommands::DownloadFromUrl => Self::download_from_url().await,
+            UrlListCommands::Sync => Self::sync_url_list().await,
         };
     }
 
@@ -47,8 +80,31 @@ impl UrlListCommands {
         let soundeo_url = Url::parse(&url)
             .into_report()
             .change_context(UrlListError)?;
+
+        let source_types: Vec<TrackedSourceType> = TrackedSourceType::iter().collect();
+        let guessed_type = TrackedSourceType::from_url(&soundeo_url);
+        let type_selection = Dialoguer::select(
+            "What kind of list is it?".to_string(),
+            source_types.clone(),
+            source_types
+                .iter()
+                .position(|source_type| *source_type == guessed_type),
+        )
+        .change_context(UrlListError)?;
+        let name = Dialoguer::optional_input(
+            "Name of the url".to_string(),
+            TrackedSource::default_name(&soundeo_url),
+        )
+        .change_context(UrlListError)?;
+        let name = if name.is_empty() {
+            TrackedSource::default_name(&soundeo_url)
+        } else {
+            name
+        };
+
+        let tracked_source = TrackedSource::new(&soundeo_url, name, source_types[type_selection]);
         let successfully_inserted =
-            DjWizardLog::add_url_to_url_list(soundeo_url.clone()).change_context(UrlListError)?;
+            DjWizardLog::add_tracked_source(tracked_source).change_context(UrlListError)?;
         if successfully_inserted {
             println!(
                 "Url successfully inserted: {}",
@@ -60,26 +116,222 @@ impl UrlListCommands {
         Ok(())
     }
 
+    fn sorted_tracked_sources() -> UrlListResult<Vec<TrackedSource>> {
+        let mut tracked_sources: Vec<TrackedSource> = DjWizardLog::get_tracked_sources()
+            .change_context(UrlListError)?
+            .into_values()
+            .collect();
+        tracked_sources.sort_by_key(|tracked_source| tracked_source.name.to_lowercase());
+        Ok(tracked_sources)
+    }
+
+    fn view_url_list() -> UrlListResult<()> {
+        let tracked_sources = Self::sorted_tracked_sources()?;
+        if tracked_sources.is_empty() {
+            println!("{}", "The url list is empty!".yellow());
+            return Ok(());
+        }
+        for tracked_source in tracked_sources {
+            println!(
+                "{} ({}): {} | Priority: {:?} | Target: {} | Created: {} | Last checked: {} | Tracks seen: {}",
+                tracked_source.name.cyan(),
+                tracked_source.source_type,
+                tracked_source.url.yellow(),
+                tracked_source.priority,
+                tracked_source.download_target.describe(),
+                tracked_source.created_at,
+                tracked_source
+                    .last_checked_date
+                    .unwrap_or_else(|| "never".to_string()),
+                tracked_source.seen_track_ids.len()
+            );
+        }
+        Ok(())
+    }
+
+    fn remove_from_url_list() -> UrlListResult<()> {
+        let tracked_sources = Self::sorted_tracked_sources()?;
+        if tracked_sources.is_empty() {
+            println!("{}", "The url list is empty!".yellow());
+            return Ok(());
+        }
+        let options: Vec<String> = tracked_sources
+            .iter()
+            .map(|tracked_source| format!("{} ({})", tracked_source.name, tracked_source.url))
+            .collect();
+        let selection = Dialoguer::select("Select the url to remove".to_string(), options, None)
+            .change_context(UrlListError)?;
+        let tracked_source = &tracked_sources[selection];
+        DjWizardLog::remove_tracked_source(&tracked_source.url).change_context(UrlListError)?;
+        println!("Url removed: {}", tracked_source.url.green());
+        Ok(())
+    }
+
+    fn edit_url_defaults() -> UrlListResult<()> {
+        let tracked_sources = Self::sorted_tracked_sources()?;
+        if tracked_sources.is_empty() {
+            println!("{}", "The url list is empty!".yellow());
+            return Ok(());
+        }
+        let options: Vec<String> = tracked_sources
+            .iter()
+            .map(|tracked_source| {
+                format!(
+                    "{} ({:?}, {})",
+                    tracked_source.name,
+                    tracked_source.priority,
+                    tracked_source.download_target.describe()
+                )
+            })
+            .collect();
+        let selection = Dialoguer::select("Select the url to edit".to_string(), options, None)
+            .change_context(UrlListError)?;
+        let tracked_source = &tracked_sources[selection];
+
+        let priority = QueueCommands::prompt_for_priority(
+            format!("Priority for the tracks of {}", tracked_source.name),
+            tracked_source.priority,
+        )
+        .change_context(UrlListError)?;
+        let download_target =
+            QueueCommands::prompt_for_download_target(&tracked_source.download_target)
+                .change_context(UrlListError)?;
+        let description = download_target.describe();
+        DjWizardLog::update_tracked_source_defaults(&tracked_source.url, priority, download_target)
+            .change_context(UrlListError)?;
+        println!(
+            "{} now queues with {:?} priority as {}",
+            tracked_source.name.cyan(),
+            priority,
+            description.green()
+        );
+        Ok(())
+    }
+
+    /// Queues the new tracks of every saved url, also used by the queue menu
+    pub async fn sync_url_list() -> UrlListResult<()> {
+        let tracked_sources = Self::sorted_tracked_sources()?;
+        if tracked_sources.is_empty() {
+            println!("{}", "The url list is empty!".yellow());
+            return Ok(());
+        }
+
+        let mut soundeo_user = SoundeoUser::new().change_context(UrlListError)?;
+        soundeo_user
+            .login_and_update_user_info()
+            .await
+            .change_context(UrlListError)?;
+
+        let mut synced_sources = Vec::new();
+        let mut failed_sources = Vec::new();
+        for tracked_source in tracked_sources {
+            println!(
+                "\nSyncing {} ({})",
+                tracked_source.name.cyan(),
+                tracked_source.url
+            );
+            match Self::queue_new_tracks(&tracked_source, &soundeo_user).await {
+                Ok(summary) => synced_sources.push((tracked_source.name, summary)),
+                Err(error) => {
+                    println!(
+                        "{}",
+                        format!("Could not sync {}: {:?}", tracked_source.name, error).red()
+                    );
+                    failed_sources.push(tracked_source.name);
+                }
+            }
+        }
+
+        println!("\n{}", "Url sync summary".green());
+        for (name, (found, added, skipped)) in synced_sources {
+            println!(
+                "{}: {} new, {} queued, {} skipped",
+                name.cyan(),
+                found.to_string().cyan(),
+                added.to_string().green(),
+                skipped.to_string().yellow()
+            );
+        }
+        for name in failed_sources.iter() {
+            println!("{}: {}", name.cyan(), "failed".red());
+        }
+
+        if !failed_sources.is_empty() {
+            return Err(Report::new(UrlListError)
+                .attach_printable(format!("Could not sync {}", failed_sources.join(", "))));
+        }
+        Ok(())
+    }
+
+    /// Walks the pages of the url, queues the unseen tracks and returns how many were found,
+    /// queued and skipped
+    async fn queue_new_tracks(
+        tracked_source: &TrackedSource,
+        soundeo_user: &SoundeoUser,
+    ) -> UrlListResult<(usize, usize, usize)> {
+        let mut track_list = SoundeoTracksList::new(tracked_source.url.clone())
+            .change_context(UrlListError)?
+            .max_pages(tracked_source.sync_max_pages());
+        // Only lists sorted by release end their new releases on a page without new tracks
+        let sorted_by_release = tracked_source.source_type.is_sorted_by_release();
+        track_list
+            .get_tracks_while(soundeo_user, |page_tracks| {
+                !sorted_by_release
+                    || !tracked_source
+                        .unseen_tracks(page_tracks.iter().map(|track| &track.id))
+                        .is_empty()
+            })
+            .await
+            .change_context(UrlListError)?;
+        let new_track_ids =
+            tracked_source.unseen_tracks(track_list.tracks.iter().map(|track| &track.id));
+
+        let (added, skipped) = if new_track_ids.is_empty() {
+            (0, 0)
+        } else {
+            TrackProcessor::process_tracks_to_queue(
+                &new_track_ids,
+                soundeo_user,
+                tracked_source.priority,
+                &tracked_source.download_target,
+                Some(format!("url:{}", tracked_source.name)),
+                false,
+                &format!("from {}", tracked_source.name),
+            )
+            .await
+            .change_context(UrlListError)?
+        };
+        let found = new_track_ids.len();
+        DjWizardLog::mark_tracked_source_checked(
+            &tracked_source.url,
+            new_track_ids.into_iter().collect(),
+        )
+        .change_context(UrlListError)?;
+        Ok((found, added, skipped))
+    }
+
     async fn download_from_url() -> UrlListResult<()> {
         let prompt_text = format!("Soundeo url: ");
         let url = Dialoguer::input(prompt_text).change_context(UrlListError)?;
         let soundeo_url = Url::parse(&url)
             .into_report()
             .change_context(UrlListError)?;
+        let max_pages = QueueCommands::prompt_for_max_pages().change_context(UrlListError)?;
         let mut soundeo_user = SoundeoUser::new().change_context(UrlListError)?;
         soundeo_user
             .login_and_update_user_info()
             .await
             .change_context(UrlListError)?;
-        let mut track_list =
-            SoundeoTracksList::new(soundeo_url.to_string()).change_context(UrlListError)?;
+        let mut track_list = SoundeoTracksList::new(soundeo_url.to_string())
+            .change_context(UrlListError)?
+            .max_pages(max_pages);
         track_list
-            .get_tracks_id(&soundeo_user)
+            .get_tracks(&soundeo_user)
             .await
             .change_context(UrlListError)?;
         // Add all tracks to collection by
-        for (_, track_id) in track_list.track_ids.into_iter().enumerate() {
-            let mut track = SoundeoTrack::new(track_id);
+        for listed_track in track_list.tracks {
+            let mut track = SoundeoTrack::new(listed_track.id);
             track
                 .download_track(&mut soundeo_user, true, false)
                 .await
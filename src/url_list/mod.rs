@@ -1,10 +1,21 @@
 pub mod commands;
 
 use ::serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fmt;
+
+use chrono::Utc;
 use url::Url;
 
-use crate::log::DjWizardLogResult;
+use crate::log::{DjWizardLogResult, Priority};
+use crate::soundeo::track::DownloadTarget;
+
+/// The first sync of a source only reads its first page, so saving a label doesn't queue
+/// its whole catalogue
+pub const FIRST_SYNC_MAX_PAGES: u32 = 1;
+
+/// Charts and searches are not sorted by release, so every sync reads this many pages
+pub const UNSORTED_SYNC_MAX_PAGES: u32 = 5;
 
 #[derive(Debug)]
 pub struct UrlListError;
@@ -19,7 +30,208 @@ impl std::error::Error for UrlListError {}
 
 pub type UrlListResult<T> = error_stack::Result<T, UrlListError>;
 
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+)]
+pub enum TrackedSourceType {
+    Label,
+    Artist,
+    Chart,
+    Search,
+}
+
+impl TrackedSourceType {
+    /// Guesses the type from the Soundeo url, e.g. a label page or a list filtered by label
+    pub fn from_url(url: &Url) -> Self {
+        let path = url.path().to_lowercase();
+        let has_filter = |filter: &str| url.query_pairs().any(|(key, _)| key == filter);
+        if path.contains("label") || has_filter("labelFilter") {
+            TrackedSourceType::Label
+        } else if path.contains("artist") || has_filter("artistFilter") {
+            TrackedSourceType::Artist
+        } else if path.contains("chart") {
+            TrackedSourceType::Chart
+        } else {
+            TrackedSourceType::Search
+        }
+    }
+
+    /// Label and artist lists show the newest releases first
+    pub fn is_sorted_by_release(&self) -> bool {
+        matches!(self, TrackedSourceType::Label | TrackedSourceType::Artist)
+    }
+}
+
+/// A saved Soundeo url whose new tracks are queued on every sync
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TrackedSource {
+    pub url: String,
+    pub name: String,
+    pub source_type: TrackedSourceType,
+    pub created_at: String,
+    /// None until the first sync
+    #[serde(default)]
+    pub last_checked_date: Option<String>,
+    /// Tracks already found on the url, only the other ones are queued
+    #[serde(default)]
+    pub seen_track_ids: HashSet<String>,
+    /// Priority of the tracks queued from this url
+    #[serde(default = "TrackedSource::default_priority")]
+    pub priority: Priority,
+    /// Format and folder of the tracks queued from this url
+    #[serde(default)]
+    pub download_target: DownloadTarget,
+}
+
+impl TrackedSource {
+    pub fn new(url: &Url, name: String, source_type: TrackedSourceType) -> Self {
+        Self {
+            url: url.to_string(),
+            name,
+            source_type,
+            created_at: Utc::now().format("%Y-%m-%d").to_string(),
+            last_checked_date: None,
+            seen_track_ids: HashSet::new(),
+            priority: Self::default_priority(),
+            download_target: DownloadTarget::default(),
+        }
+    }
+
+    fn default_priority() -> Priority {
+        Priority::Normal
+    }
+
+    /// Tracks the urls saved before they were tracked sources, with the guessed name and type
+    pub fn from_saved_url(url: &str) -> Option<Self> {
+        let url = Url::parse(url).ok()?;
+        Some(Self::new(
+            &url,
+            Self::default_name(&url),
+            TrackedSourceType::from_url(&url),
+        ))
+    }
+
+    /// Last segment of the url path, e.g. "hospital-records" for a label page
+    pub fn default_name(url: &Url) -> String {
+        url.path_segments()
+            .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+            .map(|segment| segment.to_string())
+            .unwrap_or_else(|| url.to_string())
+    }
+
+    /// Pages read by the next sync, all of them until one without new tracks when None
+    pub fn sync_max_pages(&self) -> Option<u32> {
+        if self.last_checked_date.is_none() {
+            Some(FIRST_SYNC_MAX_PAGES)
+        } else if self.source_type.is_sorted_by_release() {
+            None
+        } else {
+            Some(UNSORTED_SYNC_MAX_PAGES)
+        }
+    }
+
+    pub fn unseen_tracks<'a>(
+        &self,
+        track_ids: impl IntoIterator<Item = &'a String>,
+    ) -> Vec<String> {
+        track_ids
+            .into_iter()
+            .filter(|track_id| !self.seen_track_ids.contains(*track_id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn mark_checked(&mut self, found_track_ids: impl IntoIterator<Item = String>) {
+        self.seen_track_ids.extend(found_track_ids);
+        self.last_checked_date = Some(Utc::now().format("%Y-%m-%d").to_string());
+    }
+}
+
 pub trait UrlListCRUD {
-    fn add_url_to_url_list(soundeo_url: Url) -> DjWizardLogResult<bool>;
-    fn remove_url_from_url_list(soundeo_url: String) -> DjWizardLogResult<bool>;
+    fn get_tracked_sources() -> DjWizardLogResult<HashMap<String, TrackedSource>>;
+    fn add_tracked_source(tracked_source: TrackedSource) -> DjWizardLogResult<bool>;
+    fn remove_tracked_source(url: &str) -> DjWizardLogResult<bool>;
+    /// Replaces the priority and download target of a saved url
+    fn update_tracked_source_defaults(
+        url: &str,
+        priority: Priority,
+        download_target: DownloadTarget,
+    ) -> DjWizardLogResult<()>;
+    /// Adds the tracks found on the last sync to the seen ones and updates the last checked date
+    fn mark_tracked_source_checked(
+        url: &str,
+        found_track_ids: HashSet<String>,
+    ) -> DjWizardLogResult<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_type_from_url() {
+        let label_url = Url::parse("https://soundeo.com/label/hospital-records").unwrap();
+        let filtered_url =
+            Url::parse("https://soundeo.com/list/tracks?availableFilter=1&artistFilter=41")
+                .unwrap();
+        let chart_url = Url::parse("https://soundeo.com/charts/top-100").unwrap();
+        let search_url = Url::parse("https://soundeo.com/search?q=liquid").unwrap();
+
+        assert_eq!(
+            TrackedSourceType::from_url(&label_url),
+            TrackedSourceType::Label
+        );
+        assert_eq!(
+            TrackedSourceType::from_url(&filtered_url),
+            TrackedSourceType::Artist
+        );
+        assert_eq!(
+            TrackedSourceType::from_url(&chart_url),
+            TrackedSourceType::Chart
+        );
+        assert_eq!(
+            TrackedSourceType::from_url(&search_url),
+            TrackedSourceType::Search
+        );
+        assert_eq!(TrackedSource::default_name(&label_url), "hospital-records");
+    }
+
+    #[test]
+    fn test_only_unseen_tracks_are_new() {
+        let mut tracked_source =
+            TrackedSource::from_saved_url("https://soundeo.com/label/hospital-records").unwrap();
+        tracked_source.mark_checked(vec!["1".to_string(), "2".to_string()]);
+
+        let found_track_ids = vec!["2".to_string(), "3".to_string()];
+
+        assert_eq!(
+            tracked_source.unseen_tracks(&found_track_ids),
+            vec!["3".to_string()]
+        );
+        assert!(tracked_source.last_checked_date.is_some());
+    }
+
+    #[test]
+    fn test_sync_max_pages() {
+        let mut label =
+            TrackedSource::from_saved_url("https://soundeo.com/label/hospital-records").unwrap();
+        let mut chart =
+            TrackedSource::from_saved_url("https://soundeo.com/charts/top-100").unwrap();
+
+        assert_eq!(label.sync_max_pages(), Some(FIRST_SYNC_MAX_PAGES));
+        assert_eq!(chart.sync_max_pages(), Some(FIRST_SYNC_MAX_PAGES));
+
+        label.mark_checked(vec![]);
+        chart.mark_checked(vec![]);
+        assert_eq!(label.sync_max_pages(), None);
+        assert_eq!(chart.sync_max_pages(), Some(UNSORTED_SYNC_MAX_PAGES));
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::path::Path;
 use std::{env, fmt, fs, string};
@@ -13,7 +14,11 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tokio::time::{sleep, Duration};
 
-use crate::config::AppConfig;
+use crate::history::RunHistory;
+use crate::oauth::{OAuthProvider, OAuthToken};
+use crate::output::OutputFormat;
+use crate::queue::scheduler::QueueScheduler;
+use crate::spotify::organizer::LinkMode;
 use crate::{DjWizardCommands, Suggestion};
 
 #[derive(Debug, Clone)]
@@ -41,10 +46,25 @@ pub struct User {
     pub download_path: String,
     #[serde(default)]
     pub ipfs: IPFSConfig,
+    /// Tokens of the services dj-wizard logs into
     #[serde(default)]
-    pub spotify_access_token: String,
+    pub oauth_tokens: HashMap<OAuthProvider, OAuthToken>,
+    /// Login callback ports, for providers registered with a port other than the default one
     #[serde(default)]
-    pub spotify_refresh_token: String,
+    pub oauth_callback_ports: HashMap<OAuthProvider, u16>,
+    /// Spotify tokens of the configs written before `oauth_tokens`, migrated when read
+    #[serde(default, skip_serializing)]
+    spotify_access_token: String,
+    #[serde(default, skip_serializing)]
+    spotify_refresh_token: String,
+    /// Main downloads granted on every Soundeo quota reset, used to plan the queue
+    #[serde(default = "User::default_daily_download_quota")]
+    pub daily_download_quota: u32,
+    #[serde(default)]
+    pub queue_scheduler: QueueScheduler,
+    /// How the playlist folders reference the downloaded files
+    #[serde(default)]
+    pub playlist_link_mode: LinkMode,
 }
 
 impl User {
@@ -58,11 +78,20 @@ impl User {
                 api_key_secret: "".to_string(),
                 last_ipfs_hash: "".to_string(),
             },
+            oauth_tokens: HashMap::new(),
+            oauth_callback_ports: HashMap::new(),
             spotify_access_token: "".to_string(),
             spotify_refresh_token: "".to_string(),
+            daily_download_quota: Self::default_daily_download_quota(),
+            queue_scheduler: QueueScheduler::default(),
+            playlist_link_mode: LinkMode::default(),
         }
     }
 
+    fn default_daily_download_quota() -> u32 {
+        150
+    }
+
     pub fn read_config_file(&mut self) -> SoundeoUserResult<()> {
         let soundeo_bot_config_path =
             User::get_config_file_path().attach_printable("Failed to get the config file path")?;
@@ -80,7 +109,7 @@ impl User {
                 soundeo_bot_config_path
             ))
             .change_context(SoundeoUserError)?;
-        let config: User = serde_json::from_str(&config_content)
+        let mut config: User = serde_json::from_str(&config_content)
             .into_report()
             .attach_printable("Failed to parse the config file. Ensure it is valid JSON.")
             .change_context(SoundeoUserError)?;
@@ -94,10 +123,27 @@ impl User {
                 soundeo_bot_config_path
             )));
         }
+        config.migrate_legacy_spotify_token();
         self.clone_from(&config);
         Ok(())
     }
 
+    fn migrate_legacy_spotify_token(&mut self) {
+        let access_token = std::mem::take(&mut self.spotify_access_token);
+        let refresh_token = std::mem::take(&mut self.spotify_refresh_token);
+        if access_token.is_empty() || self.oauth_tokens.contains_key(&OAuthProvider::Spotify) {
+            return;
+        }
+        self.oauth_tokens.insert(
+            OAuthProvider::Spotify,
+            OAuthToken {
+                access_token,
+                refresh_token,
+                ..OAuthToken::default()
+            },
+        );
+    }
+
     pub fn create_new_config_file(&self) -> SoundeoUserResult<()> {
         let serialized = serde_json::to_string_pretty(self)
             .into_report()
@@ -146,52 +192,6 @@ impl User {
             .change_context(SoundeoUserError)?;
         Ok(())
     }
-
-    pub async fn refresh_spotify_token(&mut self) -> SoundeoUserResult<()> {
-        println!("Spotify access token expired. Attempting to refresh...");
-
-        if self.spotify_refresh_token.is_empty() {
-            return Err(Report::new(SoundeoUserError)
-                .attach_printable("No refresh token available. Please log in again."));
-        }
-
-        let client_id = AppConfig::SPOTIFY_CLIENT_ID.to_string();
-        let client = reqwest::Client::new();
-        let params = [
-            ("grant_type", "refresh_token".to_string()),
-            ("refresh_token", self.spotify_refresh_token.clone()),
-            ("client_id", client_id),
-        ];
-
-        let token_response: serde_json::Value = client
-            .post("https://accounts.spotify.com/api/token")
-            .form(&params)
-            .send()
-            .await
-            .into_report()
-            .change_context(SoundeoUserError)?
-            .json()
-            .await
-            .into_report()
-            .change_context(SoundeoUserError)?;
-
-        if let Some(new_access_token) = token_response["access_token"].as_str() {
-            self.spotify_access_token = new_access_token.to_string();
-
-            if let Some(new_refresh_token) = token_response["refresh_token"].as_str() {
-                self.spotify_refresh_token = new_refresh_token.to_string();
-            }
-
-            self.save_config_file()?;
-            println!("{}", "Spotify token refreshed successfully.".green());
-            Ok(())
-        } else {
-            Err(Report::new(SoundeoUserError).attach_printable(format!(
-                "Failed to refresh Spotify token. Response: {:?}",
-                token_response
-            )))
-        }
-    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -399,13 +399,14 @@ impl SoundeoUser {
         } else {
             0
         };
+        RunHistory::observe_remaining_credits(main_downloads + bonus_downloads);
 
         Ok((main_downloads, bonus_downloads))
     }
 
     pub async fn login_and_update_user_info(&mut self) -> SoundeoUserResult<()> {
         if self.cookie.is_empty() {
-            println!("Logging in with {}", self.name.clone().green());
+            OutputFormat::status(format!("Logging in with {}", self.name.clone().green()));
             self.get_cookie_from_browser()
                 .await
                 .attach_printable("Failed to retrieve cookies from the browser")?;
@@ -413,23 +414,21 @@ impl SoundeoUser {
         let mut logged_in = false;
         while !logged_in {
             let mut response = self.get_login_response().await;
-            while response.is_err() {
-                println!(
-                    "{}",
-                    colored::Colorize::red("Login response failed, retrying in 5 seconds")
-                );
+            while let Err(error) = &response {
+                log::warn!("Login response failed, retrying: {:?}", error);
+                OutputFormat::status(colored::Colorize::red(
+                    "Login response failed, retrying in 5 seconds",
+                ));
                 sleep(Duration::from_secs(5)).await;
                 response = self.get_login_response().await;
             }
             let response_unwrap = response.unwrap();
             let mut snd_data_result = self.get_snd_data(&response_unwrap).await;
-            while snd_data_result.is_err() {
-                println!(
-                    "{}",
-                    colored::Colorize::red(
-                        "Failed to retrieve 'snda[data]', retrying in 5 seconds"
-                    )
-                );
+            while let Err(error) = &snd_data_result {
+                log::warn!("Failed to retrieve 'snda[data]', retrying: {:?}", error);
+                OutputFormat::status(colored::Colorize::red(
+                    "Failed to retrieve 'snda[data]', retrying in 5 seconds",
+                ));
                 sleep(Duration::from_secs(5)).await;
                 snd_data_result = self.get_snd_data(&response_unwrap).await;
             }
@@ -477,6 +476,16 @@ impl SoundeoUser {
             "0".to_string()
         };
         self.remaining_time_to_reset = remaining_time;
+        let remaining_credits = remaining_downloads_vec
+            .iter()
+            .filter_map(|downloads| downloads.parse::<u32>().ok())
+            .sum();
+        RunHistory::observe_remaining_credits(remaining_credits);
+        log::debug!(
+            "{} credits left, reset in {}",
+            remaining_credits,
+            self.remaining_time_to_reset
+        );
         Ok(())
     }
 